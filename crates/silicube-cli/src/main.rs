@@ -2,11 +2,16 @@
 //!
 //! A command-line tool for running code in IOI isolate sandboxes.
 
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use silicube::{BoxPool, Config, EXAMPLE_CONFIG, ResourceLimits, Runner, prepare_cgroup};
+use clap::{Parser, Subcommand, ValueEnum};
+use silicube::isolate::MetaFile;
+use silicube::{
+    BatchSummary, BoxPool, ComparePolicy, Config, EXAMPLE_CONFIG, ExecutionResult, ExecutionStatus,
+    ResourceLimits, Runner, Verdict, prepare_cgroup,
+};
 use tracing::{Level, debug, info, warn};
 use tracing_subscriber::EnvFilter;
 
@@ -27,6 +32,10 @@ struct Cli {
     #[arg(short, long, global = true)]
     verbose: bool,
 
+    /// Log the first N bytes of program stdout/stderr at debug level
+    #[arg(long, global = true, default_value = "0", value_name = "N")]
+    log_output_preview: usize,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -84,6 +93,37 @@ enum Commands {
         /// Memory limit in KB
         #[arg(short, long)]
         memory_limit: Option<u64>,
+
+        /// Save stdout, stderr, and the parsed meta file (as JSON) under DIR
+        #[arg(long, value_name = "DIR")]
+        save_output: Option<PathBuf>,
+    },
+
+    /// Compile once and judge the program against a directory of test cases
+    Batch {
+        /// Source file to judge
+        #[arg(short, long, value_name = "FILE")]
+        source: PathBuf,
+
+        /// Language ID (e.g., cpp17, python3)
+        #[arg(short, long)]
+        language: String,
+
+        /// Directory of NAME.in / NAME.out test case pairs
+        #[arg(long, value_name = "DIR")]
+        tests: PathBuf,
+
+        /// How output is compared against the expected answer
+        #[arg(long, value_enum, default_value_t = CompareMode::Lines)]
+        compare: CompareMode,
+
+        /// Time limit in seconds
+        #[arg(short, long)]
+        time_limit: Option<f64>,
+
+        /// Memory limit in KB
+        #[arg(short, long)]
+        memory_limit: Option<u64>,
     },
 
     /// List available languages
@@ -93,6 +133,27 @@ enum Commands {
     ShowConfig,
 }
 
+/// Output comparison for the `batch` command
+#[derive(Clone, Copy, ValueEnum)]
+enum CompareMode {
+    /// Byte-for-byte equality
+    Exact,
+    /// Ignore trailing whitespace and trailing blank lines
+    Lines,
+    /// Compare whitespace-separated tokens
+    Tokens,
+}
+
+impl From<CompareMode> for ComparePolicy {
+    fn from(mode: CompareMode) -> Self {
+        match mode {
+            CompareMode::Exact => ComparePolicy::Exact,
+            CompareMode::Lines => ComparePolicy::IgnoreTrailingWhitespace,
+            CompareMode::Tokens => ComparePolicy::Tokens,
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -160,15 +221,39 @@ async fn main() -> Result<()> {
             input,
             time_limit,
             memory_limit,
+            save_output,
         } => {
+            let runner =
+                Runner::new(config.clone()).with_log_output_preview(cli.log_output_preview);
             run_execute(
-                &config,
+                &runner,
                 cli.box_id,
                 &source,
                 &language,
                 input.as_deref(),
-                time_limit,
-                memory_limit,
+                user_limits(time_limit, memory_limit).as_ref(),
+                save_output.as_deref(),
+            )
+            .await
+        }
+        Commands::Batch {
+            source,
+            language,
+            tests,
+            compare,
+            time_limit,
+            memory_limit,
+        } => {
+            let runner =
+                Runner::new(config.clone()).with_log_output_preview(cli.log_output_preview);
+            run_batch(
+                &runner,
+                cli.box_id,
+                &source,
+                &language,
+                &tests,
+                compare.into(),
+                user_limits(time_limit, memory_limit).as_ref(),
             )
             .await
         }
@@ -207,23 +292,10 @@ async fn run_compile(
     info!(language = %language.name, "compiling source");
 
     // Create sandbox
-    let pool = BoxPool::new(box_id, 1, config.isolate_binary(), config.cgroup);
+    let pool = BoxPool::new(box_id, 1, config.isolate_binary(), config.cgroup)
+        .with_init_clean_retry(config.init_clean_retry);
     let mut sandbox = pool.acquire().await.context("failed to acquire sandbox")?;
 
-    // Create limits (only include explicitly-specified values so they don't
-    // override per-language defaults)
-    let user_limits = ResourceLimits {
-        time_limit,
-        memory_limit,
-        wall_time_limit: None,
-        stack_limit: None,
-        max_processes: None,
-        max_output: None,
-        max_open_files: None,
-        extra_time: None,
-    };
-    let has_user_limits = time_limit.is_some() || memory_limit.is_some();
-
     // Compile
     let runner = Runner::new(config.clone());
     let result = runner
@@ -231,11 +303,7 @@ async fn run_compile(
             &sandbox,
             &source_content,
             language,
-            if has_user_limits {
-                Some(&user_limits)
-            } else {
-                None
-            },
+            user_limits(time_limit, memory_limit).as_ref(),
         )
         .await
         .context("compilation failed")?;
@@ -262,14 +330,15 @@ async fn run_compile(
 }
 
 async fn run_execute(
-    config: &Config,
+    runner: &Runner,
     box_id: u32,
     source: &PathBuf,
     language_id: &str,
-    input: Option<&std::path::Path>,
-    time_limit: Option<f64>,
-    memory_limit: Option<u64>,
+    input: Option<&Path>,
+    limits: Option<&ResourceLimits>,
+    save_output: Option<&Path>,
 ) -> Result<()> {
+    let config = runner.config();
     let language = config
         .get_language(language_id)
         .context("unknown language")?;
@@ -291,38 +360,18 @@ async fn run_execute(
     info!(language = %language.name, "running program");
 
     // Create sandbox
-    let pool = BoxPool::new(box_id, 1, config.isolate_binary(), config.cgroup);
+    let pool = BoxPool::new(box_id, 1, config.isolate_binary(), config.cgroup)
+        .with_init_clean_retry(config.init_clean_retry);
     let mut sandbox = pool.acquire().await.context("failed to acquire sandbox")?;
 
-    // Create limits (only include explicitly-specified values so they don't
-    // override per-language defaults)
-    let user_limits = ResourceLimits {
-        time_limit,
-        memory_limit,
-        wall_time_limit: None,
-        stack_limit: None,
-        max_processes: None,
-        max_output: None,
-        max_open_files: None,
-        extra_time: None,
-    };
-    let has_user_limits = time_limit.is_some() || memory_limit.is_some();
-    let limits_ref = if has_user_limits {
-        Some(&user_limits)
-    } else {
-        None
-    };
-
-    let runner = Runner::new(config.clone());
-
-    // Compile if needed
-    if language.is_compiled() {
-        info!("compiling source");
-        let compile_result = runner
-            .compile(&sandbox, &source_content, language, None)
-            .await
-            .context("compilation failed")?;
+    // Compile if needed, or write source for interpreted languages
+    info!("preparing source");
+    let compile_result = runner
+        .prepare(&sandbox, &source_content, language)
+        .await
+        .context("failed to prepare source")?;
 
+    if let Some(compile_result) = compile_result {
         if !compile_result.success {
             sandbox
                 .cleanup()
@@ -334,21 +383,26 @@ async fn run_execute(
         }
 
         debug!(time = compile_result.execution.time, "compilation complete");
-    } else {
-        // Write source for interpreted language
-        sandbox
-            .write_file(&language.source_name(), &source_content)
-            .await
-            .context("failed to write source to sandbox")?;
     }
 
     // Run
     info!("executing program");
     let result = runner
-        .run(&sandbox, input_data.as_deref(), language, limits_ref)
+        .run(&sandbox, input_data.as_deref(), language, limits)
         .await
         .context("execution failed")?;
 
+    if let Some(dir) = save_output {
+        let meta = sandbox
+            .read_file(&config.internal_file("meta.txt"))
+            .await
+            .context("failed to read meta file")?;
+        let meta = MetaFile::parse(&String::from_utf8_lossy(&meta));
+        save_run_output(dir, &result, &meta)
+            .await
+            .with_context(|| format!("failed to save output to {}", dir.display()))?;
+    }
+
     sandbox
         .cleanup()
         .await
@@ -375,6 +429,8 @@ async fn run_execute(
         memory = format_args!("{} KB", result.memory),
         exit_code = result.exit_code,
         signal = result.signal,
+        killed = result.killed,
+        cgroup = result.cgroup_used,
         "execution result"
     );
 
@@ -386,6 +442,207 @@ async fn run_execute(
     }
 }
 
+async fn run_batch(
+    runner: &Runner,
+    box_id: u32,
+    source: &Path,
+    language_id: &str,
+    tests: &Path,
+    policy: ComparePolicy,
+    limits: Option<&ResourceLimits>,
+) -> Result<()> {
+    let config = runner.config();
+    let language = config
+        .get_language(language_id)
+        .context("unknown language")?;
+
+    let source_content = tokio::fs::read(source)
+        .await
+        .context("failed to read source file")?;
+    let cases = find_test_cases(tests)
+        .await
+        .with_context(|| format!("failed to read test cases from {}", tests.display()))?;
+    if cases.is_empty() {
+        anyhow::bail!("no .in/.out test case pairs in '{}'", tests.display());
+    }
+
+    info!(language = %language.name, cases = cases.len(), "judging program");
+
+    let pool = BoxPool::new(box_id, 1, config.isolate_binary(), config.cgroup)
+        .with_init_clean_retry(config.init_clean_retry);
+    let mut sandbox = pool.acquire().await.context("failed to acquire sandbox")?;
+
+    let compile_result = runner
+        .prepare(&sandbox, &source_content, language)
+        .await
+        .context("failed to prepare source")?;
+    if let Some(compile_result) = compile_result
+        && !compile_result.success
+    {
+        sandbox
+            .cleanup()
+            .await
+            .context("failed to cleanup sandbox")?;
+        eprintln!("Compilation failed:");
+        eprintln!("{}", compile_result.output);
+        std::process::exit(1);
+    }
+
+    let mut results = Vec::with_capacity(cases.len());
+    for case in &cases {
+        let input = tokio::fs::read(&case.input)
+            .await
+            .with_context(|| format!("failed to read {}", case.input.display()))?;
+        let expected = tokio::fs::read(&case.output)
+            .await
+            .with_context(|| format!("failed to read {}", case.output.display()))?;
+        let (result, verdict) = runner
+            .run_and_judge(&sandbox, Some(&input), &expected, policy, language, limits)
+            .await
+            .with_context(|| format!("test case '{}' failed to run", case.name))?;
+        println!(
+            "{:<16} {:<4} {:>7.3}s {:>8} KB",
+            case.name,
+            verdict_label(&result, verdict),
+            result.time,
+            result.memory
+        );
+        results.push((result, verdict));
+    }
+
+    sandbox
+        .cleanup()
+        .await
+        .context("failed to cleanup sandbox")?;
+
+    let summary = Runner::summarize(&results);
+    println!("{}", summary_line(&summary));
+    if summary.all_accepted() {
+        Ok(())
+    } else {
+        std::process::exit(1);
+    }
+}
+
+/// A test case: `NAME.in` and its expected answer `NAME.out`
+#[derive(Debug, PartialEq)]
+struct TestCase {
+    name: String,
+    input: PathBuf,
+    output: PathBuf,
+}
+
+/// Find `*.in` files in `dir` that have a matching `*.out`, sorted by name
+///
+/// Inputs without an answer file are skipped with a warning.
+async fn find_test_cases(dir: &Path) -> Result<Vec<TestCase>> {
+    let mut cases = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let input = entry.path();
+        if input.extension().is_none_or(|ext| ext != "in") {
+            continue;
+        }
+        let output = input.with_extension("out");
+        let Some(name) = input.file_stem() else {
+            continue;
+        };
+        let name = name.to_string_lossy().into_owned();
+        if !output.is_file() {
+            warn!(case = %name, "skipping test case without a .out file");
+            continue;
+        }
+        cases.push(TestCase {
+            name,
+            input,
+            output,
+        });
+    }
+    cases.sort_by(|a, b| natural_key(&a.name).cmp(&natural_key(&b.name)));
+    Ok(cases)
+}
+
+/// Sort key putting `2` before `10` for numbered test cases
+fn natural_key(name: &str) -> (Option<u64>, &str) {
+    (name.parse().ok(), name)
+}
+
+/// Short verdict for one test case, with resource failures taking precedence
+fn verdict_label(result: &ExecutionResult, verdict: Verdict) -> &'static str {
+    if result.is_time_limit() {
+        "TLE"
+    } else if result.is_memory_limit() {
+        "MLE"
+    } else if result.is_output_limit() {
+        "OLE"
+    } else {
+        match (result.status, verdict) {
+            (ExecutionStatus::RuntimeError | ExecutionStatus::Signaled, _) => "RE",
+            (ExecutionStatus::Ok, Verdict::Accepted) => "AC",
+            (ExecutionStatus::Ok, Verdict::WrongAnswer) => "WA",
+            (ExecutionStatus::Ok, Verdict::PresentationError) => "PE",
+            _ => "IE",
+        }
+    }
+}
+
+fn summary_line(summary: &BatchSummary) -> String {
+    format!(
+        "{}/{} passed (WA {}, TLE {}, MLE {}, OLE {}, RE {}), max time {:.3}s, max memory {} KB",
+        summary.accepted,
+        summary.total,
+        summary.wrong,
+        summary.tle,
+        summary.mle,
+        summary.ole,
+        summary.re,
+        summary.max_time,
+        summary.max_memory
+    )
+}
+
+/// Build user limits from CLI flags
+///
+/// Only explicitly specified values are set so they don't override
+/// per-language defaults. Returns `None` if no limit flags were given.
+fn user_limits(time_limit: Option<f64>, memory_limit: Option<u64>) -> Option<ResourceLimits> {
+    if time_limit.is_none() && memory_limit.is_none() {
+        return None;
+    }
+    Some(ResourceLimits {
+        time_limit,
+        memory_limit,
+        wall_time_limit: None,
+        stack_limit: None,
+        max_processes: None,
+        max_output: None,
+        max_open_files: None,
+        extra_time: None,
+    })
+}
+
+/// Write a run's stdout, stderr, and meta entries (as JSON) into `dir`
+async fn save_run_output(dir: &Path, result: &ExecutionResult, meta: &MetaFile) -> Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(
+        dir.join("stdout.txt"),
+        result.stdout.as_deref().unwrap_or_default(),
+    )
+    .await?;
+    tokio::fs::write(
+        dir.join("stderr.txt"),
+        result.stderr.as_deref().unwrap_or_default(),
+    )
+    .await?;
+    let entries: BTreeMap<_, _> = meta.entries.iter().collect();
+    tokio::fs::write(
+        dir.join("meta.json"),
+        serde_json::to_string_pretty(&entries)?,
+    )
+    .await?;
+    Ok(())
+}
+
 fn list_languages(config: &Config) {
     println!("Available languages:\n");
 
@@ -436,3 +693,94 @@ async fn init_config(output: &PathBuf, force: bool) -> Result<()> {
     println!("Created configuration file at '{}'", output.display());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_limits_only_when_given() {
+        assert!(user_limits(None, None).is_none());
+
+        let limits = user_limits(Some(2.0), None).unwrap();
+        assert_eq!(limits.time_limit, Some(2.0));
+        assert_eq!(limits.memory_limit, None);
+    }
+
+    #[tokio::test]
+    async fn find_test_cases_pairs_in_and_out() {
+        let dir = std::env::temp_dir().join(format!("silicube-cases-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "10.in",
+            "10.out",
+            "2.in",
+            "2.out",
+            "no_answer.in",
+            "notes.txt",
+        ] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let cases = find_test_cases(&dir).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<_> = cases.iter().map(|case| case.name.as_str()).collect();
+        assert_eq!(names, ["2", "10"]);
+        assert_eq!(cases[0].input, dir.join("2.in"));
+        assert_eq!(cases[0].output, dir.join("2.out"));
+    }
+
+    #[test]
+    fn verdict_label_prefers_resource_failures() {
+        let ok = ExecutionResult {
+            exit_code: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(verdict_label(&ok, Verdict::Accepted), "AC");
+        assert_eq!(verdict_label(&ok, Verdict::WrongAnswer), "WA");
+
+        let tle = ExecutionResult {
+            status: ExecutionStatus::TimeLimitExceeded,
+            ..Default::default()
+        };
+        assert_eq!(verdict_label(&tle, Verdict::Accepted), "TLE");
+
+        let crashed = ExecutionResult {
+            status: ExecutionStatus::Signaled,
+            signal: Some(11),
+            ..Default::default()
+        };
+        assert_eq!(verdict_label(&crashed, Verdict::WrongAnswer), "RE");
+
+        let flooded = ExecutionResult {
+            limit_exceeded: silicube::LimitExceeded::Output,
+            ..crashed
+        };
+        assert_eq!(verdict_label(&flooded, Verdict::WrongAnswer), "OLE");
+        let summary = BatchSummary::from_results(&[(flooded, Verdict::WrongAnswer)]);
+        assert_eq!((summary.ole, summary.re), (1, 0));
+        assert!(summary_line(&summary).contains("OLE 1, RE 0"));
+    }
+
+    #[tokio::test]
+    async fn save_run_output_writes_files() {
+        let dir = std::env::temp_dir().join(format!("silicube-save-{}", std::process::id()));
+        let result = ExecutionResult::default()
+            .with_stdout(b"hello\n".to_vec())
+            .with_stderr(Vec::new());
+        let meta = MetaFile::parse("time:0.010\nexitcode:0\n");
+
+        save_run_output(&dir, &result, &meta).await.unwrap();
+
+        let stdout = std::fs::read(dir.join("stdout.txt")).unwrap();
+        let stderr = std::fs::read(dir.join("stderr.txt")).unwrap();
+        let meta_json = std::fs::read_to_string(dir.join("meta.json")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(stdout, b"hello\n");
+        assert!(stderr.is_empty());
+        assert!(meta_json.contains("\"exitcode\": \"0\""));
+        assert!(meta_json.contains("\"time\": \"0.010\""));
+    }
+}
@@ -0,0 +1,29 @@
+use std::path::Path;
+use std::process::Command;
+
+const FIXTURES_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../silicube/tests/fixtures");
+
+#[test]
+#[ignore = "requires root"]
+fn batch_reports_verdicts_and_summary() {
+    let fixtures = Path::new(FIXTURES_PATH);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_silicube"))
+        .args(["--box-id", "96", "batch", "--language", "python3"])
+        .arg("--source")
+        .arg(fixtures.join("sources/double.py"))
+        .arg("--tests")
+        .arg(fixtures.join("batch/double"))
+        .output()
+        .expect("failed to run silicube");
+
+    // Case 3 expects a wrong answer, so not every case passes
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4, "unexpected output: {stdout}");
+    assert!(lines[0].starts_with("1 ") && lines[0].contains(" AC "));
+    assert!(lines[1].starts_with("2 ") && lines[1].contains(" AC "));
+    assert!(lines[2].starts_with("3 ") && lines[2].contains(" WA "));
+    assert!(lines[3].starts_with("2/3 passed (WA 1,"), "{}", lines[3]);
+}
@@ -0,0 +1,29 @@
+use std::path::Path;
+use std::process::Command;
+
+const FIXTURES_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../silicube/tests/fixtures");
+
+#[test]
+#[ignore = "requires root"]
+fn run_with_save_output_writes_files() {
+    let dir = std::env::temp_dir().join(format!("silicube-cli-save-{}", std::process::id()));
+    let source = Path::new(FIXTURES_PATH).join("sources/hello.py");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_silicube"))
+        .args(["--box-id", "95", "run", "--language", "python3"])
+        .arg(&source)
+        .arg("--save-output")
+        .arg(&dir)
+        .status()
+        .expect("failed to run silicube");
+    assert!(status.success());
+
+    let stdout = std::fs::read_to_string(dir.join("stdout.txt")).unwrap();
+    let stderr = std::fs::read_to_string(dir.join("stderr.txt")).unwrap();
+    let meta = std::fs::read_to_string(dir.join("meta.json")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(stdout, "Hello, World!\n");
+    assert!(stderr.is_empty());
+    assert!(meta.contains("\"exitcode\": \"0\""));
+}
@@ -0,0 +1,217 @@
+//! Fluent construction of [`Config`] values
+//!
+//! For embedders that build configuration in code instead of loading a file.
+
+use std::path::PathBuf;
+
+use crate::config::{Config, ConfigError, Language};
+use crate::types::{MemoryReportMode, MountConfig, ResourceLimits};
+
+/// Builder for [`Config`]
+///
+/// Start from [`Config::builder`] for an empty config, or convert an
+/// existing one (such as [`Config::default`]) with `ConfigBuilder::from`.
+/// [`build`](Self::build) runs the same validation as file loading.
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Set the path to the isolate binary
+    pub fn isolate_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.isolate_path = Some(path.into());
+        self
+    }
+
+    /// Enable or disable cgroup memory limiting
+    pub fn cgroup(mut self, enable: bool) -> Self {
+        self.config.cgroup = enable;
+        self
+    }
+
+    /// Set the cgroup root path
+    pub fn cg_root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.cg_root = path.into();
+        self
+    }
+
+    /// Clean up and retry once when a box is left over from an earlier run
+    pub fn init_clean_retry(mut self, enable: bool) -> Self {
+        self.config.init_clean_retry = enable;
+        self
+    }
+
+    /// Add a mount applied to all sandbox invocations
+    pub fn sandbox_mount(mut self, mount: MountConfig) -> Self {
+        self.config.sandbox_mounts.push(mount);
+        self
+    }
+
+    /// Add a mount applied only when running interpreted languages
+    pub fn interpreted_mount(mut self, mount: MountConfig) -> Self {
+        self.config.interpreted_mounts.push(mount);
+        self
+    }
+
+    /// Set the directory relative mount sources are resolved against
+    pub fn mount_source_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.config.mount_source_root = Some(root.into());
+        self
+    }
+
+    /// Set which memory measurement populates `ExecutionResult::memory`
+    pub fn memory_report(mut self, mode: MemoryReportMode) -> Self {
+        self.config.memory_report = mode;
+        self
+    }
+
+    /// Measure the box's disk usage after each batch run
+    pub fn report_disk_usage(mut self, enable: bool) -> Self {
+        self.config.report_disk_usage = enable;
+        self
+    }
+
+    /// Set the prefix for silicube's scratch files in boxes
+    pub fn internal_file_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.config.internal_file_prefix = prefix.into();
+        self
+    }
+
+    /// Keep a per-phase timing breakdown on batch and compile results
+    pub fn collect_timings(mut self, enable: bool) -> Self {
+        self.config.collect_timings = enable;
+        self
+    }
+
+    /// Capture the program's stderr on batch runs
+    pub fn capture_stderr(mut self, enable: bool) -> Self {
+        self.config.capture_stderr = enable;
+        self
+    }
+
+    /// Set the host-side timeout in seconds for batch isolate processes
+    pub fn wrapper_timeout(mut self, seconds: f64) -> Self {
+        self.config.wrapper_timeout = Some(seconds);
+        self
+    }
+
+    /// Set the seconds between SIGTERM and SIGKILL when the wrapper timeout fires
+    pub fn wrapper_kill_grace(mut self, seconds: f64) -> Self {
+        self.config.wrapper_kill_grace = seconds;
+        self
+    }
+
+    /// Set the maximum source size in bytes
+    pub fn max_source_bytes(mut self, limit: usize) -> Self {
+        self.config.max_source_bytes = Some(limit);
+        self
+    }
+
+    /// Cap the number of concurrent compiles per runner
+    pub fn max_concurrent_compiles(mut self, limit: usize) -> Self {
+        self.config.max_concurrent_compiles = Some(limit);
+        self
+    }
+
+    /// Cap the number of concurrent runs per runner
+    pub fn max_concurrent_runs(mut self, limit: usize) -> Self {
+        self.config.max_concurrent_runs = Some(limit);
+        self
+    }
+
+    /// Set the default resource limits
+    pub fn default_limits(mut self, limits: ResourceLimits) -> Self {
+        self.config.default_limits = limits;
+        self
+    }
+
+    /// Set upper bounds on requested limits, checked by `Runner::can_admit`
+    pub fn max_limits(mut self, limits: ResourceLimits) -> Self {
+        self.config.max_limits = Some(limits);
+        self
+    }
+
+    /// Set an extra placeholder value for `env` templates
+    pub fn placeholder(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.placeholders.insert(name.into(), value.into());
+        self
+    }
+
+    /// Add or replace a language
+    pub fn language(mut self, id: impl Into<String>, language: Language) -> Self {
+        self.config.languages.insert(id.into(), language);
+        self
+    }
+
+    /// Validate and return the configuration
+    pub fn build(self) -> Result<Config, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+impl From<Config> for ConfigBuilder {
+    fn from(config: Config) -> Self {
+        Self { config }
+    }
+}
+
+impl Config {
+    /// Start building a config with no languages
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::from(Self::empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn python() -> Language {
+        Config::default()
+            .get_language("python3")
+            .expect("python3 in defaults")
+            .clone()
+    }
+
+    #[test]
+    fn test_build_valid_config() {
+        let config = Config::builder()
+            .isolate_path("/usr/local/bin/isolate")
+            .cgroup(true)
+            .language("py", python())
+            .default_limits(ResourceLimits {
+                time_limit: Some(5.0),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            config.isolate_binary(),
+            PathBuf::from("/usr/local/bin/isolate")
+        );
+        assert!(config.cgroup);
+        assert!(config.get_language("py").is_ok());
+        assert_eq!(config.default_limits.time_limit, Some(5.0));
+    }
+
+    #[test]
+    fn test_build_from_defaults_keeps_languages() {
+        let config = ConfigBuilder::from(Config::default())
+            .cgroup(true)
+            .build()
+            .unwrap();
+        assert!(config.get_language("cpp17").is_ok());
+    }
+
+    #[test]
+    fn test_build_rejects_empty_run_command() {
+        let mut language = python();
+        language.run.command.clear();
+
+        let result = Config::builder().language("py", language).build();
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+}
@@ -1,15 +1,29 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use serde::{Deserialize, Deserializer, Serialize, de};
 
 use crate::config::ConfigError;
+use crate::isolate::{IsolateError, resolve_command};
 use crate::types::{MountConfig, ResourceLimits};
 
 const INVALID_FILE_EXT_CHARS: [char; 2] = ['/', '.'];
 
+/// Environment variables set by every deterministic run
+///
+/// Per-language `deterministic_env` entries override these.
+pub const DEFAULT_DETERMINISTIC_ENV: [(&str, &str); 3] = [
+    ("PYTHONHASHSEED", "0"),
+    ("GOMAXPROCS", "1"),
+    ("OMP_NUM_THREADS", "1"),
+];
+
 /// Default PATH for sandbox execution
 pub const DEFAULT_SANDBOX_PATH: &str = "/usr/bin:/bin";
 
+/// Default working directory for sandbox execution
+pub const DEFAULT_WORKING_DIR: &str = "/box";
+
 /// Configuration for a programming language
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Language {
@@ -19,6 +33,12 @@ pub struct Language {
     /// File extension
     pub extension: FileExtension,
 
+    /// Command printing the toolchain version (e.g. `["g++", "--version"]`)
+    ///
+    /// Used by `Runner::toolchain_report`; languages without one are skipped.
+    #[serde(default)]
+    pub version_command: Vec<String>,
+
     /// Compilation configuration (None for interpreted languages)
     #[serde(default)]
     pub compile: Option<CompileConfig>,
@@ -42,17 +62,137 @@ impl Language {
         }
     }
 
+    /// Get the name substituted for `{binary}` when running this language
+    ///
+    /// This is the compile output for compiled languages and the source file
+    /// for interpreted ones.
+    pub fn binary_name(&self) -> String {
+        if let Some(ref compile) = self.compile {
+            compile.output_name.clone()
+        } else {
+            self.source_name()
+        }
+    }
+
+    /// Get the name substituted for `{entry}` when running this language
+    ///
+    /// This is the run config's `entry` if set, otherwise
+    /// [`binary_name`](Self::binary_name).
+    pub fn entry_name(&self) -> String {
+        self.run.entry.clone().unwrap_or_else(|| self.binary_name())
+    }
+
+    /// Expand placeholders in the run command, including `{entry}`
+    pub fn expand_run_command(&self) -> Vec<String> {
+        self.expand_with_entry(&self.run.command)
+    }
+
+    /// Get the argv a run actually executes
+    ///
+    /// Expands placeholders like [`expand_run_command`](Self::expand_run_command)
+    /// and resolves a bare program name against the host PATH, as isolate
+    /// `execve`s it without a PATH search.
+    pub fn resolved_run_command(&self) -> Result<Vec<String>, IsolateError> {
+        let mut command = self.expand_run_command();
+        resolve_command(&mut command)?;
+        Ok(command)
+    }
+
+    /// Get the prepare command with placeholders expanded, if configured
+    pub fn expand_prepare_command(&self) -> Option<Vec<String>> {
+        self.run
+            .prepare
+            .as_ref()
+            .map(|command| self.expand_with_entry(command))
+    }
+
+    /// Wrap a shell script as a command, for pipelines and redirection
+    ///
+    /// Isolate `execve`s a single program, so `a | b` has to be handed to a
+    /// shell. Returns `[shell, "-c", script]` with a bare `shell` name
+    /// resolved against the host PATH. `script` is passed as one argument
+    /// without quoting; placeholders in it are still expanded at run time.
+    pub fn shell_command(shell: &str, script: &str) -> Result<Vec<String>, IsolateError> {
+        let mut command = vec![shell.to_owned(), "-c".to_owned(), script.to_owned()];
+        resolve_command(&mut command)?;
+        Ok(command)
+    }
+
+    fn expand_with_entry(&self, command: &[String]) -> Vec<String> {
+        let entry = self.entry_name();
+        let command: Vec<String> = command
+            .iter()
+            .map(|arg| arg.replace("{entry}", &entry))
+            .collect();
+        Self::expand_command(&command, &self.source_name(), &self.binary_name())
+    }
+
+    /// Get a copy of this language configured for deterministic runs
+    ///
+    /// Adds [`DEFAULT_DETERMINISTIC_ENV`] and the language's
+    /// `deterministic_env` to the run environment. This only pins common
+    /// sources of nondeterminism; fully reproducible runs also need
+    /// single-process limits (`max_processes = 1`).
+    pub fn deterministic(&self) -> Language {
+        let mut language = self.clone();
+        let env = &mut language.run.env;
+        for (key, value) in DEFAULT_DETERMINISTIC_ENV {
+            env.insert(key.to_owned(), value.to_owned());
+        }
+        env.extend(self.run.deterministic_env.clone());
+        language
+    }
+
     /// Expand placeholders in the given command
+    ///
+    /// `{entry}` expands to `binary` here; use
+    /// [`expand_run_command`](Self::expand_run_command) to honor a
+    /// configured run entry.
     pub fn expand_command(command: &[String], source: &str, binary: &str) -> Vec<String> {
+        let vars = HashMap::new();
         command
             .iter()
-            .map(|arg| {
-                arg.replace("{source}", source)
-                    .replace("{output}", binary)
-                    .replace("{binary}", binary)
+            .map(|arg| Self::expand_placeholders(arg, source, binary, &vars))
+            .collect()
+    }
+
+    /// Expand placeholders in environment variable values
+    ///
+    /// Supports the same `{source}`, `{output}`, and `{binary}` placeholders
+    /// as [`expand_command`](Self::expand_command), plus `{key}` for every
+    /// entry in `vars`.
+    pub fn expand_env(
+        env: &HashMap<String, String>,
+        source: &str,
+        binary: &str,
+        vars: &HashMap<String, String>,
+    ) -> HashMap<String, String> {
+        env.iter()
+            .map(|(key, value)| {
+                let value = Self::expand_placeholders(value, source, binary, vars);
+                (key.clone(), value)
             })
             .collect()
     }
+
+    /// Expand the built-in placeholders and any caller-supplied `{key}` in `value`
+    pub fn expand_placeholders(
+        value: &str,
+        source: &str,
+        binary: &str,
+        vars: &HashMap<String, String>,
+    ) -> String {
+        // `{entry}` falls back to the binary when no separate entry applies
+        let mut expanded = value
+            .replace("{source}", source)
+            .replace("{output}", binary)
+            .replace("{binary}", binary)
+            .replace("{entry}", binary);
+        for (key, var) in vars {
+            expanded = expanded.replace(&format!("{{{key}}}"), var);
+        }
+        expanded
+    }
 }
 
 /// File extension without dot (e.g., "cpp")
@@ -110,9 +250,32 @@ pub struct CompileConfig {
     pub output_name: String,
 
     /// Environment variables to set during compilation
+    /// Values may use the same placeholders as `command`
     #[serde(default)]
     pub env: HashMap<String, String>,
 
+    /// Host environment variables passed through during compilation
+    #[serde(default)]
+    pub inherit_env: Vec<String>,
+
+    /// Directory mounts applied only during compilation
+    #[serde(default)]
+    pub mounts: Vec<MountConfig>,
+
+    /// PATH environment variable during compilation
+    ///
+    /// Defaults to "/usr/bin:/bin". A `PATH` entry in `env` still takes
+    /// precedence.
+    #[serde(default = "default_sandbox_path")]
+    pub path: String,
+
+    /// Give the compiler network access (isolate `--share-net`)
+    ///
+    /// For builds that fetch dependencies, e.g. cargo or npm. Does not
+    /// affect the run, which has its own `share_net`. Off by default.
+    #[serde(default)]
+    pub share_net: bool,
+
     /// Resource limits for compilation (overrides defaults)
     #[serde(default)]
     pub limits: Option<ResourceLimits>,
@@ -122,13 +285,50 @@ pub struct CompileConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunConfig {
     /// Command and arguments with placeholders
-    /// Placeholders: {source}, {binary}
+    /// Placeholders: {source}, {binary}, {entry}
     pub command: Vec<String>,
 
+    /// Entry point substituted for `{entry}` (e.g. the main class `Main`)
+    ///
+    /// For languages where the compile output differs from what the run
+    /// command names, such as Java's `Main.class` run as `java Main`.
+    /// Defaults to the `{binary}` value.
+    #[serde(default)]
+    pub entry: Option<String>,
+
+    /// Setup command run once in the box after the source is in place (e.g. `npm ci`)
+    ///
+    /// Run by `Runner::prepare` and `Runner::compile`, with the same
+    /// placeholders, environment, and mounts as `command` but under
+    /// `prepare_limits`. A failing prepare step fails that call. Runs never
+    /// start it themselves, so interactive sessions and boxes set up by
+    /// other means skip it.
+    #[serde(default)]
+    pub prepare: Option<Vec<String>>,
+
+    /// Resource limits for the prepare step
+    ///
+    /// Layered over the compile defaults, not the run limits, since setup
+    /// usually needs more time and processes than the program itself.
+    #[serde(default)]
+    pub prepare_limits: Option<ResourceLimits>,
+
     /// Environment Variables to set
+    /// Values may use the same placeholders as `command`
     #[serde(default)]
     pub env: HashMap<String, String>,
 
+    /// Extra environment variables set by deterministic runs
+    ///
+    /// Applied on top of the built-in deterministic defaults (such as
+    /// `PYTHONHASHSEED=0`) and `env` by `Runner::run_deterministic`.
+    #[serde(default)]
+    pub deterministic_env: HashMap<String, String>,
+
+    /// Host environment variables passed through to the program (e.g. `TZ`)
+    #[serde(default)]
+    pub inherit_env: Vec<String>,
+
     /// Directory mounts
     #[serde(default)]
     pub mounts: Vec<MountConfig>,
@@ -139,15 +339,59 @@ pub struct RunConfig {
     #[serde(default = "default_sandbox_path")]
     pub path: String,
 
+    /// Directories placed ahead of `path`, e.g. a toolchain's `bin`
+    ///
+    /// Produces `{path_prepend}:{path}`, keeping the default search path.
+    #[serde(default)]
+    pub path_prepend: Option<String>,
+
+    /// Working directory inside the sandbox
+    ///
+    /// Defaults to "/box". May be any absolute sandbox-visible path without
+    /// `..` components, such as the target of a project mount. The program
+    /// can read (and, for writable mounts, modify) everything under it, so
+    /// only point it at directories meant to be exposed. Relative run
+    /// commands like `./{binary}` resolve against it, so use `/box/{binary}`
+    /// when changing it.
+    #[serde(default = "default_working_dir")]
+    pub working_dir: String,
+
+    /// Seccomp profile passed to isolate as `--seccomp`
+    ///
+    /// Requires an isolate build that supports the flag; upstream isolate
+    /// does not. The file must exist on the host when the program runs.
+    #[serde(default)]
+    pub seccomp_profile: Option<PathBuf>,
+
+    /// Give the program network access (isolate `--share-net`)
+    ///
+    /// Off by default, and always off for locked-down runs.
+    #[serde(default)]
+    pub share_net: bool,
+
     /// Resource limits for execution (overrides defaults)
     #[serde(default)]
     pub limits: Option<ResourceLimits>,
 }
 
+impl RunConfig {
+    /// PATH passed to the sandbox, with `path_prepend` applied
+    pub fn sandbox_path(&self) -> String {
+        match &self.path_prepend {
+            Some(prepend) => format!("{prepend}:{}", self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
 fn default_sandbox_path() -> String {
     DEFAULT_SANDBOX_PATH.to_owned()
 }
 
+fn default_working_dir() -> String {
+    DEFAULT_WORKING_DIR.to_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,6 +489,13 @@ mod tests {
         assert_eq!(result, vec!["gcc", "test.c", "-o", "test"]);
     }
 
+    #[test]
+    fn expand_command_entry_defaults_to_binary() {
+        let cmd = vec!["./{entry}".to_owned()];
+        let result = Language::expand_command(&cmd, "main.c", "main");
+        assert_eq!(result, vec!["./main"]);
+    }
+
     #[test]
     fn expand_command_no_placeholders() {
         let cmd = vec!["echo".to_owned(), "hello".to_owned()];
@@ -266,40 +517,181 @@ mod tests {
         assert_eq!(result, vec!["prefix-main.c-suffix"]);
     }
 
+    #[test]
+    fn expand_env_builtin_placeholders() {
+        let env = HashMap::from([("MAIN".to_owned(), "/box/{binary}".to_owned())]);
+        let result = Language::expand_env(&env, "main.cpp", "main", &HashMap::new());
+        assert_eq!(result["MAIN"], "/box/main");
+    }
+
+    #[test]
+    fn expand_env_custom_placeholder() {
+        let env = HashMap::from([
+            ("PYTHONHASHSEED".to_owned(), "{seed}".to_owned()),
+            ("UNCHANGED".to_owned(), "{unknown}".to_owned()),
+        ]);
+        let vars = HashMap::from([("seed".to_owned(), "42".to_owned())]);
+        let result = Language::expand_env(&env, "main.py", "main.py", &vars);
+        assert_eq!(result["PYTHONHASHSEED"], "42");
+        // Unknown placeholders are left as-is
+        assert_eq!(result["UNCHANGED"], "{unknown}");
+    }
+
     #[test]
     fn language_is_compiled_true() {
         let lang = Language {
             name: "C++".to_owned(),
             extension: FileExtension::new("cpp").unwrap(),
+            version_command: vec![],
             compile: Some(CompileConfig {
                 command: vec!["g++".to_owned()],
                 source_name: "main.cpp".to_owned(),
                 output_name: "main".to_owned(),
                 env: std::collections::HashMap::new(),
+                inherit_env: vec![],
+                mounts: vec![],
+                path: DEFAULT_SANDBOX_PATH.to_owned(),
+                share_net: false,
                 limits: None,
             }),
             run: RunConfig {
                 command: vec!["./{binary}".to_owned()],
                 env: std::collections::HashMap::new(),
+                deterministic_env: std::collections::HashMap::new(),
+                inherit_env: vec![],
                 mounts: vec![],
                 path: DEFAULT_SANDBOX_PATH.to_owned(),
+                path_prepend: None,
+                working_dir: DEFAULT_WORKING_DIR.to_owned(),
+                seccomp_profile: None,
+                entry: None,
+                prepare: None,
+                prepare_limits: None,
+                share_net: false,
                 limits: None,
             },
         };
         assert!(lang.is_compiled());
     }
 
+    #[test]
+    fn language_deterministic_env() {
+        let mut run = RunConfig {
+            command: vec!["python3".to_owned(), "{source}".to_owned()],
+            env: HashMap::from([("PYTHONHASHSEED".to_owned(), "123".to_owned())]),
+            deterministic_env: HashMap::from([("GOMAXPROCS".to_owned(), "2".to_owned())]),
+            inherit_env: vec![],
+            mounts: vec![],
+            path: DEFAULT_SANDBOX_PATH.to_owned(),
+            path_prepend: None,
+            working_dir: DEFAULT_WORKING_DIR.to_owned(),
+            seccomp_profile: None,
+            entry: None,
+            prepare: None,
+            prepare_limits: None,
+            share_net: false,
+            limits: None,
+        };
+        run.env.insert("KEEP".to_owned(), "1".to_owned());
+        let lang = Language {
+            name: "Python".to_owned(),
+            extension: FileExtension::new("py").unwrap(),
+            version_command: vec![],
+            compile: None,
+            run,
+        };
+
+        let env = lang.deterministic().run.env;
+        // Built-in defaults override the normal env
+        assert_eq!(env["PYTHONHASHSEED"], "0");
+        // Per-language deterministic_env overrides the defaults
+        assert_eq!(env["GOMAXPROCS"], "2");
+        assert_eq!(env["OMP_NUM_THREADS"], "1");
+        assert_eq!(env["KEEP"], "1");
+        // The original language is unchanged
+        assert_eq!(lang.run.env["PYTHONHASHSEED"], "123");
+    }
+
+    #[test]
+    fn shell_command_wraps_script() {
+        let command = Language::shell_command("sh", "cat {source} | sort").unwrap();
+        assert!(
+            command[0].starts_with('/'),
+            "shell not resolved: {command:?}"
+        );
+        assert_eq!(command[1..], ["-c", "cat {source} | sort"]);
+
+        let command = Language::shell_command("/bin/sh", "true").unwrap();
+        assert_eq!(command, ["/bin/sh", "-c", "true"]);
+    }
+
+    #[test]
+    fn resolved_run_command_interpreted() {
+        let lang = Language {
+            name: "Shell".to_owned(),
+            extension: FileExtension::new("sh").unwrap(),
+            version_command: vec![],
+            compile: None,
+            run: RunConfig {
+                command: vec!["sh".to_owned(), "{source}".to_owned()],
+                env: HashMap::new(),
+                deterministic_env: HashMap::new(),
+                inherit_env: vec![],
+                mounts: vec![],
+                path: DEFAULT_SANDBOX_PATH.to_owned(),
+                path_prepend: None,
+                working_dir: DEFAULT_WORKING_DIR.to_owned(),
+                seccomp_profile: None,
+                entry: None,
+                prepare: None,
+                prepare_limits: None,
+                share_net: false,
+                limits: None,
+            },
+        };
+
+        let command = lang.resolved_run_command().unwrap();
+        let mut expected = vec!["sh".to_owned()];
+        resolve_command(&mut expected).unwrap();
+        assert!(command[0].starts_with('/'), "not resolved: {command:?}");
+        assert_eq!(command[0], expected[0]);
+        assert_eq!(command[1..], ["main.sh"]);
+
+        let mut missing = lang;
+        missing.run.command[0] = "silicube-no-such-interpreter".to_owned();
+        assert!(matches!(
+            missing.resolved_run_command(),
+            Err(IsolateError::CommandNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn shell_command_missing_shell() {
+        let result = Language::shell_command("silicube-no-such-shell", "true");
+        assert!(matches!(result, Err(IsolateError::CommandNotFound(_))));
+    }
+
     #[test]
     fn language_is_compiled_false() {
         let lang = Language {
             name: "Python".to_owned(),
             extension: FileExtension::new("py").unwrap(),
+            version_command: vec![],
             compile: None,
             run: RunConfig {
                 command: vec!["python3".to_owned(), "{source}".to_owned()],
                 env: std::collections::HashMap::new(),
+                deterministic_env: std::collections::HashMap::new(),
+                inherit_env: vec![],
                 mounts: vec![],
                 path: DEFAULT_SANDBOX_PATH.to_owned(),
+                path_prepend: None,
+                working_dir: DEFAULT_WORKING_DIR.to_owned(),
+                seccomp_profile: None,
+                entry: None,
+                prepare: None,
+                prepare_limits: None,
+                share_net: false,
                 limits: None,
             },
         };
@@ -311,22 +703,37 @@ mod tests {
         let lang = Language {
             name: "C++".to_owned(),
             extension: FileExtension::new("cpp").unwrap(),
+            version_command: vec![],
             compile: Some(CompileConfig {
                 command: vec!["g++".to_owned()],
                 source_name: "solution.cpp".to_owned(),
                 output_name: "solution".to_owned(),
                 env: std::collections::HashMap::new(),
+                inherit_env: vec![],
+                mounts: vec![],
+                path: DEFAULT_SANDBOX_PATH.to_owned(),
+                share_net: false,
                 limits: None,
             }),
             run: RunConfig {
                 command: vec!["./{binary}".to_owned()],
                 env: std::collections::HashMap::new(),
+                deterministic_env: std::collections::HashMap::new(),
+                inherit_env: vec![],
                 mounts: vec![],
                 path: DEFAULT_SANDBOX_PATH.to_owned(),
+                path_prepend: None,
+                working_dir: DEFAULT_WORKING_DIR.to_owned(),
+                seccomp_profile: None,
+                entry: None,
+                prepare: None,
+                prepare_limits: None,
+                share_net: false,
                 limits: None,
             },
         };
         assert_eq!(lang.source_name(), "solution.cpp");
+        assert_eq!(lang.binary_name(), "solution");
     }
 
     #[test]
@@ -334,16 +741,27 @@ mod tests {
         let lang = Language {
             name: "Python".to_owned(),
             extension: FileExtension::new("py").unwrap(),
+            version_command: vec![],
             compile: None,
             run: RunConfig {
                 command: vec!["python3".to_owned(), "{source}".to_owned()],
                 env: std::collections::HashMap::new(),
+                deterministic_env: std::collections::HashMap::new(),
+                inherit_env: vec![],
                 mounts: vec![],
                 path: DEFAULT_SANDBOX_PATH.to_owned(),
+                path_prepend: None,
+                working_dir: DEFAULT_WORKING_DIR.to_owned(),
+                seccomp_profile: None,
+                entry: None,
+                prepare: None,
+                prepare_limits: None,
+                share_net: false,
                 limits: None,
             },
         };
         assert_eq!(lang.source_name(), "main.py");
+        assert_eq!(lang.binary_name(), "main.py");
     }
 
     #[test]
@@ -6,15 +6,34 @@ use std::path::Path;
 
 use config::{Config as ConfigBuilder, File, FileFormat};
 
-use crate::config::{Config, ConfigError};
+use crate::config::{Config, ConfigError, EXAMPLE_CONFIG};
+use crate::isolate::{validate_box_file_name, validate_working_dir};
+use crate::types::ResourceLimits;
 
 impl Config {
     /// Load configuration from a file
+    ///
+    /// The result contains exactly what the file declares; the embedded
+    /// default languages are not included. Use
+    /// [`Config::from_file_with_defaults`] to layer the file over them.
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::from_file_with_defaults(path, false)
+    }
+
+    /// Load configuration from a file, optionally merged over the defaults
+    ///
+    /// When `merge_defaults` is true the file is layered on top of
+    /// [`EXAMPLE_CONFIG`], so it only needs to declare what differs.
+    pub fn from_file_with_defaults(
+        path: impl AsRef<Path>,
+        merge_defaults: bool,
+    ) -> Result<Self, ConfigError> {
         let path = path.as_ref();
-        let config = ConfigBuilder::builder()
-            .add_source(File::from(path))
-            .build()?;
+        let mut builder = ConfigBuilder::builder();
+        if merge_defaults {
+            builder = builder.add_source(File::from_str(EXAMPLE_CONFIG, FileFormat::Toml));
+        }
+        let config = builder.add_source(File::from(path)).build()?;
 
         let config: Config = config.try_deserialize()?;
         config.validate()?;
@@ -33,7 +52,37 @@ impl Config {
     }
 
     /// Validate the configuration
-    fn validate(&self) -> Result<(), ConfigError> {
+    pub(super) fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_concurrent_compiles == Some(0) || self.max_concurrent_runs == Some(0) {
+            return Err(ConfigError::Invalid(
+                "concurrency limits must be at least 1".to_string(),
+            ));
+        }
+
+        if self
+            .wrapper_timeout
+            .is_some_and(|t| !t.is_finite() || t <= 0.0)
+        {
+            return Err(ConfigError::Invalid(
+                "wrapper_timeout must be a positive number of seconds".to_string(),
+            ));
+        }
+        if !self.wrapper_kill_grace.is_finite() || self.wrapper_kill_grace < 0.0 {
+            return Err(ConfigError::Invalid(
+                "wrapper_kill_grace must not be negative".to_string(),
+            ));
+        }
+
+        if let Err(e) = self.default_limits.validate() {
+            return Err(ConfigError::Invalid(format!("default_limits: {e}")));
+        }
+
+        if self.internal_file_prefix.contains('/') {
+            return Err(ConfigError::Invalid(
+                "internal_file_prefix must not contain '/'".to_string(),
+            ));
+        }
+
         // Validate all languages have required fields
         for (id, lang) in &self.languages {
             if lang.name.is_empty() {
@@ -51,6 +100,14 @@ impl Config {
                     "language '{id}' has empty run command"
                 )));
             }
+            if lang.run.prepare.as_ref().is_some_and(Vec::is_empty) {
+                return Err(ConfigError::Invalid(format!(
+                    "language '{id}' has empty prepare command"
+                )));
+            }
+            if let Err(e) = validate_working_dir(&lang.run.working_dir) {
+                return Err(ConfigError::Invalid(format!("language '{id}': {e}")));
+            }
             if let Some(ref compile) = lang.compile
                 && compile.command.is_empty()
             {
@@ -58,6 +115,31 @@ impl Config {
                     "language '{id}' has empty compile command"
                 )));
             }
+            for name in [lang.source_name(), lang.binary_name()] {
+                if let Err(e) = validate_box_file_name(&name, &self.internal_file_prefix) {
+                    return Err(ConfigError::Invalid(format!("language '{id}': {e}")));
+                }
+            }
+            let limits = [
+                (
+                    "compile",
+                    lang.compile.as_ref().and_then(|c| c.limits.as_ref()),
+                ),
+                ("run", lang.run.limits.as_ref()),
+                ("prepare", lang.run.prepare_limits.as_ref()),
+            ];
+            for (stage, limits) in limits {
+                if let Some(Err(e)) = limits.map(ResourceLimits::validate) {
+                    return Err(ConfigError::Invalid(format!(
+                        "language '{id}' {stage} limits: {e}"
+                    )));
+                }
+            }
+            if lang.is_compiled() && lang.source_name() == lang.binary_name() {
+                return Err(ConfigError::Invalid(format!(
+                    "language '{id}' compiles its source onto itself"
+                )));
+            }
         }
 
         Ok(())
@@ -165,6 +247,468 @@ command = ["./{binary}"]
         assert_eq!(compile_limits.wall_time_limit, None);
     }
 
+    #[test]
+    fn test_parse_compile_mounts() {
+        let toml = r#"
+[languages.cpp]
+name = "C++"
+extension = "cpp"
+
+[languages.cpp.compile]
+command = ["g++", "-o", "{output}", "{source}"]
+source_name = "main.cpp"
+output_name = "main"
+
+[[languages.cpp.compile.mounts]]
+source = "/opt/include"
+target = "/opt/include"
+
+[languages.cpp.run]
+command = ["./{binary}"]
+"#;
+
+        let config = Config::parse_toml(toml).unwrap();
+        let language = &config.languages["cpp"];
+        let mounts = &language.compile.as_ref().unwrap().mounts;
+        assert_eq!(mounts.len(), 1);
+        assert_eq!(mounts[0].source, "/opt/include");
+        assert_eq!(mounts[0].target, "/opt/include");
+        assert!(!mounts[0].writable);
+        assert!(language.run.mounts.is_empty());
+    }
+
+    #[test]
+    fn test_parse_inherit_env() {
+        let toml = r#"
+[languages.cpp]
+name = "C++"
+extension = "cpp"
+
+[languages.cpp.compile]
+command = ["g++", "-o", "{output}", "{source}"]
+source_name = "main.cpp"
+output_name = "main"
+inherit_env = ["LANG"]
+
+[languages.cpp.run]
+command = ["./{binary}"]
+inherit_env = ["TZ", "LANG"]
+"#;
+
+        let config = Config::parse_toml(toml).unwrap();
+        let language = &config.languages["cpp"];
+        assert_eq!(language.compile.as_ref().unwrap().inherit_env, ["LANG"]);
+        assert_eq!(language.run.inherit_env, ["TZ", "LANG"]);
+
+        let config = Config::parse_toml(SINGLE_LANGUAGE).unwrap();
+        assert!(config.languages["test"].run.inherit_env.is_empty());
+    }
+
+    #[test]
+    fn test_parse_deterministic_env() {
+        let toml = r#"
+[languages.test]
+name = "Test Language"
+extension = "test"
+
+[languages.test.run]
+command = ["./test"]
+
+[languages.test.run.deterministic_env]
+SEED = "0"
+"#;
+
+        let config = Config::parse_toml(toml).unwrap();
+        assert_eq!(config.languages["test"].run.deterministic_env["SEED"], "0");
+    }
+
+    #[test]
+    fn test_parse_working_dir() {
+        let toml = r#"
+[languages.test]
+name = "Test Language"
+extension = "test"
+
+[languages.test.run]
+command = ["/box/test"]
+working_dir = "/project"
+"#;
+
+        let config = Config::parse_toml(toml).unwrap();
+        assert_eq!(config.languages["test"].run.working_dir, "/project");
+
+        let config = Config::parse_toml(SINGLE_LANGUAGE).unwrap();
+        assert_eq!(config.languages["test"].run.working_dir, "/box");
+    }
+
+    #[test]
+    fn test_parse_concurrency_limits() {
+        let toml =
+            format!("max_concurrent_compiles = 2\nmax_concurrent_runs = 8\n{SINGLE_LANGUAGE}");
+        let config = Config::parse_toml(&toml).unwrap();
+        assert_eq!(config.max_concurrent_compiles, Some(2));
+        assert_eq!(config.max_concurrent_runs, Some(8));
+
+        let toml = format!("max_concurrent_compiles = 0\n{SINGLE_LANGUAGE}");
+        assert!(Config::parse_toml(&toml).is_err());
+
+        let toml = format!("max_concurrent_runs = 0\n{SINGLE_LANGUAGE}");
+        assert!(Config::parse_toml(&toml).is_err());
+    }
+
+    #[test]
+    fn test_parse_internal_file_prefix() {
+        let config = Config::parse_toml(SINGLE_LANGUAGE).unwrap();
+        assert_eq!(config.internal_file("meta.txt"), "meta.txt");
+
+        let toml = format!("internal_file_prefix = \".silicube_\"\n{SINGLE_LANGUAGE}");
+        let config = Config::parse_toml(&toml).unwrap();
+        assert_eq!(config.internal_file("meta.txt"), ".silicube_meta.txt");
+
+        let toml = format!("internal_file_prefix = \"../\"\n{SINGLE_LANGUAGE}");
+        assert!(Config::parse_toml(&toml).is_err());
+    }
+
+    #[test]
+    fn test_parse_capture_stderr() {
+        let config = Config::parse_toml(SINGLE_LANGUAGE).unwrap();
+        assert!(config.capture_stderr);
+
+        let toml = format!("capture_stderr = false\n{SINGLE_LANGUAGE}");
+        let config = Config::parse_toml(&toml).unwrap();
+        assert!(!config.capture_stderr);
+    }
+
+    #[test]
+    fn test_parse_wrapper_timeout() {
+        let config = Config::parse_toml(SINGLE_LANGUAGE).unwrap();
+        assert_eq!(config.wrapper_timeout, None);
+        assert_eq!(config.wrapper_kill_grace, 1.0);
+
+        let toml = format!("wrapper_timeout = 30.0\nwrapper_kill_grace = 0.5\n{SINGLE_LANGUAGE}");
+        let config = Config::parse_toml(&toml).unwrap();
+        assert_eq!(config.wrapper_timeout, Some(30.0));
+        assert_eq!(config.wrapper_kill_grace, 0.5);
+
+        let toml = format!("wrapper_timeout = 0.0\n{SINGLE_LANGUAGE}");
+        assert!(Config::parse_toml(&toml).is_err());
+        let toml = format!("wrapper_kill_grace = -1.0\n{SINGLE_LANGUAGE}");
+        assert!(Config::parse_toml(&toml).is_err());
+    }
+
+    #[test]
+    fn test_parse_interpreted_mounts() {
+        let toml = format!(
+            r#"
+[[interpreted_mounts]]
+source = "/usr/lib/python3"
+target = "/usr/lib/python3"
+optional = true
+{SINGLE_LANGUAGE}"#
+        );
+        let config = Config::parse_toml(&toml).unwrap();
+        assert_eq!(config.interpreted_mounts.len(), 1);
+        assert_eq!(config.interpreted_mounts[0].target, "/usr/lib/python3");
+        assert!(config.interpreted_mounts[0].optional);
+        assert!(config.sandbox_mounts.is_empty());
+    }
+
+    #[test]
+    fn test_parse_max_limits() {
+        let toml = format!("[max_limits]\nmemory_limit = 1048576\n{SINGLE_LANGUAGE}");
+        let config = Config::parse_toml(&toml).unwrap();
+        let max = config.max_limits.unwrap();
+        assert_eq!(max.memory_limit, Some(1048576));
+        assert_eq!(max.time_limit, None);
+
+        let config = Config::parse_toml(SINGLE_LANGUAGE).unwrap();
+        assert!(config.max_limits.is_none());
+    }
+
+    #[test]
+    fn test_parse_compile_path() {
+        let toml = r#"
+[languages.cpp]
+name = "C++"
+extension = "cpp"
+
+[languages.cpp.compile]
+command = ["g++", "{source}"]
+source_name = "main.cpp"
+output_name = "main"
+path = "/opt/gcc/bin:/usr/bin:/bin"
+
+[languages.cpp.run]
+command = ["./{binary}"]
+"#;
+        let config = Config::parse_toml(toml).unwrap();
+        let compile = config.languages["cpp"].compile.as_ref().unwrap();
+        assert_eq!(compile.path, "/opt/gcc/bin:/usr/bin:/bin");
+
+        let toml = toml.replace("path = \"/opt/gcc/bin:/usr/bin:/bin\"\n", "");
+        let config = Config::parse_toml(&toml).unwrap();
+        let compile = config.languages["cpp"].compile.as_ref().unwrap();
+        assert_eq!(compile.path, crate::config::DEFAULT_SANDBOX_PATH);
+    }
+
+    #[test]
+    fn test_reserved_source_name() {
+        let toml = r#"
+[languages.c]
+name = "C"
+extension = "c"
+
+[languages.c.compile]
+command = ["gcc", "{source}", "-o", "{binary}"]
+source_name = "meta.txt"
+output_name = "main"
+
+[languages.c.run]
+command = ["./{binary}"]
+"#;
+        let err = Config::parse_toml(toml).unwrap_err();
+        assert!(err.to_string().contains("reserved"), "{err}");
+
+        let toml = toml.replace("meta.txt", "main");
+        assert!(matches!(
+            Config::parse_toml(&toml),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_run_prepare() {
+        let toml = r#"
+[languages.node]
+name = "Node.js"
+extension = "js"
+
+[languages.node.run]
+command = ["node", "{source}"]
+prepare = ["npm", "ci", "--prefix", "/box"]
+
+[languages.node.run.prepare_limits]
+wall_time_limit = 300.0
+"#;
+        let config = Config::parse_toml(toml).unwrap();
+        let language = &config.languages["node"];
+        assert_eq!(
+            language.expand_prepare_command().unwrap(),
+            ["npm", "ci", "--prefix", "/box"]
+        );
+        assert_eq!(
+            language
+                .run
+                .prepare_limits
+                .as_ref()
+                .and_then(|l| l.wall_time_limit),
+            Some(300.0)
+        );
+
+        let config = Config::parse_toml(SINGLE_LANGUAGE).unwrap();
+        assert!(config.languages["test"].run.prepare.is_none());
+        assert!(config.languages["test"].run.prepare_limits.is_none());
+
+        let toml = toml.replace(r#"["npm", "ci", "--prefix", "/box"]"#, "[]");
+        assert!(matches!(
+            Config::parse_toml(&toml),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_run_entry() {
+        let toml = r#"
+[languages.java]
+name = "Java"
+extension = "java"
+
+[languages.java.compile]
+command = ["javac", "{source}"]
+source_name = "Main.java"
+output_name = "Main.class"
+
+[languages.java.run]
+command = ["java", "{entry}"]
+entry = "Main"
+"#;
+
+        let config = Config::parse_toml(toml).unwrap();
+        let java = &config.languages["java"];
+        assert_eq!(java.run.entry.as_deref(), Some("Main"));
+        assert_eq!(java.expand_run_command(), ["java", "Main"]);
+
+        let config = Config::parse_toml(SINGLE_LANGUAGE).unwrap();
+        let language = &config.languages["test"];
+        assert!(language.run.entry.is_none());
+        assert_eq!(language.entry_name(), language.binary_name());
+    }
+
+    #[test]
+    fn test_parse_path_prepend() {
+        let toml = r#"
+[languages.go]
+name = "Go"
+extension = "go"
+
+[languages.go.run]
+command = ["./{binary}"]
+path_prepend = "/usr/local/go/bin"
+"#;
+
+        let config = Config::parse_toml(toml).unwrap();
+        let run = &config.languages["go"].run;
+        assert_eq!(run.path_prepend.as_deref(), Some("/usr/local/go/bin"));
+        assert_eq!(run.sandbox_path(), "/usr/local/go/bin:/usr/bin:/bin");
+
+        let config = Config::parse_toml(SINGLE_LANGUAGE).unwrap();
+        let run = &config.languages["test"].run;
+        assert!(run.path_prepend.is_none());
+        assert_eq!(run.sandbox_path(), run.path);
+    }
+
+    #[test]
+    fn test_parse_share_net() {
+        let toml = r#"
+[languages.test]
+name = "Test Language"
+extension = "test"
+
+[languages.test.compile]
+command = ["build"]
+source_name = "main.test"
+output_name = "main"
+share_net = true
+
+[languages.test.run]
+command = ["./test"]
+"#;
+
+        let config = Config::parse_toml(toml).unwrap();
+        let language = &config.languages["test"];
+        assert!(language.compile.as_ref().unwrap().share_net);
+        assert!(!language.run.share_net);
+
+        let config = Config::parse_toml(&format!("{toml}share_net = true\n")).unwrap();
+        assert!(config.languages["test"].run.share_net);
+    }
+
+    #[test]
+    fn test_parse_seccomp_profile() {
+        let toml = r#"
+[languages.test]
+name = "Test Language"
+extension = "test"
+
+[languages.test.run]
+command = ["./test"]
+seccomp_profile = "/etc/silicube/strict.bpf"
+"#;
+
+        let config = Config::parse_toml(toml).unwrap();
+        assert_eq!(
+            config.languages["test"].run.seccomp_profile.as_deref(),
+            Some(Path::new("/etc/silicube/strict.bpf"))
+        );
+
+        let config = Config::parse_toml(SINGLE_LANGUAGE).unwrap();
+        assert!(config.languages["test"].run.seccomp_profile.is_none());
+    }
+
+    #[test]
+    fn test_invalid_working_dir() {
+        for dir in ["project", "/box/../etc"] {
+            let toml = format!(
+                r#"
+[languages.test]
+name = "Test Language"
+extension = "test"
+
+[languages.test.run]
+command = ["/box/test"]
+working_dir = "{dir}"
+"#
+            );
+            assert!(
+                Config::parse_toml(&toml).is_err(),
+                "{dir} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_limits() {
+        let toml = r#"
+[default_limits]
+memory_limit = 0
+"#;
+        match Config::parse_toml(toml) {
+            Err(ConfigError::Invalid(msg)) => assert!(msg.contains("default_limits"), "{msg}"),
+            other => panic!("expected invalid default limits, got {other:?}"),
+        }
+
+        let toml = r#"
+[languages.test]
+name = "Test Language"
+extension = "test"
+
+[languages.test.run]
+command = ["/box/test"]
+
+[languages.test.run.limits]
+time_limit = 0.0
+"#;
+        match Config::parse_toml(toml) {
+            Err(ConfigError::Invalid(msg)) => {
+                assert!(msg.contains("language 'test' run limits"), "{msg}")
+            }
+            other => panic!("expected invalid run limits, got {other:?}"),
+        }
+    }
+
+    /// Write `content` to a uniquely named TOML file in the temp directory
+    fn write_temp_config(name: &str, content: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("silicube-{name}-{}.toml", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    const SINGLE_LANGUAGE: &str = r#"
+[languages.test]
+name = "Test Language"
+extension = "test"
+
+[languages.test.run]
+command = ["./test"]
+"#;
+
+    #[test]
+    fn test_from_file_has_only_file_languages() {
+        let path = write_temp_config("only-file", SINGLE_LANGUAGE);
+        let config = Config::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let config = config.unwrap();
+        assert_eq!(config.languages.len(), 1);
+        assert!(config.languages.contains_key("test"));
+    }
+
+    #[test]
+    fn test_from_file_with_defaults_merges_languages() {
+        let path = write_temp_config("merged", SINGLE_LANGUAGE);
+        let config = Config::from_file_with_defaults(&path, true);
+        std::fs::remove_file(&path).unwrap();
+
+        let config = config.unwrap();
+        assert!(config.languages.contains_key("test"));
+        assert!(config.languages.contains_key("cpp17"));
+        assert_eq!(
+            config.languages.len(),
+            Config::default().languages.len() + 1
+        );
+    }
+
     #[test]
     fn test_invalid_empty_name() {
         let toml = r#"
@@ -3,12 +3,16 @@ use std::path::PathBuf;
 
 use serde::Deserialize;
 use thiserror::Error;
+use tracing::warn;
 
+pub use crate::config::builder::ConfigBuilder;
 pub use crate::config::language::{
     CompileConfig, DEFAULT_SANDBOX_PATH, FileExtension, Language, RunConfig,
 };
-use crate::types::{MountConfig, ResourceLimits};
+use crate::isolate::{IsolateError, isolate_cg_root};
+use crate::types::{MemoryReportMode, MountConfig, ResourceLimits};
 
+mod builder;
 pub mod language;
 mod loader;
 
@@ -43,6 +47,9 @@ pub enum ConfigError {
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     /// Path to the Isolate binary (uses PATH if not specified).
+    ///
+    /// Used to create boxes; commands for a box always use the binary that
+    /// created it, so pools built with another path target that binary.
     #[serde(default)]
     pub isolate_path: Option<PathBuf>,
 
@@ -62,16 +69,119 @@ pub struct Config {
     #[serde(default = "default_cg_root")]
     pub cg_root: PathBuf,
 
+    /// Clean up and retry once when `isolate --init` finds a stale box.
+    ///
+    /// A crashed process can leave a box initialized, making the next init
+    /// for that ID fail. Off by default, since cleanup kills anything still
+    /// running in that box.
+    #[serde(default)]
+    pub init_clean_retry: bool,
+
     /// Global directory mounts applied to all sandbox invocations
     /// (both compilation and execution).
     #[serde(default)]
     pub sandbox_mounts: Vec<MountConfig>,
 
+    /// Extra mounts applied only when running interpreted languages,
+    /// such as an interpreter's library directory.
+    #[serde(default)]
+    pub interpreted_mounts: Vec<MountConfig>,
+
+    /// Host directory that relative mount `source` paths are resolved against.
+    ///
+    /// Absolute sources are used as-is. Unset means relative sources are
+    /// passed to isolate unchanged.
+    #[serde(default)]
+    pub mount_source_root: Option<PathBuf>,
+
+    /// Which memory measurement populates `ExecutionResult::memory`.
+    ///
+    /// Defaults to `preferred` (cg-mem when available, else max-rss). Both
+    /// detailed measurements are always kept in their own fields.
+    #[serde(default)]
+    pub memory_report: MemoryReportMode,
+
+    /// Measure the box's disk usage after each batch run.
+    ///
+    /// Fills `ExecutionResult::disk_usage`, at the cost of walking `/box`
+    /// after every run. Off by default.
+    #[serde(default)]
+    pub report_disk_usage: bool,
+
+    /// Prefix for the scratch files silicube writes into boxes.
+    ///
+    /// Applied to names like `stdout.txt` and `meta.txt` so they cannot
+    /// collide with files a submission creates. Empty by default.
+    #[serde(default)]
+    pub internal_file_prefix: String,
+
+    /// Keep a per-phase host timing breakdown on batch and compile results.
+    ///
+    /// Fills `ExecutionResult::timings` for tuning where time goes around
+    /// isolate. Off by default.
+    #[serde(default)]
+    pub collect_timings: bool,
+
+    /// Capture the program's stderr on batch runs.
+    ///
+    /// When off, stderr goes to `/dev/null` and `ExecutionResult::stderr`
+    /// is `None`, saving a file write and read per run for judges that only
+    /// check stdout. Checkers always capture it. On by default.
+    #[serde(default = "default_capture_stderr")]
+    pub capture_stderr: bool,
+
+    /// Host-side wall-clock limit in seconds on each batch isolate process.
+    ///
+    /// A safety net for isolate itself hanging, on top of the sandbox's own
+    /// limits. When it fires isolate gets SIGTERM, then SIGKILL after
+    /// `wrapper_kill_grace`, and the box is cleaned up and re-initialized.
+    /// Disabled if unset.
+    #[serde(default)]
+    pub wrapper_timeout: Option<f64>,
+
+    /// Seconds between SIGTERM and SIGKILL when `wrapper_timeout` fires
+    #[serde(default = "default_wrapper_kill_grace")]
+    pub wrapper_kill_grace: f64,
+
+    /// Maximum size in bytes of a source file written into a box.
+    ///
+    /// Guards host disk against oversized submissions. Unlimited if unset.
+    #[serde(default)]
+    pub max_source_bytes: Option<usize>,
+
+    /// Maximum number of compiles a runner performs at once.
+    ///
+    /// Independent of box count, so CPU-heavy compiles can be capped below
+    /// run concurrency. Unlimited if unset; must be at least 1.
+    #[serde(default)]
+    pub max_concurrent_compiles: Option<usize>,
+
+    /// Maximum number of batch runs a runner performs at once.
+    ///
+    /// Checker runs count toward this; interactive sessions and
+    /// [`Runner::run_stream`](crate::Runner::run_stream) don't. Unlimited if
+    /// unset; must be at least 1.
+    #[serde(default)]
+    pub max_concurrent_runs: Option<usize>,
+
     /// Default resource limits applied to all executions.
     /// This will be overridden if the code execution request specifies different limits
     #[serde(default)]
     pub default_limits: ResourceLimits,
 
+    /// Upper bounds on limits a request may ask for.
+    ///
+    /// Checked by `Runner::can_admit`; only the limits set here are bounded.
+    #[serde(default)]
+    pub max_limits: Option<ResourceLimits>,
+
+    /// Extra placeholder values for compile and run `env` templates.
+    ///
+    /// Keyed by name without braces, so `seed = "42"` expands `{seed}` in
+    /// values like `PYTHONHASHSEED = "{seed}"`.
+    #[serde(default)]
+    pub placeholders: HashMap<String, String>,
+
     /// Language configurations keyed by language ID
     #[serde(default)]
     pub languages: HashMap<String, Language>,
@@ -89,12 +199,35 @@ impl Config {
             isolate_path: None,
             cgroup: false,
             cg_root: default_cg_root(),
+            init_clean_retry: false,
             sandbox_mounts: Vec::new(),
+            interpreted_mounts: Vec::new(),
+            mount_source_root: None,
+            memory_report: MemoryReportMode::default(),
+            report_disk_usage: false,
+            internal_file_prefix: String::new(),
+            collect_timings: false,
+            capture_stderr: true,
+            wrapper_timeout: None,
+            wrapper_kill_grace: default_wrapper_kill_grace(),
+            max_source_bytes: None,
+            max_concurrent_compiles: None,
+            max_concurrent_runs: None,
             default_limits: ResourceLimits::default(),
+            max_limits: None,
+            placeholders: HashMap::new(),
             languages: HashMap::new(),
         }
     }
 
+    /// Create a config without the embedded default languages
+    ///
+    /// Alias of [`Config::empty`] for embedders that only want their own
+    /// languages; [`Config::default`] always includes the embedded ones.
+    pub fn default_empty() -> Self {
+        Self::empty()
+    }
+
     /// Get a language by ID
     pub fn get_language(&self, id: &str) -> Result<&Language, ConfigError> {
         self.languages
@@ -109,6 +242,56 @@ impl Config {
             .unwrap_or_else(|| PathBuf::from("isolate"))
     }
 
+    /// Check that `cg_root` matches the cgroup root isolate was built with.
+    ///
+    /// A mismatch makes isolate look for box cgroups somewhere silicube never
+    /// prepared, which surfaces as confusing cgroup failures at run time.
+    /// Logs a warning and returns [`IsolateError::CgRootMismatch`] on mismatch.
+    pub async fn verify_cgroup_alignment(&self) -> Result<(), IsolateError> {
+        let isolate = isolate_cg_root(&self.isolate_binary()).await?;
+        if isolate != self.cg_root {
+            let err = IsolateError::CgRootMismatch {
+                configured: self.cg_root.clone(),
+                isolate,
+            };
+            warn!("{err}");
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Resolve relative mount sources against `mount_source_root`
+    pub fn resolve_mounts(&self, mounts: &[MountConfig]) -> Vec<MountConfig> {
+        match self.mount_source_root {
+            Some(ref root) => mounts.iter().map(|m| m.resolve_source(root)).collect(),
+            None => mounts.to_vec(),
+        }
+    }
+
+    /// Global mounts for running `language`
+    ///
+    /// `sandbox_mounts`, followed by `interpreted_mounts` if the language
+    /// is not compiled.
+    pub fn run_mounts(&self, language: &Language) -> Vec<MountConfig> {
+        let interpreted = if language.is_compiled() {
+            &[][..]
+        } else {
+            &self.interpreted_mounts[..]
+        };
+        self.sandbox_mounts
+            .iter()
+            .chain(interpreted)
+            .cloned()
+            .collect()
+    }
+
+    /// Check a source size against `max_source_bytes`
+    ///
+    /// Returns the configured limit if `len` exceeds it.
+    pub fn source_size_exceeded(&self, len: usize) -> Option<usize> {
+        self.max_source_bytes.filter(|&limit| len > limit)
+    }
+
     /// Merge resource limits with defaults
     pub fn effective_limits(&self, overrides: Option<&ResourceLimits>) -> ResourceLimits {
         match overrides {
@@ -116,6 +299,11 @@ impl Config {
             None => self.default_limits.clone(),
         }
     }
+
+    /// Name of a silicube scratch file in the box, with the configured prefix
+    pub fn internal_file(&self, name: &str) -> String {
+        format!("{}{name}", self.internal_file_prefix)
+    }
 }
 
 impl Default for Config {
@@ -128,6 +316,14 @@ fn default_cg_root() -> PathBuf {
     PathBuf::from("/sys/fs/cgroup/isolate")
 }
 
+fn default_wrapper_kill_grace() -> f64 {
+    1.0
+}
+
+fn default_capture_stderr() -> bool {
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,6 +354,34 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn run_mounts_adds_interpreted_mounts_for_interpreted_languages() {
+        let mount = |target: &str| MountConfig {
+            source: "/usr/lib".to_owned(),
+            target: target.to_owned(),
+            writable: false,
+            optional: true,
+            tmp: false,
+            dev: false,
+        };
+        let config = Config {
+            sandbox_mounts: vec![mount("/shared")],
+            interpreted_mounts: vec![mount("/interp")],
+            ..Config::default()
+        };
+
+        let targets = |id: &str| -> Vec<String> {
+            let language = config.get_language(id).unwrap();
+            config
+                .run_mounts(language)
+                .into_iter()
+                .map(|m| m.target)
+                .collect()
+        };
+        assert_eq!(targets("python3"), ["/shared", "/interp"]);
+        assert_eq!(targets("cpp17"), ["/shared"]);
+    }
+
     #[test]
     fn isolate_binary_default() {
         let config = Config::empty();
@@ -170,8 +394,23 @@ mod tests {
             isolate_path: Some(PathBuf::from("/usr/local/bin/isolate")),
             cgroup: false,
             cg_root: default_cg_root(),
+            init_clean_retry: false,
             sandbox_mounts: Vec::new(),
+            interpreted_mounts: Vec::new(),
+            mount_source_root: None,
+            memory_report: MemoryReportMode::default(),
+            report_disk_usage: false,
+            internal_file_prefix: String::new(),
+            collect_timings: false,
+            capture_stderr: true,
+            wrapper_timeout: None,
+            wrapper_kill_grace: default_wrapper_kill_grace(),
+            max_source_bytes: None,
+            max_concurrent_compiles: None,
+            max_concurrent_runs: None,
             default_limits: ResourceLimits::default(),
+            max_limits: None,
+            placeholders: std::collections::HashMap::new(),
             languages: std::collections::HashMap::new(),
         };
         assert_eq!(
@@ -180,6 +419,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn source_size_unlimited_by_default() {
+        let config = Config::empty();
+        assert_eq!(config.source_size_exceeded(usize::MAX), None);
+    }
+
+    #[test]
+    fn source_size_at_limit_allowed() {
+        let config = Config {
+            max_source_bytes: Some(1024),
+            ..Config::empty()
+        };
+        assert_eq!(config.source_size_exceeded(1024), None);
+    }
+
+    #[test]
+    fn source_size_over_limit_rejected() {
+        let config = Config {
+            max_source_bytes: Some(1024),
+            ..Config::empty()
+        };
+        assert_eq!(config.source_size_exceeded(1025), Some(1024));
+    }
+
+    #[test]
+    fn cg_root_mismatch_message() {
+        let err = IsolateError::CgRootMismatch {
+            configured: PathBuf::from("/sys/fs/cgroup/isolate"),
+            isolate: PathBuf::from("/sys/fs/cgroup/isolate.slice"),
+        };
+        assert_eq!(
+            err.to_string(),
+            "cgroup root mismatch: silicube is configured with /sys/fs/cgroup/isolate \
+             but isolate uses /sys/fs/cgroup/isolate.slice"
+        );
+    }
+
     #[test]
     fn effective_limits_no_override() {
         let config = Config::default();
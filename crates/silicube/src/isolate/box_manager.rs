@@ -2,14 +2,36 @@
 //!
 //! Manages the initialization, use, and cleanup of Isolate sandbox boxes.
 
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
 
+use sha2::{Digest, Sha256, Sha512};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
-use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
 use tracing::{debug, instrument, warn};
 
 use crate::isolate::IsolateError;
 use crate::isolate::command::{IsolateAction, IsolateCommand};
+use crate::types::HashAlgo;
+
+/// Maximum subdirectory depth descended by [`IsolateBox::list_files`]
+pub(crate) const MAX_LIST_DEPTH: usize = 8;
+
+/// Chunk size used when streaming files into a hasher
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Outcome of `isolate --init` for a box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitInfo {
+    /// Path to the box directory reported by isolate
+    pub box_path: PathBuf,
+
+    /// Whether the box was initialized with cgroup support
+    pub cgroup_enabled: bool,
+}
 
 /// An Isolate sandbox
 ///
@@ -34,8 +56,8 @@ pub struct IsolateBox {
     /// Box ID
     id: u32,
 
-    /// Path to the box directory
-    box_path: PathBuf,
+    /// Init outcome, including the box directory
+    init: InitInfo,
 
     /// Path to the isolate binary
     isolate_path: PathBuf,
@@ -43,51 +65,83 @@ pub struct IsolateBox {
     /// Whether the box is initialized
     initialized: bool,
 
-    /// Whether cgroup support is enabled
-    cgroup: bool,
+    /// Label attached to tracing spans for this box
+    label: Option<String>,
 
     /// Pool permit (if acquired from a pool)
-    _permit: Option<OwnedSemaphorePermit>,
+    _permit: Option<PoolPermit>,
 }
 
 impl IsolateBox {
     /// Initialize a new isolate box
-    #[instrument(skip(isolate_path))]
     pub async fn init(
         id: u32,
         isolate_path: impl Into<PathBuf>,
         cgroup: bool,
     ) -> Result<Self, IsolateError> {
-        let isolate_path = isolate_path.into();
-
-        // Run `isolate --init`
-        let cmd = IsolateCommand::new(&isolate_path, id)
-            .action(IsolateAction::Init)
-            .cgroup(cgroup);
-        let args = cmd.build();
-
-        debug!(?args, "initializing isolate box");
-
-        let program = args
-            .first()
-            .ok_or_else(|| IsolateError::CommandFailed("empty command arguments".to_string()))?;
-        let output = Command::new(program)
-            .args(&args[1..])
-            .output()
-            .await
-            .map_err(IsolateError::SpawnFailed)?;
+        Self::init_with_box_root(id, isolate_path, cgroup, None).await
+    }
+
+    /// Initialize a new isolate box, optionally overriding its host path
+    ///
+    /// Normally the box path is taken from what `isolate --init` prints. In
+    /// chrooted or containerized installs that path can differ from the one
+    /// visible to this process; pass the host-visible box root (isolate's
+    /// `box_root`) as `box_root_override` to use `{override}/{id}` instead.
+    pub async fn init_with_box_root(
+        id: u32,
+        isolate_path: impl Into<PathBuf>,
+        cgroup: bool,
+        box_root_override: Option<&Path>,
+    ) -> Result<Self, IsolateError> {
+        Self::init_box(id, isolate_path.into(), cgroup, box_root_override, false).await
+    }
+
+    /// Initialize a new isolate box, cleaning up a stale box with the same ID
+    ///
+    /// If `isolate --init` reports that the box already exists (for example
+    /// after a crash skipped cleanup), runs `isolate --cleanup` once and
+    /// retries the init.
+    pub async fn init_with_clean_retry(
+        id: u32,
+        isolate_path: impl Into<PathBuf>,
+        cgroup: bool,
+    ) -> Result<Self, IsolateError> {
+        Self::init_box(id, isolate_path.into(), cgroup, None, true).await
+    }
+
+    #[instrument(skip(isolate_path))]
+    async fn init_box(
+        id: u32,
+        isolate_path: PathBuf,
+        cgroup: bool,
+        box_root_override: Option<&Path>,
+        clean_retry: bool,
+    ) -> Result<Self, IsolateError> {
+        let mut output = run_action(&isolate_path, id, cgroup, IsolateAction::Init).await?;
+
+        if !output.status.success()
+            && clean_retry
+            && is_stale_box_error(&String::from_utf8_lossy(&output.stderr))
+        {
+            warn!(id, "box already exists, cleaning up and retrying init");
+            let cleanup = run_action(&isolate_path, id, cgroup, IsolateAction::Cleanup).await?;
+            if !cleanup.status.success() {
+                return Err(IsolateError::CleanupFailed {
+                    id,
+                    message: String::from_utf8_lossy(&cleanup.stderr).to_string(),
+                });
+            }
+            output = run_action(&isolate_path, id, cgroup, IsolateAction::Init).await?;
+        }
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(IsolateError::InitFailed {
-                id,
-                message: stderr.to_string(),
-            });
+            return Err(init_error(id, &stderr));
         }
 
-        // Parse box path from stdout
         let stdout = String::from_utf8_lossy(&output.stdout);
-        let box_path = PathBuf::from(stdout.trim());
+        let box_path = box_path_for(id, &stdout, box_root_override);
 
         if !box_path.exists() {
             return Err(IsolateError::InitFailed {
@@ -96,14 +150,18 @@ impl IsolateBox {
             });
         }
 
-        debug!(?box_path, "box initialized");
+        let init = InitInfo {
+            box_path,
+            cgroup_enabled: cgroup,
+        };
+        debug!(box_path = ?init.box_path, cgroup = init.cgroup_enabled, "box initialized");
 
         Ok(Self {
             id,
-            box_path,
+            init,
             isolate_path,
             initialized: true,
-            cgroup,
+            label: None,
             _permit: None,
         })
     }
@@ -113,9 +171,30 @@ impl IsolateBox {
         self.id
     }
 
+    /// Label this box, e.g. with a submission ID
+    ///
+    /// The label is recorded on the tracing spans of this box's operations
+    /// and of runner calls using it, so log lines for one submission can be
+    /// correlated across compile, run, and cleanup.
+    #[must_use]
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Get the box label, if set
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
     /// Get the path to the box directory (where files can be placed)
     pub fn path(&self) -> &Path {
-        &self.box_path
+        &self.init.box_path
+    }
+
+    /// Get the outcome of initializing this box
+    pub fn init_info(&self) -> &InitInfo {
+        &self.init
     }
 
     /// Get the host path to a file inside the box
@@ -129,7 +208,7 @@ impl IsolateBox {
                 name
             )));
         }
-        Ok(self.box_path.join("box").join(name))
+        Ok(self.init.box_path.join("box").join(name))
     }
 
     /// Get the sandbox-internal path for a file inside the box
@@ -147,13 +226,15 @@ impl IsolateBox {
         Ok(PathBuf::from("/box").join(name))
     }
 
-    /// Get the path to the isolate binary
+    /// Get the path to the isolate binary that created this box
+    ///
+    /// Runner commands for this box use the same binary.
     pub fn isolate_path(&self) -> &Path {
         &self.isolate_path
     }
 
     /// Write a file into the box
-    #[instrument(skip(self, content))]
+    #[instrument(skip(self, content), fields(id = self.id, label = self.label.as_deref()))]
     pub async fn write_file(&self, name: &str, content: &[u8]) -> Result<(), IsolateError> {
         let path = self.file_path(name)?;
 
@@ -167,8 +248,79 @@ impl IsolateBox {
         Ok(())
     }
 
+    /// Stream a reader into a file in the box
+    ///
+    /// Copies in chunks, so the data is never held in memory whole. On a
+    /// read error the partial file is removed. Returns the bytes written.
+    #[instrument(skip(self, reader), fields(id = self.id, label = self.label.as_deref()))]
+    pub async fn write_file_from<R: AsyncRead + Unpin>(
+        &self,
+        name: &str,
+        mut reader: R,
+    ) -> Result<u64, IsolateError> {
+        let path = self.file_path(name)?;
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = tokio::fs::File::create(&path).await?;
+        let written = match tokio::io::copy(&mut reader, &mut file).await {
+            Ok(len) => file.flush().await.map(|()| len),
+            Err(e) => Err(e),
+        };
+
+        match written {
+            Ok(len) => {
+                debug!(?path, len, "streamed file into box");
+                Ok(len)
+            }
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&path).await;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Decompress gzip data into a file in the box
+    ///
+    /// The file is written as it is decompressed, so large inputs are never
+    /// held in memory whole. On invalid gzip data the partial file is removed
+    /// and an [`IsolateError::Io`] is returned; data that decompresses to more
+    /// than [`MAX_GUNZIP_BYTES`](crate::isolate::MAX_GUNZIP_BYTES) fails with
+    /// [`IsolateError::DecompressedTooLarge`].
+    #[cfg(feature = "gzip")]
+    #[instrument(skip(self, gz_bytes), fields(id = self.id, label = self.label.as_deref()))]
+    pub async fn write_file_gz(&self, name: &str, gz_bytes: &[u8]) -> Result<(), IsolateError> {
+        let path = self.file_path(name)?;
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let gz_bytes = gz_bytes.to_vec();
+        let target = path.clone();
+        let written = tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::File::create(&target)?;
+            gunzip_into(&gz_bytes, &mut file, crate::isolate::MAX_GUNZIP_BYTES)
+        })
+        .await
+        .map_err(std::io::Error::other)?;
+
+        match written {
+            Ok(len) => {
+                debug!(?path, len, "decompressed file into box");
+                Ok(())
+            }
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&path).await;
+                Err(e)
+            }
+        }
+    }
+
     /// Read a file from the box
-    #[instrument(skip(self))]
+    #[instrument(skip(self), fields(id = self.id, label = self.label.as_deref()))]
     pub async fn read_file(&self, name: &str) -> Result<Vec<u8>, IsolateError> {
         let path = self.file_path(name)?;
         let content = tokio::fs::read(&path).await?;
@@ -176,12 +328,110 @@ impl IsolateBox {
         Ok(content)
     }
 
+    /// Copy a file from this box into another box, preserving its permissions
+    #[instrument(skip(self, dest), fields(id = self.id, label = self.label.as_deref(), dest = dest.id))]
+    pub async fn copy_file_to(&self, name: &str, dest: &IsolateBox) -> Result<(), IsolateError> {
+        let content = self.read_file(name).await?;
+        dest.write_file(name, &content).await?;
+
+        let permissions = tokio::fs::metadata(self.file_path(name)?)
+            .await?
+            .permissions();
+        tokio::fs::set_permissions(dest.file_path(name)?, permissions).await?;
+        Ok(())
+    }
+
+    /// Compute the hex-encoded hash of a file in the box
+    ///
+    /// The file is streamed rather than read into memory at once, so this is
+    /// suitable for large compiled artifacts.
+    #[instrument(skip(self), fields(id = self.id, label = self.label.as_deref()))]
+    pub async fn file_hash(&self, name: &str, algo: HashAlgo) -> Result<String, IsolateError> {
+        let path = self.file_path(name)?;
+        let mut file = tokio::fs::File::open(&path).await?;
+        let digest = match algo {
+            HashAlgo::Sha256 => digest_reader::<Sha256>(&mut file).await?,
+            HashAlgo::Sha512 => digest_reader::<Sha512>(&mut file).await?,
+        };
+        Ok(digest.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
+
     /// Check if a file exists in the box
     pub async fn file_exists(&self, name: &str) -> Result<bool, IsolateError> {
         let path = self.file_path(name)?;
         Ok(tokio::fs::metadata(&path).await.is_ok())
     }
 
+    /// List the files in the box's `/box` directory
+    ///
+    /// Returns paths relative to `/box` (e.g. `main.cpp`, `out/result.txt`),
+    /// sorted. Subdirectories are descended up to 8 levels deep; directories
+    /// themselves are not included.
+    #[instrument(skip(self), fields(id = self.id, label = self.label.as_deref()))]
+    pub async fn list_files(&self) -> Result<Vec<String>, IsolateError> {
+        let files = list_files_under(&self.init.box_path.join("box")).await?;
+        debug!(count = files.len(), "listed box files");
+        Ok(files)
+    }
+
+    /// Total size in bytes of the files under the box's `/box` directory
+    ///
+    /// Includes silicube's own scratch files such as `stdout.txt`. Symlinks
+    /// are counted by their own size and not followed.
+    #[instrument(skip(self), fields(id = self.id, label = self.label.as_deref()))]
+    pub async fn disk_usage(&self) -> Result<u64, IsolateError> {
+        let mut total = 0;
+        let mut pending = vec![self.init.box_path.join("box")];
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = tokio::fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let metadata = entry.metadata().await?;
+                if metadata.is_dir() {
+                    pending.push(entry.path());
+                } else {
+                    total += metadata.len();
+                }
+            }
+        }
+
+        debug!(total, "measured box disk usage");
+        Ok(total)
+    }
+
+    /// Remove everything under the box's `/box` directory
+    ///
+    /// The box stays initialized and can be reused for another run without a
+    /// full cleanup and init. Symlinks are removed rather than followed, so a
+    /// program cannot use them to make reset delete files outside `/box`.
+    #[instrument(skip(self), fields(id = self.id, label = self.label.as_deref()))]
+    pub async fn reset(&self) -> Result<(), IsolateError> {
+        let root = self.init.box_path.join("box");
+        if !tokio::fs::symlink_metadata(&root).await?.is_dir() {
+            return Err(IsolateError::InvalidPath(format!(
+                "box directory is not a directory: {}",
+                root.display()
+            )));
+        }
+
+        let mut removed = 0;
+        let mut entries = tokio::fs::read_dir(&root).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            // `file_type` does not follow symlinks, and `remove_dir_all`
+            // removes links inside the tree without following them
+            if entry.file_type().await?.is_dir() {
+                tokio::fs::remove_dir_all(&path).await?;
+            } else {
+                tokio::fs::remove_file(&path).await?;
+            }
+            removed += 1;
+        }
+
+        debug!(removed, "reset box directory");
+        Ok(())
+    }
+
     /// Clean up the box
     ///
     /// This method should always be called before dropping the box to ensure
@@ -192,27 +442,19 @@ impl IsolateBox {
     ///
     /// Returns an error if the isolate cleanup command fails.
     #[must_use = "cleanup errors should be handled"]
-    #[instrument(skip(self))]
+    #[instrument(skip(self), fields(id = self.id, label = self.label.as_deref()))]
     pub async fn cleanup(&mut self) -> Result<(), IsolateError> {
         if !self.initialized {
             return Ok(());
         }
 
-        let cmd = IsolateCommand::new(&self.isolate_path, self.id)
-            .action(IsolateAction::Cleanup)
-            .cgroup(self.cgroup);
-        let args = cmd.build();
-
-        debug!(?args, "cleaning up isolate box");
-
-        let program = args
-            .first()
-            .ok_or_else(|| IsolateError::CommandFailed("empty command arguments".to_string()))?;
-        let output = Command::new(program)
-            .args(&args[1..])
-            .output()
-            .await
-            .map_err(IsolateError::SpawnFailed)?;
+        let output = run_action(
+            &self.isolate_path,
+            self.id,
+            self.init.cgroup_enabled,
+            IsolateAction::Cleanup,
+        )
+        .await?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -228,8 +470,32 @@ impl IsolateBox {
         Ok(())
     }
 
+    /// Run `isolate --cleanup` and `--init` again for this box
+    ///
+    /// Cleanup kills anything still running in the box, which is what reaps
+    /// a sandboxed program orphaned by killing isolate. The box keeps its ID
+    /// and path but loses its files.
+    #[instrument(skip(self), fields(id = self.id, label = self.label.as_deref()))]
+    pub(crate) async fn reinit(&self) -> Result<(), IsolateError> {
+        let cgroup = self.init.cgroup_enabled;
+        let cleanup =
+            run_action(&self.isolate_path, self.id, cgroup, IsolateAction::Cleanup).await?;
+        if !cleanup.status.success() {
+            return Err(IsolateError::CleanupFailed {
+                id: self.id,
+                message: String::from_utf8_lossy(&cleanup.stderr).to_string(),
+            });
+        }
+        let init = run_action(&self.isolate_path, self.id, cgroup, IsolateAction::Init).await?;
+        if !init.status.success() {
+            return Err(init_error(self.id, &String::from_utf8_lossy(&init.stderr)));
+        }
+        debug!("box re-initialized");
+        Ok(())
+    }
+
     /// Attach a pool permit to this box
-    pub(crate) fn with_permit(mut self, permit: OwnedSemaphorePermit) -> Self {
+    pub(crate) fn with_permit(mut self, permit: PoolPermit) -> Self {
         self._permit = Some(permit);
         self
     }
@@ -247,7 +513,7 @@ impl Drop for IsolateBox {
             // Callers should explicitly call cleanup() before dropping
             warn!(
                 box_id = self.id,
-                box_path = %self.box_path.display(),
+                box_path = %self.init.box_path.display(),
                 "IsolateBox dropped without explicit cleanup! \
                  Call cleanup() before dropping to ensure proper resource release. \
                  Attempting best-effort cleanup via spawned thread (may not complete)."
@@ -258,7 +524,7 @@ impl Drop for IsolateBox {
             // before process exit, leading to leaked sandbox resources
             let isolate_path = self.isolate_path.clone();
             let id = self.id;
-            let cgroup = self.cgroup;
+            let cgroup = self.init.cgroup_enabled;
 
             std::thread::spawn(move || {
                 let cmd = IsolateCommand::new(&isolate_path, id)
@@ -292,6 +558,180 @@ impl Drop for IsolateBox {
     }
 }
 
+/// Determine a box's host path from `isolate --init` output or an override
+///
+/// The returned path is the box directory containing `box/`.
+fn box_path_for(id: u32, init_stdout: &str, box_root_override: Option<&Path>) -> PathBuf {
+    match box_root_override {
+        Some(root) => root.join(id.to_string()),
+        None => PathBuf::from(init_stdout.trim()),
+    }
+}
+
+/// Run `isolate --init` or `isolate --cleanup` for a box
+async fn run_action(
+    isolate_path: &Path,
+    id: u32,
+    cgroup: bool,
+    action: IsolateAction,
+) -> Result<std::process::Output, IsolateError> {
+    let args = IsolateCommand::new(isolate_path, id)
+        .action(action)
+        .cgroup(cgroup)
+        .build();
+
+    debug!(?args, "running isolate box command");
+
+    let program = args
+        .first()
+        .ok_or_else(|| IsolateError::CommandFailed("empty command arguments".to_string()))?;
+    Command::new(program)
+        .args(&args[1..])
+        .output()
+        .await
+        .map_err(IsolateError::SpawnFailed)
+}
+
+/// Decompress gzip data in memory, up to `max_bytes`
+#[cfg(feature = "gzip")]
+pub(crate) async fn gunzip(gz_bytes: &[u8], max_bytes: u64) -> Result<Vec<u8>, IsolateError> {
+    let gz_bytes = gz_bytes.to_vec();
+    tokio::task::spawn_blocking(move || {
+        let mut data = Vec::new();
+        gunzip_into(&gz_bytes, &mut data, max_bytes)?;
+        Ok(data)
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Decompress gzip data into `out`, failing past `max_bytes`
+#[cfg(feature = "gzip")]
+fn gunzip_into(
+    gz_bytes: &[u8],
+    out: &mut impl std::io::Write,
+    max_bytes: u64,
+) -> Result<u64, IsolateError> {
+    use std::io::Read;
+
+    // Read one byte past the limit to detect overflow
+    let mut decoder = flate2::read::GzDecoder::new(gz_bytes).take(max_bytes + 1);
+    let len = std::io::copy(&mut decoder, out)?;
+    if len > max_bytes {
+        return Err(IsolateError::DecompressedTooLarge { limit: max_bytes });
+    }
+    Ok(len)
+}
+
+/// Hash everything read from `reader` with digest `D`
+async fn digest_reader<D: Digest>(
+    reader: &mut (impl AsyncReadExt + Unpin),
+) -> std::io::Result<Vec<u8>> {
+    let mut hasher = D::new();
+    let mut buf = vec![0u8; HASH_CHUNK_SIZE];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// List the files under `root` as sorted relative paths
+///
+/// Subdirectories are descended up to 8 levels deep; directories themselves
+/// are not included.
+pub(crate) async fn list_files_under(root: &Path) -> Result<Vec<String>, IsolateError> {
+    let mut files = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+
+    while let Some((dir, depth)) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if entry.file_type().await?.is_dir() {
+                if depth < MAX_LIST_DEPTH {
+                    pending.push((path, depth + 1));
+                }
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_string_lossy().into_owned());
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Stderr fragments isolate prints when it lacks root privileges
+///
+/// Only isolate's own wording; generic errno text such as "permission
+/// denied" also comes from unrelated failures (a read-only box root, a bad
+/// mount) that root would not fix.
+const NEEDS_ROOT_MESSAGES: [&str; 1] = ["must be started as root"];
+
+/// Substrings of `isolate --init` errors for a box left over from an earlier run
+const STALE_BOX_MESSAGES: [&str; 2] = ["already exists", "already initialized"];
+
+/// Check whether a failed init was caused by a box that was never cleaned up
+fn is_stale_box_error(stderr: &str) -> bool {
+    let lower = stderr.to_lowercase();
+    STALE_BOX_MESSAGES.iter().any(|msg| lower.contains(msg))
+}
+
+/// Map a failed `isolate --init` to an error, recognizing permission failures
+fn init_error(id: u32, stderr: &str) -> IsolateError {
+    let lower = stderr.to_lowercase();
+    if NEEDS_ROOT_MESSAGES.iter().any(|msg| lower.contains(msg)) {
+        return IsolateError::NeedsRoot {
+            message: stderr.trim().to_string(),
+        };
+    }
+    IsolateError::InitFailed {
+        id,
+        message: stderr.to_string(),
+    }
+}
+
+/// Box IDs currently leased by pools in this process
+///
+/// In cgroup mode isolate creates one child cgroup per box ID under `cg_root`,
+/// so two live boxes sharing an ID would corrupt each other's accounting.
+static LEASED_IDS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+/// Exclusive claim on a box ID, released on drop
+#[derive(Debug)]
+struct BoxIdLease {
+    id: u32,
+}
+
+impl BoxIdLease {
+    /// Claim a box ID, failing if it is already leased in this process
+    fn claim(id: u32) -> Result<Self, IsolateError> {
+        let mut leased = LEASED_IDS.lock().unwrap_or_else(PoisonError::into_inner);
+        if !leased.insert(id) {
+            return Err(IsolateError::BoxInUse(id));
+        }
+        Ok(Self { id })
+    }
+}
+
+impl Drop for BoxIdLease {
+    fn drop(&mut self) {
+        let mut leased = LEASED_IDS.lock().unwrap_or_else(PoisonError::into_inner);
+        leased.remove(&self.id);
+    }
+}
+
+/// Resources a pooled box holds until it is dropped
+#[derive(Debug)]
+pub(crate) struct PoolPermit {
+    _permit: OwnedSemaphorePermit,
+    _lease: BoxIdLease,
+}
+
 /// Pool of isolate boxes for concurrent execution
 #[derive(Debug)]
 pub struct BoxPool {
@@ -307,6 +747,18 @@ pub struct BoxPool {
     /// Whether cgroup support is enabled
     cgroup: bool,
 
+    /// Host-visible box root used instead of isolate's reported path
+    box_root_override: Option<PathBuf>,
+
+    /// Clean up and retry once when a box is left over from an earlier run
+    init_clean_retry: bool,
+
+    /// Maximum number of `acquire` calls allowed to wait for a free box
+    max_waiters: Option<usize>,
+
+    /// Number of `acquire` calls currently waiting for a free box
+    waiters: AtomicUsize,
+
     /// Semaphore to limit concurrent boxes
     semaphore: std::sync::Arc<Semaphore>,
 
@@ -322,34 +774,115 @@ impl BoxPool {
             count,
             isolate_path: isolate_path.into(),
             cgroup,
+            box_root_override: None,
+            init_clean_retry: false,
+            max_waiters: None,
+            waiters: AtomicUsize::new(0),
             semaphore: std::sync::Arc::new(Semaphore::new(count as usize)),
             next_id: std::sync::atomic::AtomicU32::new(start_id),
         }
     }
 
+    /// Compute box paths as `{root}/{id}` instead of trusting isolate's output
+    ///
+    /// See [`IsolateBox::init_with_box_root`] for when this is needed.
+    #[must_use]
+    pub fn with_box_root_override(mut self, root: impl Into<PathBuf>) -> Self {
+        self.box_root_override = Some(root.into());
+        self
+    }
+
+    /// Clean up and retry once when `isolate --init` finds a stale box
+    ///
+    /// See [`IsolateBox::init_with_clean_retry`].
+    #[must_use]
+    pub fn with_init_clean_retry(mut self, enable: bool) -> Self {
+        self.init_clean_retry = enable;
+        self
+    }
+
+    /// Bound how many `acquire` calls may wait for a free box
+    ///
+    /// Once `limit` callers are waiting, further calls fail immediately with
+    /// [`IsolateError::PoolExhausted`] instead of queueing. Zero disables
+    /// waiting entirely. Unbounded by default.
+    #[must_use]
+    pub fn with_max_waiters(mut self, limit: usize) -> Self {
+        self.max_waiters = Some(limit);
+        self
+    }
+
+    /// Acquire a box from the pool and label it
+    ///
+    /// Like [`acquire`](Self::acquire), with [`IsolateBox::with_label`]
+    /// applied to the returned box.
+    #[instrument(skip(self))]
+    pub async fn acquire_labeled(&self, label: &str) -> Result<IsolateBox, IsolateError> {
+        Ok(self.acquire().await?.with_label(label))
+    }
+
     /// Acquire a box from the pool
+    ///
+    /// Waits for a free box if all are in use, unless the waiter backlog set
+    /// with [`with_max_waiters`](Self::with_max_waiters) is full.
     #[instrument(skip(self))]
     pub async fn acquire(&self) -> Result<IsolateBox, IsolateError> {
-        // Wait for a permit
-        let permit = self
-            .semaphore
-            .clone()
-            .acquire_owned()
-            .await
-            .map_err(|_| IsolateError::PoolExhausted)?;
+        let permit = match self.semaphore.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(TryAcquireError::NoPermits) => {
+                let _waiter = WaiterSlot::enter(&self.waiters, self.max_waiters)?;
+                self.semaphore
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| IsolateError::PoolExhausted)?
+            }
+            Err(TryAcquireError::Closed) => return Err(IsolateError::PoolExhausted),
+        };
 
         // Get next box ID
-        let id = self
-            .next_id
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        let id = self.start_id + (id - self.start_id) % self.count;
+        let lease = self.claim_next_id()?;
+        let id = lease.id;
 
         debug!(id, "acquired box from pool");
 
         // Initialize the box
-        let sandbox = IsolateBox::init(id, &self.isolate_path, self.cgroup).await?;
+        let sandbox = IsolateBox::init_box(
+            id,
+            self.isolate_path.clone(),
+            self.cgroup,
+            self.box_root_override.as_deref(),
+            self.init_clean_retry,
+        )
+        .await?;
+
+        Ok(sandbox.with_permit(PoolPermit {
+            _permit: permit,
+            _lease: lease,
+        }))
+    }
 
-        Ok(sandbox.with_permit(permit))
+    /// Claim the next box ID in round-robin order that is not already in use
+    ///
+    /// Boxes can be released out of order, so the next ID in sequence may
+    /// still be held. Those are skipped; if every ID in the range is held
+    /// (e.g. by another pool with an overlapping range) this fails with
+    /// [`IsolateError::BoxInUse`].
+    fn claim_next_id(&self) -> Result<BoxIdLease, IsolateError> {
+        let mut first = None;
+        for _ in 0..self.count {
+            let id = self
+                .next_id
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let id = self.start_id + (id - self.start_id) % self.count;
+            match BoxIdLease::claim(id) {
+                Ok(lease) => return Ok(lease),
+                Err(_) => {
+                    first.get_or_insert(id);
+                }
+            }
+        }
+        Err(IsolateError::BoxInUse(first.unwrap_or(self.start_id)))
     }
 
     /// Get the number of available boxes
@@ -361,10 +894,160 @@ impl BoxPool {
     pub fn capacity(&self) -> u32 {
         self.count
     }
+
+    /// Snapshot the pool's capacity, free boxes and waiting callers
+    pub fn metrics(&self) -> PoolMetrics {
+        PoolMetrics {
+            capacity: self.count,
+            available: self.available(),
+            waiting: self.waiters.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Point-in-time usage of a [`BoxPool`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Total number of boxes in the pool
+    pub capacity: u32,
+
+    /// Boxes not currently acquired
+    pub available: usize,
+
+    /// `acquire` calls waiting for a free box
+    pub waiting: usize,
+}
+
+/// A counted place in a pool's waiter backlog, released on drop
+///
+/// Dropping on cancellation keeps the count accurate when an `acquire`
+/// future is abandoned while waiting.
+struct WaiterSlot<'a> {
+    waiters: &'a AtomicUsize,
+}
+
+impl<'a> WaiterSlot<'a> {
+    /// Join the backlog, failing if `max_waiters` callers already wait
+    fn enter(waiters: &'a AtomicUsize, max_waiters: Option<usize>) -> Result<Self, IsolateError> {
+        waiters
+            .fetch_update(
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+                |waiting| match max_waiters {
+                    Some(limit) if waiting >= limit => None,
+                    _ => Some(waiting + 1),
+                },
+            )
+            .map_err(|_| IsolateError::PoolExhausted)?;
+        Ok(Self { waiters })
+    }
+}
+
+impl Drop for WaiterSlot<'_> {
+    fn drop(&mut self) {
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Box pools for several resource classes, selected by name
+///
+/// Lets operators give each class its own box-id range and concurrency,
+/// e.g. ids 0-9 for "fast" runs with small limits and 10-19 for "heavy"
+/// ones. Ranges should not overlap; a box ID is never handed out twice at
+/// once, so overlapping pools would starve each other.
+#[derive(Debug, Default)]
+pub struct MultiPool {
+    pools: HashMap<String, Arc<BoxPool>>,
+}
+
+impl MultiPool {
+    /// Create a multi-pool with no classes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the pool for `class`
+    #[must_use]
+    pub fn with_class(mut self, class: impl Into<String>, pool: BoxPool) -> Self {
+        self.pools.insert(class.into(), Arc::new(pool));
+        self
+    }
+
+    /// Get the pool for `class`
+    ///
+    /// The pool is shared, so it can also be handed to `Runner::with_pool`.
+    pub fn pool(&self, class: &str) -> Option<&Arc<BoxPool>> {
+        self.pools.get(class)
+    }
+
+    /// Iterate over the configured class names
+    pub fn classes(&self) -> impl Iterator<Item = &str> {
+        self.pools.keys().map(String::as_str)
+    }
+
+    /// Acquire a box from the pool for `class`
+    pub async fn acquire(&self, class: &str) -> Result<IsolateBox, IsolateError> {
+        self.pool(class)
+            .ok_or_else(|| IsolateError::UnknownPoolClass(class.to_string()))?
+            .acquire()
+            .await
+    }
+}
+
+#[cfg(test)]
+/// Write a stand-in isolate that fails `--init` while `stale` exists
+///
+/// `--cleanup` removes `stale`; every action is appended to `log`. `--run`
+/// writes an empty meta file and exits successfully. If a `delay` file
+/// exists, each run sleeps that many seconds between appending `start` and
+/// `end` to `spans`, so overlapping runs show up interleaved.
+pub(crate) fn fake_isolate(name: &str, stale: bool) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!(
+        "silicube-fake-isolate-{name}-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(dir.join("box")).unwrap();
+    let script = dir.join("isolate");
+    std::fs::write(
+        &script,
+        format!(
+            r#"#!/bin/sh
+dir='{dir}'
+case "$*" in
+    *--cleanup*) echo cleanup >> "$dir/log"; rm -f "$dir/stale" ;;
+    *--init*)
+        echo init >> "$dir/log"
+        if [ -e "$dir/stale" ]; then echo "Box already exists" >&2; exit 2; fi
+        echo "$dir" ;;
+    *--run*)
+        echo run >> "$dir/log"
+        if [ -e "$dir/delay" ]; then
+            echo start >> "$dir/spans"
+            sleep "$(cat "$dir/delay")"
+            echo end >> "$dir/spans"
+        fi
+        for arg in "$@"; do
+            case "$arg" in --meta=*) : > "${{arg#--meta=}}" ;; esac
+        done ;;
+esac
+"#,
+            dir = dir.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    if stale {
+        std::fs::write(dir.join("stale"), "").unwrap();
+    }
+    script
 }
 
 #[cfg(test)]
 mod tests {
+    use tracing_test::traced_test;
+
     use super::*;
 
     // Note: These tests require the isolate binary and root privileges.
@@ -395,15 +1078,239 @@ mod tests {
         sandbox.cleanup().await.unwrap();
     }
 
+    #[test]
+    fn test_init_error_needs_root() {
+        let err = init_error(0, "Must be started as root\n");
+        match err {
+            IsolateError::NeedsRoot { message } => {
+                assert_eq!(message, "Must be started as root")
+            }
+            other => panic!("expected NeedsRoot, got {other:?}"),
+        }
+
+        // Generic permission errors are reported as plain init failures
+        let err = init_error(
+            0,
+            "Cannot create /var/local/lib/isolate/0: Permission denied",
+        );
+        assert!(matches!(err, IsolateError::InitFailed { id: 0, .. }));
+        let err = init_error(0, "mount: Operation not permitted");
+        assert!(matches!(err, IsolateError::InitFailed { id: 0, .. }));
+    }
+
+    #[test]
+    fn test_init_error_other_failure() {
+        let err = init_error(3, "Box 3 is currently in use by another process");
+        assert!(matches!(err, IsolateError::InitFailed { id: 3, .. }));
+    }
+
+    #[test]
+    fn test_is_stale_box_error() {
+        assert!(is_stale_box_error("Box 7 already exists\n"));
+        assert!(!is_stale_box_error(
+            "Box 3 is currently in use by another process"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_init_clean_retry_recovers_stale_box() {
+        let isolate = fake_isolate("retry", true);
+        let dir = isolate.parent().unwrap().to_path_buf();
+
+        let mut sandbox = IsolateBox::init_with_clean_retry(7, &isolate, false)
+            .await
+            .unwrap();
+        assert_eq!(sandbox.path(), dir);
+        let log = std::fs::read_to_string(dir.join("log")).unwrap();
+        assert_eq!(log, "init\ncleanup\ninit\n");
+
+        sandbox.cleanup().await.unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_label_recorded_on_cleanup_span() {
+        let isolate = fake_isolate("label", false);
+        let dir = isolate.parent().unwrap().to_path_buf();
+
+        let mut sandbox = IsolateBox::init(8, &isolate, false)
+            .await
+            .unwrap()
+            .with_label("submission-42");
+        assert_eq!(sandbox.label(), Some("submission-42"));
+        sandbox.cleanup().await.unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+
+        logs_assert(|lines: &[&str]| {
+            lines
+                .iter()
+                .find(|line| line.contains("box cleaned up"))
+                .filter(|line| line.contains(r#"cleanup{id=8 label="submission-42"}"#))
+                .map(|_| ())
+                .ok_or_else(|| format!("no labeled cleanup line in {lines:#?}"))
+        });
+    }
+
+    #[tokio::test]
+    async fn test_init_without_clean_retry_fails_on_stale_box() {
+        let isolate = fake_isolate("no-retry", true);
+        let dir = isolate.parent().unwrap().to_path_buf();
+
+        let err = IsolateBox::init(7, &isolate, false).await.unwrap_err();
+        assert!(matches!(err, IsolateError::InitFailed { id: 7, .. }));
+        let log = std::fs::read_to_string(dir.join("log")).unwrap();
+        assert_eq!(log, "init\n");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// `gzip.compress(b"3\n1 2\n10 20\n-5 15\n", mtime=0)`
+    #[cfg(feature = "gzip")]
+    const GZ_INPUT: [u8; 38] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x33, 0xe6, 0x32, 0x54, 0x30,
+        0xe2, 0x32, 0x34, 0x50, 0x30, 0x32, 0xe0, 0xd2, 0x35, 0x55, 0x30, 0x34, 0xe5, 0x02, 0x00,
+        0x62, 0xd9, 0x4b, 0xb7, 0x12, 0x00, 0x00, 0x00,
+    ];
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn test_write_file_gz() {
+        let isolate = fake_isolate("gzip", false);
+        let mut sandbox = IsolateBox::init(9320, &isolate, false).await.unwrap();
+
+        sandbox.write_file_gz("input.txt", &GZ_INPUT).await.unwrap();
+        let content = sandbox.read_file("input.txt").await.unwrap();
+        assert_eq!(content, b"3\n1 2\n10 20\n-5 15\n");
+
+        let err = sandbox
+            .write_file_gz("bad.txt", b"not gzip")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, IsolateError::Io(_)));
+        assert!(!sandbox.file_exists("bad.txt").await.unwrap());
+
+        sandbox.cleanup().await.unwrap();
+        std::fs::remove_dir_all(isolate.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn test_gunzip() {
+        assert_eq!(
+            gunzip(&GZ_INPUT, 18).await.unwrap(),
+            b"3\n1 2\n10 20\n-5 15\n"
+        );
+        assert!(gunzip(b"not gzip", 18).await.is_err());
+        assert!(matches!(
+            gunzip(&GZ_INPUT, 17).await,
+            Err(IsolateError::DecompressedTooLarge { limit: 17 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pool_max_waiters() {
+        let isolate = fake_isolate("max-waiters", false);
+        let pool = Arc::new(BoxPool::new(9330, 1, &isolate, false).with_max_waiters(1));
+
+        let mut held = pool.acquire().await.unwrap();
+        let waiter = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.acquire().await }
+        });
+        while pool.metrics().waiting == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(
+            pool.metrics(),
+            PoolMetrics {
+                capacity: 1,
+                available: 0,
+                waiting: 1,
+            }
+        );
+
+        // The backlog is full, so this fails instead of queueing
+        let err = pool.acquire().await.unwrap_err();
+        assert!(matches!(err, IsolateError::PoolExhausted));
+
+        held.cleanup().await.unwrap();
+        drop(held);
+        let mut next = waiter.await.unwrap().unwrap();
+        assert_eq!(pool.metrics().waiting, 0);
+
+        next.cleanup().await.unwrap();
+        std::fs::remove_dir_all(isolate.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_multi_pool_acquires_from_class_range() {
+        let isolate = fake_isolate("multi-pool", false);
+        let pools = MultiPool::new()
+            .with_class("fast", BoxPool::new(9300, 2, &isolate, false))
+            .with_class("heavy", BoxPool::new(9310, 2, &isolate, false));
+
+        let mut fast = pools.acquire("fast").await.unwrap();
+        assert_eq!(fast.isolate_path(), isolate);
+        let mut heavy = pools.acquire("heavy").await.unwrap();
+        assert!((9300..9302).contains(&fast.id()));
+        assert!((9310..9312).contains(&heavy.id()));
+        assert_eq!(pools.pool("fast").unwrap().available(), 1);
+        assert_eq!(pools.pool("heavy").unwrap().available(), 1);
+
+        let err = pools.acquire("gpu").await.unwrap_err();
+        assert!(matches!(err, IsolateError::UnknownPoolClass(class) if class == "gpu"));
+
+        fast.cleanup().await.unwrap();
+        heavy.cleanup().await.unwrap();
+        std::fs::remove_dir_all(isolate.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_box_id_lease_exclusive() {
+        let lease = BoxIdLease::claim(9000).unwrap();
+        assert!(matches!(
+            BoxIdLease::claim(9000),
+            Err(IsolateError::BoxInUse(9000))
+        ));
+
+        drop(lease);
+        assert!(BoxIdLease::claim(9000).is_ok());
+    }
+
+    #[test]
+    fn test_pool_skips_ids_in_use() {
+        let pool = BoxPool::new(9100, 2, "isolate", false);
+
+        let first = pool.claim_next_id().unwrap();
+        let second = pool.claim_next_id().unwrap();
+        assert_eq!(first.id, 9100);
+        assert_eq!(second.id, 9101);
+
+        // Release out of order: the next ID in sequence (9100) is still held
+        drop(second);
+        let third = pool.claim_next_id().unwrap();
+        assert_eq!(third.id, 9101);
+
+        // Every ID is held now
+        assert!(matches!(
+            pool.claim_next_id(),
+            Err(IsolateError::BoxInUse(_))
+        ));
+    }
+
     #[test]
     fn test_file_path_validation() {
         // Create a mock IsolateBox for path validation testing
         let sandbox = IsolateBox {
             id: 0,
-            box_path: std::path::PathBuf::from("/tmp/box0"),
+            init: InitInfo {
+                box_path: std::path::PathBuf::from("/tmp/box0"),
+                cgroup_enabled: false,
+            },
             isolate_path: std::path::PathBuf::from("isolate"),
             initialized: false,
-            cgroup: false,
+            label: None,
             _permit: None,
         };
 
@@ -417,14 +1324,119 @@ mod tests {
         assert!(sandbox.file_path("/absolute/path").is_err());
     }
 
+    #[tokio::test]
+    async fn test_file_hash() {
+        let box_path = std::env::temp_dir().join(format!("silicube-hash-{}", std::process::id()));
+        std::fs::create_dir_all(box_path.join("box")).unwrap();
+        std::fs::write(box_path.join("box/hello.txt"), b"hello").unwrap();
+
+        let sandbox = IsolateBox {
+            id: 0,
+            init: InitInfo {
+                box_path: box_path.clone(),
+                cgroup_enabled: false,
+            },
+            isolate_path: std::path::PathBuf::from("isolate"),
+            initialized: false,
+            label: None,
+            _permit: None,
+        };
+        let sha256 = sandbox.file_hash("hello.txt", HashAlgo::Sha256).await;
+        let sha512 = sandbox.file_hash("hello.txt", HashAlgo::Sha512).await;
+        let missing = sandbox.file_hash("missing.txt", HashAlgo::Sha256).await;
+        std::fs::remove_dir_all(&box_path).unwrap();
+
+        assert_eq!(
+            sha256.unwrap(),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(sha512.unwrap().len(), 128);
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_file_from_reader() {
+        let box_path = std::env::temp_dir().join(format!("silicube-stream-{}", std::process::id()));
+        std::fs::create_dir_all(box_path.join("box")).unwrap();
+
+        let sandbox = IsolateBox {
+            id: 0,
+            init: InitInfo {
+                box_path: box_path.clone(),
+                cgroup_enabled: false,
+            },
+            isolate_path: std::path::PathBuf::from("isolate"),
+            initialized: false,
+            label: None,
+            _permit: None,
+        };
+        let data: Vec<u8> = (0..100_000u32).flat_map(u32::to_le_bytes).collect();
+        let written = sandbox
+            .write_file_from("stdin.txt", std::io::Cursor::new(&data))
+            .await;
+        let content = std::fs::read(box_path.join("box/stdin.txt"));
+        std::fs::remove_dir_all(&box_path).unwrap();
+
+        assert_eq!(written.unwrap(), data.len() as u64);
+        assert_eq!(content.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_disk_usage_sums_nested_files() {
+        let box_path =
+            std::env::temp_dir().join(format!("silicube-disk-usage-{}", std::process::id()));
+        std::fs::create_dir_all(box_path.join("box/nested/deeper")).unwrap();
+        std::fs::write(box_path.join("box/a.txt"), [0; 100]).unwrap();
+        std::fs::write(box_path.join("box/nested/deeper/b.bin"), [0; 4096]).unwrap();
+
+        let sandbox = IsolateBox {
+            id: 0,
+            init: InitInfo {
+                box_path: box_path.clone(),
+                cgroup_enabled: false,
+            },
+            isolate_path: std::path::PathBuf::from("isolate"),
+            initialized: false,
+            label: None,
+            _permit: None,
+        };
+        let usage = sandbox.disk_usage().await;
+        std::fs::remove_dir_all(&box_path).unwrap();
+
+        assert_eq!(usage.unwrap(), 4196);
+    }
+
+    #[test]
+    fn test_box_path_from_init_output() {
+        let path = box_path_for(3, "/var/local/lib/isolate/3\n", None);
+        assert_eq!(path, PathBuf::from("/var/local/lib/isolate/3"));
+    }
+
+    #[test]
+    fn test_box_path_with_override() {
+        let path = box_path_for(
+            3,
+            "/var/local/lib/isolate/3\n",
+            Some(Path::new("/chroot/var/local/lib/isolate")),
+        );
+        assert_eq!(path, PathBuf::from("/chroot/var/local/lib/isolate/3"));
+        assert_eq!(
+            path.join("box"),
+            PathBuf::from("/chroot/var/local/lib/isolate/3/box")
+        );
+    }
+
     #[test]
     fn test_sandbox_path() {
         let sandbox = IsolateBox {
             id: 0,
-            box_path: std::path::PathBuf::from("/var/local/lib/isolate/0"),
+            init: InitInfo {
+                box_path: std::path::PathBuf::from("/var/local/lib/isolate/0"),
+                cgroup_enabled: false,
+            },
             isolate_path: std::path::PathBuf::from("isolate"),
             initialized: false,
-            cgroup: false,
+            label: None,
             _permit: None,
         };
 
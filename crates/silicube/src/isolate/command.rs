@@ -4,9 +4,21 @@
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use crate::types::{MountConfig, ResourceLimits};
 
+/// Host-side deadline on the isolate process itself
+///
+/// Not passed to isolate; enforced by the code that spawns it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrapperTimeout {
+    /// How long isolate may run before it is signalled
+    pub timeout: Duration,
+    /// Time between SIGTERM and SIGKILL
+    pub kill_grace: Duration,
+}
+
 /// Builder for Isolate command-line arguments
 #[derive(Debug)]
 pub struct IsolateCommand {
@@ -19,11 +31,15 @@ pub struct IsolateCommand {
     /// Resource limits
     limits: ResourceLimits,
     mounts: Vec<MountConfig>,
+    /// Host directory relative mount sources are resolved against
+    mount_source_root: Option<PathBuf>,
     /// -E, --env
     env: HashMap<String, String>,
     env_inherit: Vec<String>,
     /// -e, --full-env
     full_env: bool,
+    /// --share-net
+    share_net: bool,
     /// -M, --meta
     meta_file: Option<PathBuf>,
     /// -i, --stdin
@@ -32,9 +48,16 @@ pub struct IsolateCommand {
     stdout: Option<PathBuf>,
     /// -r, --stderr
     stderr: Option<PathBuf>,
+    /// Whether stderr goes to `stderr` rather than `/dev/null`
+    capture_stderr: bool,
     working_dir: Option<String>,
+    /// --seccomp (requires an isolate build that supports it)
+    seccomp_profile: Option<PathBuf>,
     command: Vec<String>,
     cgroup: bool,
+    wrapper_timeout: Option<WrapperTimeout>,
+    /// Prefix for the scratch files the command reads and writes in the box
+    internal_file_prefix: String,
 }
 
 impl IsolateCommand {
@@ -46,16 +69,22 @@ impl IsolateCommand {
             box_id,
             limits: ResourceLimits::default(),
             mounts: Vec::new(),
+            mount_source_root: None,
             env: HashMap::new(),
             env_inherit: Vec::new(),
             full_env: false,
+            share_net: false,
             meta_file: None,
             stdin: None,
             stdout: None,
             stderr: None,
+            capture_stderr: true,
             working_dir: None,
+            seccomp_profile: None,
             command: Vec::new(),
             cgroup: false,
+            wrapper_timeout: None,
+            internal_file_prefix: String::new(),
         }
     }
 
@@ -83,6 +112,23 @@ impl IsolateCommand {
         self
     }
 
+    /// Resolve relative mount sources against `root` when building
+    ///
+    /// Absolute sources are left untouched. `None` leaves all sources as-is.
+    pub fn mount_source_root(mut self, root: Option<PathBuf>) -> Self {
+        self.mount_source_root = root;
+        self
+    }
+
+    /// Pass a seccomp profile to isolate with `--seccomp`
+    ///
+    /// Upstream isolate has no such flag; only use this with an isolate
+    /// build that accepts it. `None` omits the flag.
+    pub fn seccomp_profile(mut self, profile: Option<PathBuf>) -> Self {
+        self.seccomp_profile = profile;
+        self
+    }
+
     /// Set an environment variable
     pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
         self.env.insert(key.into(), value.into());
@@ -119,7 +165,18 @@ impl IsolateCommand {
         self
     }
 
-    /// Set the working directory inside the sandbox
+    /// Send stderr to `/dev/null` instead of the [`stderr`](Self::stderr) file
+    ///
+    /// Defaults to `true` (capture).
+    pub fn capture_stderr(mut self, capture: bool) -> Self {
+        self.capture_stderr = capture;
+        self
+    }
+
+    /// Set the working directory inside the sandbox (isolate `--chdir`)
+    ///
+    /// Any sandbox-visible absolute path is accepted; callers should check
+    /// untrusted values with [`validate_working_dir`](crate::isolate::validate_working_dir).
     pub fn working_dir(mut self, dir: impl Into<String>) -> Self {
         self.working_dir = Some(dir.into());
         self
@@ -137,12 +194,30 @@ impl IsolateCommand {
         self
     }
 
+    /// Share the host's network namespace with the sandbox
+    pub fn share_net(mut self, enable: bool) -> Self {
+        self.share_net = enable;
+        self
+    }
+
     /// Enable cgroup support for memory limiting
     pub fn cgroup(mut self, enable: bool) -> Self {
         self.cgroup = enable;
         self
     }
 
+    /// Kill isolate if it runs past a host-side deadline
+    pub fn wrapper_timeout(mut self, timeout: Option<WrapperTimeout>) -> Self {
+        self.wrapper_timeout = timeout;
+        self
+    }
+
+    /// Prefix the names of scratch files like `stdout.txt` and `meta.txt`
+    pub fn internal_file_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.internal_file_prefix = prefix.into();
+        self
+    }
+
     /// Build the command-line arguments
     ///
     /// Consumes self to avoid cloning the command vector.
@@ -164,6 +239,9 @@ impl IsolateCommand {
             IsolateAction::Cleanup => {
                 args.push("--cleanup".to_string());
             }
+            IsolateAction::PrintCgRoot => {
+                args.push("--print-cg-root".to_string());
+            }
             IsolateAction::Run => {
                 args.push("--run".to_string());
 
@@ -197,20 +275,22 @@ impl IsolateCommand {
                     args.push(format!("--open-files={open_files}"));
                 }
 
-                // Mounts
-                for mount in &self.mounts {
-                    // Skip optional mounts whose source doesn't exist
-                    if mount.optional && !std::path::Path::new(&mount.source).exists() {
-                        continue;
-                    }
-                    let mut opts = String::new();
-                    if mount.writable {
-                        opts.push_str(":rw");
-                    }
-                    if mount.optional {
-                        opts.push_str(":maybe");
-                    }
-                    args.push(format!("--dir={}={}{}", mount.target, mount.source, opts));
+                if let Some(ref profile) = self.seccomp_profile {
+                    args.push(format!("--seccomp={}", profile.display()));
+                }
+
+                // Mounts (optional mounts whose source doesn't exist are skipped)
+                args.extend(
+                    self.mounts
+                        .iter()
+                        .filter_map(|mount| match self.mount_source_root {
+                            Some(ref root) => mount.resolve_source(root).to_isolate_arg(),
+                            None => mount.to_isolate_arg(),
+                        }),
+                );
+
+                if self.share_net {
+                    args.push("--share-net".to_string());
                 }
 
                 // Environment
@@ -236,7 +316,9 @@ impl IsolateCommand {
                 if let Some(ref stdout) = self.stdout {
                     args.push(format!("--stdout={}", stdout.display()));
                 }
-                if let Some(ref stderr) = self.stderr {
+                if !self.capture_stderr {
+                    args.push("--stderr=/dev/null".to_string());
+                } else if let Some(ref stderr) = self.stderr {
                     args.push(format!("--stderr={}", stderr.display()));
                 }
 
@@ -263,6 +345,26 @@ impl IsolateCommand {
     pub fn box_id(&self) -> u32 {
         self.box_id
     }
+
+    /// Check whether cgroup support is enabled
+    pub fn cgroup_enabled(&self) -> bool {
+        self.cgroup
+    }
+
+    /// Name of a scratch file in the box, with this command's prefix
+    pub fn internal_file(&self, name: &str) -> String {
+        format!("{}{name}", self.internal_file_prefix)
+    }
+
+    /// Check whether stderr is captured to a file
+    pub fn captures_stderr(&self) -> bool {
+        self.capture_stderr
+    }
+
+    /// Get the host-side deadline, if any
+    pub fn wrapper_deadline(&self) -> Option<WrapperTimeout> {
+        self.wrapper_timeout
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -273,6 +375,8 @@ pub enum IsolateAction {
     Run,
     /// Clean up a box
     Cleanup,
+    /// Print isolate's configured cgroup root
+    PrintCgRoot,
 }
 
 #[cfg(test)]
@@ -293,6 +397,28 @@ mod tests {
         assert_eq!(args, vec!["isolate", "--box-id=5", "--cleanup"]);
     }
 
+    #[test]
+    fn test_print_cg_root_command() {
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::PrintCgRoot)
+            .cgroup(true);
+        let args = cmd.build();
+        assert_eq!(
+            args,
+            vec!["isolate", "--box-id=0", "--cg", "--print-cg-root"]
+        );
+    }
+
+    #[test]
+    fn test_cgroup_enabled() {
+        assert!(!IsolateCommand::new("isolate", 0).cgroup_enabled());
+        assert!(
+            IsolateCommand::new("isolate", 0)
+                .cgroup(true)
+                .cgroup_enabled()
+        );
+    }
+
     #[test]
     fn test_run_command_with_limits() {
         let limits = ResourceLimits {
@@ -391,6 +517,8 @@ mod tests {
             target: "/lib".to_string(),
             writable: false,
             optional: false,
+            tmp: false,
+            dev: false,
         };
         let cmd = IsolateCommand::new("isolate", 0)
             .action(IsolateAction::Run)
@@ -408,6 +536,8 @@ mod tests {
             target: "/work".to_string(),
             writable: true,
             optional: false,
+            tmp: false,
+            dev: false,
         };
         let cmd = IsolateCommand::new("isolate", 0)
             .action(IsolateAction::Run)
@@ -418,6 +548,44 @@ mod tests {
         assert!(args.contains(&"--dir=/work=/tmp/work:rw".to_string()));
     }
 
+    #[test]
+    fn test_mount_dev() {
+        let mount = MountConfig {
+            source: "/dev/dri".to_string(),
+            target: "/dev/dri".to_string(),
+            writable: false,
+            optional: false,
+            tmp: false,
+            dev: true,
+        };
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::Run)
+            .mount(mount)
+            .command(vec!["./main"]);
+        let args = cmd.build();
+
+        assert!(args.contains(&"--dir=/dev/dri=/dev/dri:dev".to_string()));
+    }
+
+    #[test]
+    fn test_mount_dev_read_write() {
+        let mount = MountConfig {
+            source: "/dev/dri".to_string(),
+            target: "/dev/dri".to_string(),
+            writable: true,
+            optional: false,
+            tmp: false,
+            dev: true,
+        };
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::Run)
+            .mount(mount)
+            .command(vec!["./main"]);
+        let args = cmd.build();
+
+        assert!(args.contains(&"--dir=/dev/dri=/dev/dri:rw:dev".to_string()));
+    }
+
     #[test]
     fn test_multiple_mounts() {
         let mounts = vec![
@@ -426,12 +594,16 @@ mod tests {
                 target: "/lib".to_string(),
                 writable: false,
                 optional: false,
+                tmp: false,
+                dev: false,
             },
             MountConfig {
                 source: "/tmp/data".to_string(),
                 target: "/data".to_string(),
                 writable: true,
                 optional: false,
+                tmp: false,
+                dev: false,
             },
         ];
         let cmd = IsolateCommand::new("isolate", 0)
@@ -444,6 +616,55 @@ mod tests {
         assert!(args.contains(&"--dir=/data=/tmp/data:rw".to_string()));
     }
 
+    #[test]
+    fn test_mount_source_root_relative_and_absolute() {
+        let mounts = vec![
+            MountConfig {
+                source: "headers".to_string(),
+                target: "/headers".to_string(),
+                writable: false,
+                optional: false,
+                tmp: false,
+                dev: false,
+            },
+            MountConfig {
+                source: "/usr/lib".to_string(),
+                target: "/lib".to_string(),
+                writable: false,
+                optional: false,
+                tmp: false,
+                dev: false,
+            },
+        ];
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::Run)
+            .mounts(mounts)
+            .mount_source_root(Some(PathBuf::from("/srv/silicube")))
+            .command(vec!["./main"]);
+        let args = cmd.build();
+
+        assert!(args.contains(&"--dir=/headers=/srv/silicube/headers".to_string()));
+        assert!(args.contains(&"--dir=/lib=/usr/lib".to_string()));
+    }
+
+    #[test]
+    fn test_mount_source_root_unset_keeps_relative() {
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::Run)
+            .mount(MountConfig {
+                source: "headers".to_string(),
+                target: "/headers".to_string(),
+                writable: false,
+                optional: false,
+                tmp: false,
+                dev: false,
+            })
+            .command(vec!["./main"]);
+        let args = cmd.build();
+
+        assert!(args.contains(&"--dir=/headers=headers".to_string()));
+    }
+
     #[test]
     fn test_env_single() {
         let cmd = IsolateCommand::new("isolate", 0)
@@ -455,6 +676,29 @@ mod tests {
         assert!(args.contains(&"--env=PATH=/usr/bin".to_string()));
     }
 
+    #[test]
+    fn test_env_prepended_path() {
+        let config = crate::config::Config::parse_toml(
+            r#"
+[languages.go]
+name = "Go"
+extension = "go"
+
+[languages.go.run]
+command = ["./{binary}"]
+path_prepend = "/opt/go/bin"
+"#,
+        )
+        .unwrap();
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::Run)
+            .env("PATH", config.languages["go"].run.sandbox_path())
+            .command(vec!["./main"]);
+        let args = cmd.build();
+
+        assert!(args.contains(&"--env=PATH=/opt/go/bin:/usr/bin:/bin".to_string()));
+    }
+
     #[test]
     fn test_env_multiple() {
         let cmd = IsolateCommand::new("isolate", 0)
@@ -479,6 +723,20 @@ mod tests {
         assert!(args.contains(&"--env=LANG".to_string()));
     }
 
+    #[test]
+    fn test_env_inherit_multiple_bare() {
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::Run)
+            .env_inherit("TZ")
+            .env_inherit("LANG")
+            .command(vec!["./main"]);
+        let args = cmd.build();
+
+        assert!(args.contains(&"--env=TZ".to_string()));
+        assert!(args.contains(&"--env=LANG".to_string()));
+        assert!(!args.iter().any(|arg| arg.starts_with("--env=TZ=")));
+    }
+
     #[test]
     fn test_full_env() {
         let cmd = IsolateCommand::new("isolate", 0)
@@ -501,6 +759,19 @@ mod tests {
         assert!(!args.contains(&"--full-env".to_string()));
     }
 
+    #[test]
+    fn test_share_net() {
+        let cmd = || {
+            IsolateCommand::new("isolate", 0)
+                .action(IsolateAction::Run)
+                .command(vec!["./main"])
+        };
+        assert!(!cmd().build().contains(&"--share-net".to_string()));
+
+        let args = cmd().share_net(true).build();
+        assert!(args.contains(&"--share-net".to_string()));
+    }
+
     #[test]
     fn test_stdin_redirect() {
         let cmd = IsolateCommand::new("isolate", 0)
@@ -534,6 +805,20 @@ mod tests {
         assert!(args.contains(&"--stderr=/tmp/error.txt".to_string()));
     }
 
+    #[test]
+    fn test_stderr_discarded() {
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::Run)
+            .stderr("/tmp/error.txt")
+            .capture_stderr(false)
+            .command(vec!["./main"]);
+        assert!(!cmd.captures_stderr());
+        let args = cmd.build();
+
+        assert!(args.contains(&"--stderr=/dev/null".to_string()));
+        assert!(!args.iter().any(|arg| arg.contains("error.txt")));
+    }
+
     #[test]
     fn test_all_io_redirects() {
         let cmd = IsolateCommand::new("isolate", 0)
@@ -571,6 +856,35 @@ mod tests {
         assert!(args.contains(&"--chdir=/box".to_string()));
     }
 
+    #[test]
+    fn test_working_dir_outside_box() {
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::Run)
+            .working_dir("/project/src")
+            .command(vec!["/box/main"]);
+        let args = cmd.build();
+
+        assert!(args.contains(&"--chdir=/project/src".to_string()));
+        assert!(!args.iter().any(|arg| arg == "--chdir=/box"));
+    }
+
+    #[test]
+    fn test_seccomp_profile() {
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::Run)
+            .seccomp_profile(Some(PathBuf::from("/etc/silicube/strict.bpf")))
+            .command(vec!["./main"]);
+        let args = cmd.build();
+        assert!(args.contains(&"--seccomp=/etc/silicube/strict.bpf".to_string()));
+
+        let cmd = IsolateCommand::new("isolate", 0)
+            .action(IsolateAction::Run)
+            .seccomp_profile(None)
+            .command(vec!["./main"]);
+        let args = cmd.build();
+        assert!(!args.iter().any(|arg| arg.starts_with("--seccomp")));
+    }
+
     #[test]
     fn test_command_with_args() {
         let cmd = IsolateCommand::new("isolate", 0)
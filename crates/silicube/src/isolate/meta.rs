@@ -165,7 +165,8 @@ impl MetaFile {
     }
 
     /// Get peak resident set size in kilobytes (max-rss from isolate meta).
-    /// Measures only the process's own resident memory.
+    /// Measures only the process's own resident memory. May be absent in
+    /// cgroup mode, where only `cg-mem` is reported.
     pub fn max_rss(&self) -> Option<u64> {
         self.get_u64("max-rss")
     }
@@ -190,8 +191,20 @@ impl MetaFile {
         self.get("killed").is_some()
     }
 
+    /// Get whether the cgroup OOM killer terminated the process
+    pub fn cg_oom_killed(&self) -> bool {
+        self.get("cg-oom-killed").is_some()
+    }
+
     /// Determine which limit was exceeded based on status and message
+    ///
+    /// A cgroup OOM kill is reported as memory even though isolate's message
+    /// only mentions the signal.
     pub fn limit_exceeded(&self) -> LimitExceeded {
+        if self.cg_oom_killed() {
+            return LimitExceeded::Memory;
+        }
+
         let status = self.status();
         let message = self.message();
 
@@ -206,13 +219,34 @@ impl MetaFile {
             return LimitExceeded::Time;
         }
 
+        // Writing past --fsize raises SIGXFSZ, which isolate reports as a
+        // plain fatal signal
+        if self.signal() == Some(libc::SIGXFSZ) {
+            return LimitExceeded::Output;
+        }
+
         LimitExceeded::NotExceeded
     }
 
     /// Convert to an ExecutionResult
+    ///
+    /// Some isolate versions report `status:XX` with neither an exit code nor
+    /// a signal. The result then has status `InternalError` and carries
+    /// isolate's message, or a generic one if isolate gave none, so callers
+    /// can tell why the run failed.
     pub fn to_execution_result(&self) -> ExecutionResult {
-        ExecutionResult {
-            status: self.status(),
+        let status = self.status();
+        let mut message = self.message();
+        if status == ExecutionStatus::InternalError
+            && message.is_none()
+            && self.exit_code().is_none()
+            && self.signal().is_none()
+        {
+            message = Some("isolate reported an internal error without details".to_string());
+        }
+
+        let mut result = ExecutionResult {
+            status,
             limit_exceeded: self.limit_exceeded(),
             time: self.time(),
             wall_time: self.wall_time(),
@@ -221,10 +255,21 @@ impl MetaFile {
             max_rss: self.max_rss(),
             exit_code: self.exit_code(),
             signal: self.signal(),
-            message: self.message(),
+            killed: self.killed(),
+            cg_oom_killed: self.cg_oom_killed(),
+            cgroup_used: false,
+            message,
+            disk_usage: None,
+            timings: None,
+            output_truncated: false,
             stdout: None,
             stderr: None,
+        };
+        if result.is_oom() {
+            result.limit_exceeded = LimitExceeded::Memory;
         }
+        result.output_truncated = result.limit_exceeded == LimitExceeded::Output;
+        result
     }
 }
 
@@ -232,6 +277,26 @@ impl MetaFile {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_output_limit_signal_marks_truncation() {
+        let content = format!(
+            "time:0.010\ntime-wall:0.020\nmax-rss:1500\nexitsig:{sig}\nstatus:SG\n\
+             message:Caught fatal signal {sig}\n",
+            sig = libc::SIGXFSZ
+        );
+        let meta = MetaFile::parse(&content);
+        assert_eq!(meta.limit_exceeded(), LimitExceeded::Output);
+
+        let result = meta.to_execution_result();
+        assert!(result.output_truncated);
+        assert!(result.is_output_limit());
+        assert_eq!(result.signal, Some(libc::SIGXFSZ));
+
+        let result = MetaFile::parse("exitsig:11\nstatus:SG\n").to_execution_result();
+        assert!(!result.output_truncated);
+        assert!(!result.is_output_limit());
+    }
+
     #[test]
     fn test_parse_success_meta() {
         let content = r#"
@@ -349,6 +414,70 @@ message:Caught fatal signal 11
         assert_eq!(meta.signal(), Some(11));
     }
 
+    #[test]
+    fn test_exit_code_and_signal_both_preserved() {
+        let content = r#"
+time:0.010
+exitcode:3
+exitsig:6
+status:SG
+message:Caught fatal signal 6
+"#;
+        let result = MetaFile::parse(content).to_execution_result();
+
+        assert_eq!(result.exit_code, Some(3));
+        assert_eq!(result.signal, Some(6));
+        assert!(result.terminated_by_signal());
+    }
+
+    #[test]
+    fn test_wall_time_sigkill_is_not_oom() {
+        let content = r#"
+time:0.100
+time-wall:3.000
+exitsig:9
+killed:1
+status:TO
+message:Time limit exceeded (wall clock)
+"#;
+        let result = MetaFile::parse(content).to_execution_result();
+
+        assert!(!result.is_oom());
+        assert_eq!(result.limit_exceeded, LimitExceeded::WallTime);
+    }
+
+    #[test]
+    fn test_cgroup_oom_sigkill_is_memory() {
+        let content = r#"
+time:0.300
+time-wall:0.400
+cg-mem:262144
+cg-oom-killed:1
+exitsig:9
+status:SG
+message:Caught fatal signal 9
+"#;
+        let result = MetaFile::parse(content).to_execution_result();
+
+        assert!(result.cg_oom_killed);
+        assert!(result.is_oom());
+        assert_eq!(result.limit_exceeded, LimitExceeded::Memory);
+    }
+
+    #[test]
+    fn test_exit_code_zero_with_signal_is_not_success() {
+        let content = r#"
+time:0.010
+time-wall:0.020
+exitcode:0
+exitsig:15
+"#;
+        let result = MetaFile::parse(content).to_execution_result();
+
+        assert_eq!(result.exit_code, Some(0));
+        assert!(!result.is_success());
+    }
+
     #[test]
     fn test_parse_cgroup_mem() {
         let content = r#"
@@ -395,6 +524,58 @@ message:Time limit exceeded
         assert_eq!(result.limit_exceeded, LimitExceeded::Time);
     }
 
+    #[test]
+    fn test_to_execution_result_internal_error_without_exit() {
+        let content = r#"
+status:XX
+message:Cannot run proxy, clone failed
+"#;
+        let result = MetaFile::parse(content).to_execution_result();
+
+        assert_eq!(result.status, ExecutionStatus::InternalError);
+        assert_eq!(result.exit_code, None);
+        assert_eq!(result.signal, None);
+        assert!(!result.is_success());
+        assert_eq!(
+            result.message.as_deref(),
+            Some("Cannot run proxy, clone failed")
+        );
+    }
+
+    #[test]
+    fn test_to_execution_result_internal_error_without_message() {
+        let result = MetaFile::parse("status:XX\n").to_execution_result();
+
+        assert_eq!(result.status, ExecutionStatus::InternalError);
+        assert!(result.message.is_some());
+    }
+
+    #[test]
+    fn test_to_execution_result_killed() {
+        let content = r#"
+time:2.001
+time-wall:2.500
+killed:1
+status:TO
+message:Time limit exceeded
+"#;
+        let result = MetaFile::parse(content).to_execution_result();
+        assert!(result.killed);
+    }
+
+    #[test]
+    fn test_to_execution_result_not_killed() {
+        let content = r#"
+time:0.010
+time-wall:0.020
+exitcode:1
+status:RE
+"#;
+        let result = MetaFile::parse(content).to_execution_result();
+        assert!(!result.killed);
+        assert_eq!(result.exit_code, Some(1));
+    }
+
     #[test]
     fn test_try_parse_success() {
         let content = "time:0.042\ntime-wall:0.050";
@@ -12,10 +12,21 @@ use std::path::{Path, PathBuf};
 
 use thiserror::Error;
 
-pub use crate::isolate::box_manager::{BoxPool, IsolateBox};
-pub use crate::isolate::command::{IsolateAction, IsolateCommand};
+pub(crate) use crate::isolate::box_manager::MAX_LIST_DEPTH;
+#[cfg(test)]
+pub(crate) use crate::isolate::box_manager::fake_isolate;
+#[cfg(feature = "gzip")]
+pub(crate) use crate::isolate::box_manager::gunzip;
+pub use crate::isolate::box_manager::{BoxPool, InitInfo, IsolateBox, MultiPool, PoolMetrics};
+pub use crate::isolate::command::{IsolateAction, IsolateCommand, WrapperTimeout};
 pub use crate::isolate::meta::{MetaFile, MetaParseError};
-pub use crate::isolate::process::{IsolateProcess, run_batch, run_with_output};
+pub(crate) use crate::isolate::process::{
+    BatchStdin, CapturedOutput, run_batch_with, run_with_split_output,
+    run_with_split_output_streaming,
+};
+pub use crate::isolate::process::{
+    IsolateProcess, run_batch, run_with_output, run_with_output_streaming,
+};
 use crate::types::MountConfig;
 
 mod box_manager;
@@ -23,18 +34,52 @@ mod command;
 mod meta;
 mod process;
 
+/// Scratch files silicube writes into boxes
+///
+/// Sources and other user-named files must not use these names, or they
+/// would be overwritten by (or clobber) silicube's own I/O. Listed without
+/// `Config::internal_file_prefix`, which is prepended when one is set.
+pub const RESERVED_BOX_FILES: [&str; 11] = [
+    "meta.txt",
+    "stdin.txt",
+    "stdout.txt",
+    "stderr.txt",
+    "compile_stdin.txt",
+    "compile_stdout.txt",
+    "compile_stderr.txt",
+    "interactive_meta.txt",
+    "checker_input.txt",
+    "checker_output.txt",
+    "checker_expected.txt",
+];
+
+/// Most bytes gzip input may decompress to
+///
+/// Bounds [`IsolateBox::write_file_gz`] and `Runner::run_with_gzip_input`
+/// so a small archive cannot fill the disk or memory.
+#[cfg(feature = "gzip")]
+pub const MAX_GUNZIP_BYTES: u64 = 256 * 1024 * 1024;
+
 /// Errors that occur during isolate sandbox operations
 #[derive(Debug, Error)]
 pub enum IsolateError {
     #[error("failed to initialize box {id}: {message}")]
     InitFailed { id: u32, message: String },
 
+    #[error(
+        "isolate requires root privileges: run as root or install isolate setuid root ({message})"
+    )]
+    NeedsRoot { message: String },
+
     #[error("failed to cleanup box {id}: {message}")]
     CleanupFailed { id: u32, message: String },
 
     #[error("isolate command failed: {0}")]
     CommandFailed(String),
 
+    #[error("command '{0}' not found in PATH")]
+    CommandNotFound(String),
+
     #[error("failed to spawn isolate process: {0}")]
     SpawnFailed(#[source] std::io::Error),
 
@@ -47,6 +92,12 @@ pub enum IsolateError {
     #[error("no available boxes in pool")]
     PoolExhausted,
 
+    #[error("box {0} is already in use by this process")]
+    BoxInUse(u32),
+
+    #[error("no box pool for class '{0}'")]
+    UnknownPoolClass(String),
+
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -56,11 +107,37 @@ pub enum IsolateError {
     #[error("invalid path: {0}")]
     InvalidPath(String),
 
+    #[error("file name '{0}' is reserved in the box")]
+    ReservedFileName(String),
+
     #[error("mount source path does not exist: {0}")]
     MountSourceNotFound(String),
 
+    #[error("seccomp profile does not exist: {0}")]
+    SeccompProfileNotFound(PathBuf),
+
+    #[error("decompressed data exceeds the limit of {limit} bytes")]
+    DecompressedTooLarge { limit: u64 },
+
     #[error("stdin is closed")]
     StdinClosed,
+
+    #[error("isolate exited with code {code}: {stderr}")]
+    WrapperExit { code: i32, stderr: String },
+
+    #[error("isolate in box {id} was killed after the {timeout:?} wrapper timeout")]
+    WrapperTimeout {
+        id: u32,
+        timeout: std::time::Duration,
+    },
+
+    #[error(
+        "cgroup root mismatch: silicube is configured with {configured} but isolate uses {isolate}"
+    )]
+    CgRootMismatch {
+        configured: PathBuf,
+        isolate: PathBuf,
+    },
 }
 
 /// Attempt to set up the cgroup v2 hierarchy for isolate.
@@ -120,13 +197,48 @@ pub fn prepare_cgroup(cg_root: &Path) -> Result<bool, IsolateError> {
     Ok(true)
 }
 
+/// Query isolate for the cgroup root it was configured with.
+///
+/// Runs `isolate --cg --print-cg-root`, which reports the `cg_root` value from
+/// isolate's own configuration file.
+pub async fn isolate_cg_root(isolate_path: &Path) -> Result<PathBuf, IsolateError> {
+    let args = IsolateCommand::new(isolate_path, 0)
+        .action(IsolateAction::PrintCgRoot)
+        .cgroup(true)
+        .build();
+
+    let program = args
+        .first()
+        .ok_or_else(|| IsolateError::CommandFailed("empty command arguments".to_string()))?;
+    let output = tokio::process::Command::new(program)
+        .args(&args[1..])
+        .output()
+        .await
+        .map_err(IsolateError::SpawnFailed)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(IsolateError::CommandFailed(stderr.to_string()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(PathBuf::from(stdout.trim()))
+}
+
 /// Validate that all mount source paths exist
 ///
 /// Returns an error if any non-optional mount source path does not exist on the host filesystem.
-/// Optional mounts (with `optional: true`) are silently skipped if the source doesn't exist.
+/// Optional mounts (with `optional: true`) are silently skipped if the source doesn't exist,
+/// and `tmp` mounts have no source to check.
 pub fn validate_mounts(mounts: &[MountConfig]) -> Result<(), IsolateError> {
     for mount in mounts {
-        if mount.optional {
+        if mount.dev && (mount.tmp || mount.source.is_empty()) {
+            return Err(IsolateError::InvalidPath(format!(
+                "device mount '{}' needs a host source and cannot be tmp",
+                mount.target
+            )));
+        }
+        if mount.optional || mount.tmp {
             continue;
         }
         let path = Path::new(&mount.source);
@@ -137,6 +249,57 @@ pub fn validate_mounts(mounts: &[MountConfig]) -> Result<(), IsolateError> {
     Ok(())
 }
 
+/// Validate that a configured seccomp profile exists on the host
+pub fn validate_seccomp_profile(profile: Option<&Path>) -> Result<(), IsolateError> {
+    match profile {
+        Some(path) if !path.exists() => {
+            Err(IsolateError::SeccompProfileNotFound(path.to_path_buf()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Validate a sandbox working directory
+///
+/// The directory must be absolute and must not contain `..` components, so
+/// it cannot be used to climb out of the mount it names.
+pub fn validate_working_dir(dir: &str) -> Result<(), IsolateError> {
+    let path = Path::new(dir);
+    if !path.is_absolute() {
+        return Err(IsolateError::InvalidPath(format!(
+            "working directory must be absolute: {dir}"
+        )));
+    }
+    if path
+        .components()
+        .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(IsolateError::InvalidPath(format!(
+            "working directory must not contain '..': {dir}"
+        )));
+    }
+    Ok(())
+}
+
+/// Validate that a box file name is not one of [`RESERVED_BOX_FILES`]
+///
+/// `prefix` is the configured `internal_file_prefix`, prepended to each
+/// reserved name. Leading `./` components are ignored, so `./meta.txt` is
+/// rejected too.
+pub fn validate_box_file_name(name: &str, prefix: &str) -> Result<(), IsolateError> {
+    let normalized: PathBuf = Path::new(name)
+        .components()
+        .filter(|c| !matches!(c, std::path::Component::CurDir))
+        .collect();
+    if RESERVED_BOX_FILES
+        .iter()
+        .any(|reserved| normalized == Path::new(&format!("{prefix}{reserved}")))
+    {
+        return Err(IsolateError::ReservedFileName(name.to_owned()));
+    }
+    Ok(())
+}
+
 /// Resolve the program in a command to an absolute path using the host's PATH.
 ///
 /// Isolate uses `execve` which does not search PATH, so commands must be
@@ -172,7 +335,5 @@ pub fn resolve_command(command: &mut [String]) -> Result<(), IsolateError> {
         }
     }
 
-    Err(IsolateError::CommandFailed(format!(
-        "command '{first}' not found in PATH",
-    )))
+    Err(IsolateError::CommandNotFound(first.clone()))
 }
@@ -2,78 +2,264 @@
 //!
 //! Handles running commands inside Isolate and capturing output.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::time::{Duration, Instant};
 
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::process::Command;
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 
 use crate::isolate::IsolateError;
 use crate::isolate::box_manager::IsolateBox;
-use crate::isolate::command::IsolateCommand;
+use crate::isolate::command::{IsolateCommand, WrapperTimeout};
 use crate::isolate::meta::MetaFile;
-use crate::types::ExecutionResult;
+use crate::types::{ExecutionResult, TimingBreakdown};
+
+/// How long to wait for the meta file after isolate exits
+const META_POLL_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Delay between checks for the meta file
+const META_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How often compile output files are checked for new data while streaming
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Poll `check` until it returns true or `timeout` elapses
+///
+/// Returns whether `check` succeeded. `check` is always called at least once.
+async fn poll_until(
+    mut check: impl FnMut() -> bool,
+    timeout: Duration,
+    interval: Duration,
+) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if check() {
+            return true;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Wait briefly for isolate's meta file to appear
+///
+/// On some kernels the meta file is flushed slightly after the isolate
+/// process exits, so checking once right after `wait` can spuriously miss
+/// it. Polls for up to [`META_POLL_TIMEOUT`] before giving up.
+async fn wait_for_meta(meta_path: &Path) -> bool {
+    poll_until(|| meta_path.exists(), META_POLL_TIMEOUT, META_POLL_INTERVAL).await
+}
 
 /// Run an isolate command and parse the meta file result
+///
+/// With a `deadline`, isolate is stopped once it passes the timeout and the
+/// box is re-initialized to reap anything left running in it. The returned
+/// timings leave `read_output` for the caller to fill in.
 async fn run_isolate_command(
+    sandbox: &IsolateBox,
     args: Vec<String>,
     meta_path: &Path,
-) -> Result<(std::process::Output, MetaFile), IsolateError> {
+    deadline: Option<WrapperTimeout>,
+) -> Result<(std::process::Output, MetaFile, TimingBreakdown), IsolateError> {
     let program = args
         .first()
         .ok_or_else(|| IsolateError::CommandFailed("empty command arguments".to_string()))?;
 
-    let output = Command::new(program)
+    let mut command = Command::new(program);
+    command
         .args(&args[1..])
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await
-        .map_err(IsolateError::SpawnFailed)?;
+        .stderr(Stdio::piped());
+    let (output, mut timings) = spawn_and_wait(&mut command, deadline).await?;
+    let Some(output) = output else {
+        if let Err(e) = sandbox.reinit().await {
+            warn!(?e, "failed to reap box after wrapper timeout");
+        }
+        return Err(IsolateError::WrapperTimeout {
+            id: sandbox.id(),
+            timeout: deadline.map(|d| d.timeout).unwrap_or_default(),
+        });
+    };
 
     // Parse meta file
-    let meta = if meta_path.exists() {
-        MetaFile::load(meta_path).await?
+    let meta_start = Instant::now();
+    let meta = load_meta(meta_path, output.status, &output.stderr).await?;
+    timings.meta_parse = meta_start.elapsed();
+
+    Ok((output, meta, timings))
+}
+
+/// Load the meta file of an isolate process that exited with `status`
+///
+/// `stderr` is isolate's own stderr, reported if the run failed.
+async fn load_meta(
+    meta_path: &Path,
+    status: std::process::ExitStatus,
+    stderr: &[u8],
+) -> Result<MetaFile, IsolateError> {
+    let meta = if wait_for_meta(meta_path).await {
+        Some(MetaFile::load(meta_path).await?)
     } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(IsolateError::CommandFailed(stderr.to_string()));
+        None
     };
+    check_wrapper_exit(status, stderr, meta)
+}
+
+/// Separate isolate's own failures from the sandboxed program's
+///
+/// Isolate exits 0 on success and 1 when the program failed; any other
+/// code is an error in isolate itself. A meta file reporting `XX` is kept
+/// so the internal error surfaces as a status, while a code of 2 or more
+/// alongside an ordinary meta file means the result can't be trusted.
+fn check_wrapper_exit(
+    status: std::process::ExitStatus,
+    stderr: &[u8],
+    meta: Option<MetaFile>,
+) -> Result<MetaFile, IsolateError> {
+    let stderr = || String::from_utf8_lossy(stderr).into_owned();
+    match (status.code(), meta) {
+        (Some(0 | 1) | None, Some(meta)) => Ok(meta),
+        (Some(_), Some(meta)) if meta.get("status") == Some("XX") => Ok(meta),
+        (Some(code), _) if code != 0 => Err(IsolateError::WrapperExit {
+            code,
+            stderr: stderr(),
+        }),
+        _ => Err(IsolateError::CommandFailed(stderr())),
+    }
+}
+
+/// Spawn `command` and collect its output, timing both steps
+///
+/// The output is `None` if `deadline` passed and the child was stopped.
+async fn spawn_and_wait(
+    command: &mut Command,
+    deadline: Option<WrapperTimeout>,
+) -> Result<(Option<std::process::Output>, TimingBreakdown), IsolateError> {
+    let spawn_start = Instant::now();
+    let child = command.spawn().map_err(IsolateError::SpawnFailed)?;
+    let spawned = Instant::now();
+
+    let output = match deadline {
+        None => Some(child.wait_with_output().await?),
+        Some(deadline) => output_within(child, deadline).await?,
+    };
+    let timings = TimingBreakdown {
+        spawn: spawned - spawn_start,
+        execute: spawned.elapsed(),
+        ..TimingBreakdown::default()
+    };
+    Ok((output, timings))
+}
+
+/// Collect a child's output, stopping it if it outlives `deadline`
+///
+/// Returns `None` if the deadline passed. Isolate is sent SIGTERM first so
+/// it can kill the sandboxed program itself, then SIGKILL after the grace.
+async fn output_within(
+    mut child: tokio::process::Child,
+    deadline: WrapperTimeout,
+) -> Result<Option<std::process::Output>, IsolateError> {
+    let stdout_reader = tokio::spawn(read_pipe(child.stdout.take()));
+    let stderr_reader = tokio::spawn(read_pipe(child.stderr.take()));
+
+    let Ok(status) = tokio::time::timeout(deadline.timeout, child.wait()).await else {
+        warn!(
+            timeout = ?deadline.timeout,
+            "isolate outlived the wrapper timeout, terminating"
+        );
+        terminate(&mut child, deadline.kill_grace).await?;
+        return Ok(None);
+    };
+
+    Ok(Some(std::process::Output {
+        status: status?,
+        stdout: stdout_reader.await.unwrap_or_default(),
+        stderr: stderr_reader.await.unwrap_or_default(),
+    }))
+}
+
+/// Read a child's pipe to the end, ignoring read errors
+async fn read_pipe<R: AsyncRead + Unpin>(pipe: Option<R>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if let Some(mut pipe) = pipe {
+        let _ = pipe.read_to_end(&mut buf).await;
+    }
+    buf
+}
 
-    Ok((output, meta))
+/// Send SIGTERM, then SIGKILL if the child is still running after `grace`
+async fn terminate(child: &mut tokio::process::Child, grace: Duration) -> Result<(), IsolateError> {
+    if let Some(pid) = child.id() {
+        // SAFETY: kill(2) has no memory-safety preconditions, and the child
+        // has not been reaped while `id()` still returns its pid
+        let sent = unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } == 0;
+        if sent && tokio::time::timeout(grace, child.wait()).await.is_ok() {
+            return Ok(());
+        }
+        warn!(pid, "isolate did not exit after SIGTERM, sending SIGKILL");
+    }
+    child.kill().await?;
+    Ok(())
 }
 
 /// Run a command in an Isolate box with batch I/O
 ///
 /// Runs the command with non-interactive I/O. The input is given once via
 /// stdin.txt and the result from stdout and stderr is captured into their
-/// respective files.
-#[instrument(skip(sandbox, stdin_data))]
+/// respective files. Stderr is left unread if the command does not
+/// [capture it](IsolateCommand::capture_stderr).
 pub async fn run_batch(
     sandbox: &IsolateBox,
     command: IsolateCommand,
     stdin_data: Option<&[u8]>,
+) -> Result<ExecutionResult, IsolateError> {
+    run_batch_with(sandbox, command, BatchStdin::Data(stdin_data)).await
+}
+
+/// Source of a batch run's stdin
+pub(crate) enum BatchStdin<'a> {
+    /// Write these bytes (or an empty file if `None`) to stdin.txt first
+    Data(Option<&'a [u8]>),
+
+    /// stdin.txt has already been written into the box
+    Written,
+}
+
+/// Run a command like [`run_batch`], with stdin from `stdin`
+#[instrument(skip(sandbox, stdin))]
+pub(crate) async fn run_batch_with(
+    sandbox: &IsolateBox,
+    command: IsolateCommand,
+    stdin: BatchStdin<'_>,
 ) -> Result<ExecutionResult, IsolateError> {
     // Host paths (for meta file and reading back results)
-    let meta_path = sandbox.file_path("meta.txt")?;
-    let stdout_host_path = sandbox.file_path("stdout.txt")?;
-    let stderr_host_path = sandbox.file_path("stderr.txt")?;
+    let meta_path = sandbox.file_path(&command.internal_file("meta.txt"))?;
+    let stdout_host_path = sandbox.file_path(&command.internal_file("stdout.txt"))?;
+    let stderr_host_path = sandbox.file_path(&command.internal_file("stderr.txt"))?;
 
     // Sandbox-internal paths (for isolate --stdin/--stdout/--stderr, opened inside the sandbox)
-    let stdin_sandbox_path = sandbox.sandbox_path("stdin.txt")?;
-    let stdout_sandbox_path = sandbox.sandbox_path("stdout.txt")?;
-    let stderr_sandbox_path = sandbox.sandbox_path("stderr.txt")?;
+    let stdin_name = command.internal_file("stdin.txt");
+    let stdin_sandbox_path = sandbox.sandbox_path(&stdin_name)?;
+    let stdout_sandbox_path = sandbox.sandbox_path(&command.internal_file("stdout.txt"))?;
+    let stderr_sandbox_path = sandbox.sandbox_path(&command.internal_file("stderr.txt"))?;
 
     // Write stdin data if provided.
     // Isolate requires a stdin file even if empty - it cannot read from /dev/null
     // when --stdin is specified, so we always create the file.
-    if let Some(data) = stdin_data {
-        sandbox.write_file("stdin.txt", data).await?;
-    } else {
-        sandbox.write_file("stdin.txt", b"").await?;
+    match stdin {
+        BatchStdin::Data(Some(data)) => sandbox.write_file(&stdin_name, data).await?,
+        BatchStdin::Data(None) => sandbox.write_file(&stdin_name, b"").await?,
+        BatchStdin::Written => {}
     }
 
+    let cgroup_used = command.cgroup_enabled();
+    let capture_stderr = command.captures_stderr();
+
     // Configure command with I/O files
     let command = command
         .meta_file(&meta_path)
@@ -81,21 +267,27 @@ pub async fn run_batch(
         .stdout(&stdout_sandbox_path)
         .stderr(&stderr_sandbox_path);
 
+    let deadline = command.wrapper_deadline();
     let args = command.build();
     debug!(?args, "running isolate command");
 
     // Run the command
-    let (_output, meta) = run_isolate_command(args, &meta_path).await?;
+    let (_output, meta, mut timings) =
+        run_isolate_command(sandbox, args, &meta_path, deadline).await?;
 
     let mut result = meta.to_execution_result();
+    result.cgroup_used = cgroup_used;
 
     // Read stdout/stderr via host paths
+    let read_start = Instant::now();
     if stdout_host_path.exists() {
         result.stdout = Some(tokio::fs::read(&stdout_host_path).await?);
     }
-    if stderr_host_path.exists() {
+    if capture_stderr && stderr_host_path.exists() {
         result.stderr = Some(tokio::fs::read(&stderr_host_path).await?);
     }
+    timings.read_output = read_start.elapsed();
+    result.timings = Some(Box::new(timings));
 
     debug!(
         status = ?result.status,
@@ -107,6 +299,25 @@ pub async fn run_batch(
     Ok(result)
 }
 
+/// Stdout and stderr captured from a compile-style run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CapturedOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CapturedOutput {
+    /// Stdout followed by stderr, joined by a newline when both are present
+    pub fn combined(&self) -> String {
+        let mut combined = self.stdout.clone();
+        if !combined.is_empty() && !self.stderr.is_empty() {
+            combined.push('\n');
+        }
+        combined.push_str(&self.stderr);
+        combined
+    }
+}
+
 /// Run a command and capture output (for compilation feedback)
 ///
 /// Used for compiling programs. Writes stdout and stderr outputs to
@@ -116,47 +327,226 @@ pub async fn run_with_output(
     sandbox: &IsolateBox,
     command: IsolateCommand,
 ) -> Result<(ExecutionResult, String), IsolateError> {
-    // Host paths (for meta file and reading back results)
-    let meta_path = sandbox.file_path("meta.txt")?;
-    let stdout_host_path = sandbox.file_path("compile_stdout.txt")?;
-    let stderr_host_path = sandbox.file_path("compile_stderr.txt")?;
+    let (result, output) = run_with_split_output(sandbox, command).await?;
+    Ok((result, output.combined()))
+}
 
-    // Sandbox-internal paths (for isolate --stdin/--stdout/--stderr, opened inside the sandbox)
-    let stdin_sandbox_path = sandbox.sandbox_path("compile_stdin.txt")?;
-    let stdout_sandbox_path = sandbox.sandbox_path("compile_stdout.txt")?;
-    let stderr_sandbox_path = sandbox.sandbox_path("compile_stderr.txt")?;
+/// Host paths of the scratch files a compile-style command writes
+struct CompileFiles {
+    meta: PathBuf,
+    stdout: PathBuf,
+    stderr: PathBuf,
+    cgroup_used: bool,
+}
 
-    // Write empty stdin - isolate requires a stdin file when --stdin is specified
-    sandbox.write_file("compile_stdin.txt", b"").await?;
+impl CompileFiles {
+    /// Point `command` at the compile scratch files in `sandbox`
+    ///
+    /// Writes the empty stdin file isolate requires when `--stdin` is set.
+    async fn attach(
+        sandbox: &IsolateBox,
+        command: IsolateCommand,
+    ) -> Result<(Self, IsolateCommand), IsolateError> {
+        // Host paths (for meta file and reading back results)
+        let files = Self {
+            meta: sandbox.file_path(&command.internal_file("meta.txt"))?,
+            stdout: sandbox.file_path(&command.internal_file("compile_stdout.txt"))?,
+            stderr: sandbox.file_path(&command.internal_file("compile_stderr.txt"))?,
+            cgroup_used: command.cgroup_enabled(),
+        };
 
-    let command = command
-        .meta_file(&meta_path)
-        .stdin(&stdin_sandbox_path)
-        .stdout(&stdout_sandbox_path)
-        .stderr(&stderr_sandbox_path);
+        // Sandbox-internal paths (for isolate --stdin/--stdout/--stderr, opened inside the sandbox)
+        let stdin_name = command.internal_file("compile_stdin.txt");
+        let stdin_sandbox_path = sandbox.sandbox_path(&stdin_name)?;
+        let stdout_sandbox_path =
+            sandbox.sandbox_path(&command.internal_file("compile_stdout.txt"))?;
+        let stderr_sandbox_path =
+            sandbox.sandbox_path(&command.internal_file("compile_stderr.txt"))?;
+
+        sandbox.write_file(&stdin_name, b"").await?;
+
+        let command = command
+            .meta_file(&files.meta)
+            .stdin(&stdin_sandbox_path)
+            .stdout(&stdout_sandbox_path)
+            .stderr(&stderr_sandbox_path);
+        Ok((files, command))
+    }
+
+    /// Build the execution result from the command's meta file
+    fn result(&self, meta: &MetaFile) -> ExecutionResult {
+        let mut result = meta.to_execution_result();
+        result.cgroup_used = self.cgroup_used;
+        result
+    }
+}
+
+/// Run a command like [`run_with_output`], keeping stdout and stderr apart
+pub(crate) async fn run_with_split_output(
+    sandbox: &IsolateBox,
+    command: IsolateCommand,
+) -> Result<(ExecutionResult, CapturedOutput), IsolateError> {
+    let (files, command) = CompileFiles::attach(sandbox, command).await?;
 
+    let deadline = command.wrapper_deadline();
     let args = command.build();
     debug!(?args, "running compile command");
 
-    let (_output, meta) = run_isolate_command(args, &meta_path).await?;
+    let (_output, meta, mut timings) =
+        run_isolate_command(sandbox, args, &files.meta, deadline).await?;
+    let mut result = files.result(&meta);
 
-    let result = meta.to_execution_result();
+    // Read compiler output back via host paths
+    let read_start = Instant::now();
+    let mut output = CapturedOutput::default();
+    if files.stdout.exists() {
+        output.stdout = tokio::fs::read_to_string(&files.stdout).await?;
+    }
+    if files.stderr.exists() {
+        output.stderr = tokio::fs::read_to_string(&files.stderr).await?;
+    }
+    timings.read_output = read_start.elapsed();
+    result.timings = Some(Box::new(timings));
 
-    // Combine stdout and stderr for compiler output (read via host paths)
-    let mut compiler_output = String::new();
-    if stdout_host_path.exists() {
-        let stdout = tokio::fs::read_to_string(&stdout_host_path).await?;
-        compiler_output.push_str(&stdout);
+    Ok((result, output))
+}
+
+/// Incremental reader for an output file that is still being written
+struct FileTail {
+    path: PathBuf,
+    file: Option<tokio::fs::File>,
+    collected: Vec<u8>,
+}
+
+impl FileTail {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: None,
+            collected: Vec::new(),
+        }
     }
-    if stderr_host_path.exists() {
-        let stderr = tokio::fs::read_to_string(&stderr_host_path).await?;
-        if !compiler_output.is_empty() && !stderr.is_empty() {
-            compiler_output.push('\n');
+
+    /// Copy any bytes written since the last call into `sink`
+    async fn drain_into<W: AsyncWrite + Unpin>(
+        &mut self,
+        sink: &mut W,
+    ) -> Result<(), IsolateError> {
+        if self.file.is_none() {
+            match tokio::fs::File::open(&self.path).await {
+                Ok(file) => self.file = Some(file),
+                // Isolate has not created the file yet
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+
+        let mut chunk = Vec::new();
+        file.read_to_end(&mut chunk).await?;
+        if !chunk.is_empty() {
+            sink.write_all(&chunk).await?;
+            sink.flush().await?;
+            self.collected.extend_from_slice(&chunk);
         }
-        compiler_output.push_str(&stderr);
+        Ok(())
     }
+}
+
+/// Run a command like [`run_with_output`], forwarding output to `sink` live
+///
+/// The compile stdout and stderr files are polled every 50 ms while the
+/// command runs, so output reaches `sink` with roughly that much latency.
+/// Chunks from the two streams are written in the order they are observed,
+/// which may not match the order the compiler produced them. The returned
+/// string is the same as [`run_with_output`] would return.
+#[instrument(skip(sandbox, sink))]
+pub async fn run_with_output_streaming<W: AsyncWrite + Unpin>(
+    sandbox: &IsolateBox,
+    command: IsolateCommand,
+    sink: &mut W,
+) -> Result<(ExecutionResult, String), IsolateError> {
+    let (result, output) = run_with_split_output_streaming(sandbox, command, sink).await?;
+    Ok((result, output.combined()))
+}
+
+/// Run a command like [`run_with_output_streaming`], keeping stdout and
+/// stderr apart in the returned output
+pub(crate) async fn run_with_split_output_streaming<W: AsyncWrite + Unpin>(
+    sandbox: &IsolateBox,
+    command: IsolateCommand,
+    sink: &mut W,
+) -> Result<(ExecutionResult, CapturedOutput), IsolateError> {
+    let (files, command) = CompileFiles::attach(sandbox, command).await?;
+
+    // Output left over from a previous compile must not be streamed
+    for path in [&files.stdout, &files.stderr] {
+        match tokio::fs::remove_file(path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+
+    let args = command.build();
+    debug!(?args, "running streaming compile command");
+
+    let program = args
+        .first()
+        .ok_or_else(|| IsolateError::CommandFailed("empty command arguments".to_string()))?;
+    let spawn_start = Instant::now();
+    let mut child = Command::new(program)
+        .args(&args[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(IsolateError::SpawnFailed)?;
+    let spawned = Instant::now();
+
+    // Isolate's own diagnostics are only needed if no meta file appears
+    let stderr_reader = tokio::spawn(read_pipe(child.stderr.take()));
+
+    let mut stdout_tail = FileTail::new(files.stdout.clone());
+    let mut stderr_tail = FileTail::new(files.stderr.clone());
+    let exit_status;
+    loop {
+        tokio::select! {
+            status = child.wait() => {
+                exit_status = status?;
+                break;
+            }
+            () = tokio::time::sleep(STREAM_POLL_INTERVAL) => {
+                stdout_tail.drain_into(sink).await?;
+                stderr_tail.drain_into(sink).await?;
+            }
+        }
+    }
+    let mut timings = TimingBreakdown {
+        spawn: spawned - spawn_start,
+        execute: spawned.elapsed(),
+        ..TimingBreakdown::default()
+    };
+
+    // Forward whatever the compiler wrote after the last poll
+    let read_start = Instant::now();
+    stdout_tail.drain_into(sink).await?;
+    stderr_tail.drain_into(sink).await?;
+    timings.read_output = read_start.elapsed();
 
-    Ok((result, compiler_output))
+    let stderr = stderr_reader.await.unwrap_or_default();
+    let meta_start = Instant::now();
+    let meta = load_meta(&files.meta, exit_status, &stderr).await?;
+    timings.meta_parse = meta_start.elapsed();
+
+    let output = CapturedOutput {
+        stdout: String::from_utf8_lossy(&stdout_tail.collected).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr_tail.collected).into_owned(),
+    };
+    let mut result = files.result(&meta);
+    result.timings = Some(Box::new(timings));
+    Ok((result, output))
 }
 
 /// Process handle for interactive execution
@@ -166,7 +556,8 @@ pub struct IsolateProcess {
     stdin: Option<tokio::process::ChildStdin>,
     stdout: Option<tokio::process::ChildStdout>,
     stderr: Option<tokio::process::ChildStderr>,
-    meta_path: std::path::PathBuf,
+    meta_path: PathBuf,
+    cgroup_used: bool,
 }
 
 impl IsolateProcess {
@@ -176,8 +567,15 @@ impl IsolateProcess {
         sandbox: &IsolateBox,
         command: IsolateCommand,
     ) -> Result<Self, IsolateError> {
-        let meta_path = sandbox.file_path("interactive_meta.txt")?;
+        let meta_path = sandbox.file_path(&command.internal_file("interactive_meta.txt"))?;
 
+        // A meta file left by an earlier session must not be mistaken for this one's
+        match tokio::fs::remove_file(&meta_path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+
+        let cgroup_used = command.cgroup_enabled();
         let command = command.meta_file(&meta_path);
         let args = command.build();
 
@@ -204,6 +602,7 @@ impl IsolateProcess {
             stdout,
             stderr,
             meta_path,
+            cgroup_used,
         })
     }
 
@@ -223,6 +622,11 @@ impl IsolateProcess {
         self.stdin = None;
     }
 
+    /// Take ownership of stdin
+    pub fn take_stdin(&mut self) -> Option<tokio::process::ChildStdin> {
+        self.stdin.take()
+    }
+
     /// Get the stdout handle
     pub fn stdout(&mut self) -> Option<&mut tokio::process::ChildStdout> {
         self.stdout.as_mut()
@@ -252,7 +656,7 @@ impl IsolateProcess {
         let _ = self.child.wait().await?;
 
         // Parse meta file
-        let meta = if self.meta_path.exists() {
+        let meta = if wait_for_meta(&self.meta_path).await {
             MetaFile::load(&self.meta_path).await?
         } else {
             return Err(IsolateError::CommandFailed(
@@ -260,7 +664,25 @@ impl IsolateProcess {
             ));
         };
 
-        Ok(meta.to_execution_result())
+        let mut result = meta.to_execution_result();
+        result.cgroup_used = self.cgroup_used;
+        Ok(result)
+    }
+
+    /// Parse the meta file as it is right now, if isolate has written one
+    pub(crate) async fn current_result(&self) -> Option<ExecutionResult> {
+        let meta = MetaFile::load(&self.meta_path).await.ok()?;
+        let mut result = meta.to_execution_result();
+        result.cgroup_used = self.cgroup_used;
+        Some(result)
+    }
+
+    /// Wait for the isolate process to exit without collecting the result
+    ///
+    /// Cancel-safe; call [`wait`](Self::wait) afterwards for the result.
+    pub(crate) async fn wait_exit(&mut self) -> Result<(), IsolateError> {
+        self.child.wait().await?;
+        Ok(())
     }
 
     /// Kill the process
@@ -277,3 +699,159 @@ impl IsolateProcess {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_terminate_sends_sigterm_first() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let started = std::time::Instant::now();
+        terminate(&mut child, Duration::from_secs(5)).await.unwrap();
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_file_tail_forwards_appended_data() {
+        let path = std::env::temp_dir().join(format!("silicube-tail-{}.txt", std::process::id()));
+        let mut tail = FileTail::new(path.clone());
+        let mut sink = Vec::new();
+
+        // Missing file is not an error
+        tail.drain_into(&mut sink).await.unwrap();
+        assert!(sink.is_empty());
+
+        tokio::fs::write(&path, b"first ").await.unwrap();
+        tail.drain_into(&mut sink).await.unwrap();
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .await
+            .unwrap();
+        file.write_all(b"second").await.unwrap();
+        tail.drain_into(&mut sink).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(sink, b"first second");
+        assert_eq!(tail.collected, b"first second");
+    }
+
+    #[tokio::test]
+    async fn test_output_within_stops_overdue_child() {
+        let deadline = WrapperTimeout {
+            timeout: Duration::from_millis(50),
+            kill_grace: Duration::from_secs(1),
+        };
+        let child = Command::new("sleep")
+            .arg("30")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let started = std::time::Instant::now();
+        let output = output_within(child, deadline).await.unwrap();
+        assert!(output.is_none());
+        // SIGTERM is enough for sleep, so the grace is not waited out
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let child = Command::new("echo")
+            .arg("done")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let output = output_within(child, deadline).await.unwrap().unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"done\n");
+    }
+
+    #[tokio::test]
+    async fn test_spawn_and_wait_times_phases() {
+        let started = Instant::now();
+        let mut command = Command::new("sleep");
+        command
+            .arg("0.2")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let (output, timings) = spawn_and_wait(&mut command, None).await.unwrap();
+        let elapsed = started.elapsed();
+
+        assert!(output.unwrap().status.success());
+        assert!(timings.execute >= Duration::from_millis(200));
+        assert!(timings.total() <= elapsed);
+        assert!(elapsed - timings.total() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_check_wrapper_exit() {
+        use std::os::unix::process::ExitStatusExt;
+        let exited = |code: i32| std::process::ExitStatus::from_raw(code << 8);
+        let meta = || MetaFile::parse("status:RE\nexitcode:1\n");
+
+        // The program failing is not a wrapper error
+        let kept = check_wrapper_exit(exited(1), b"", Some(meta())).unwrap();
+        assert_eq!(kept.get("status"), Some("RE"));
+
+        // Isolate's own failure wins over a meta file it still wrote
+        let err = check_wrapper_exit(exited(2), b"cannot chdir", Some(meta())).unwrap_err();
+        assert!(matches!(
+            err,
+            IsolateError::WrapperExit { code: 2, ref stderr } if stderr == "cannot chdir"
+        ));
+
+        // An internal error recorded in the meta file stays a status
+        let internal = MetaFile::parse("status:XX\nmessage:oops\n");
+        assert!(check_wrapper_exit(exited(2), b"", Some(internal)).is_ok());
+
+        let err = check_wrapper_exit(exited(2), b"", None).unwrap_err();
+        assert!(matches!(err, IsolateError::WrapperExit { code: 2, .. }));
+        let err = check_wrapper_exit(exited(0), b"no meta", None).unwrap_err();
+        assert!(matches!(err, IsolateError::CommandFailed(ref s) if s == "no meta"));
+    }
+
+    #[tokio::test]
+    async fn test_poll_until_succeeds_on_second_poll() {
+        let mut polls = 0;
+        let found = poll_until(
+            || {
+                polls += 1;
+                polls >= 2
+            },
+            Duration::from_secs(1),
+            Duration::from_millis(1),
+        )
+        .await;
+        assert!(found);
+        assert_eq!(polls, 2);
+    }
+
+    #[tokio::test]
+    async fn test_poll_until_gives_up_after_timeout() {
+        let mut polls = 0;
+        let found = poll_until(
+            || {
+                polls += 1;
+                false
+            },
+            Duration::from_millis(10),
+            Duration::from_millis(2),
+        )
+        .await;
+        assert!(!found);
+        assert!(polls >= 2);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_meta_missing_file() {
+        let found = wait_for_meta(Path::new("/nonexistent/silicube/meta.txt")).await;
+        assert!(!found);
+    }
+}
@@ -9,18 +9,25 @@
 //! - **Sandboxed execution** — Pool-based lifecycle for running untrusted code safely using Isolate.
 //! - **Multi-language** — Supports both compiled and interpreted languages.
 //! - **TOML configuration** — Flexible per-language compiler/runtime settings.
-//! - **Interactive execution** — FIFO-based sessions for interactive programs.
+//! - **Interactive execution** — Pipe-based sessions for interactive programs.
 //! - **Resource limits** — Enforce CPU time, memory, wall time, processes, and output constraints.
 //! - **cgroup v2 support** — Memory limiting in container environments.
 
 pub use config::{Config, ConfigError, EXAMPLE_CONFIG, Language};
-pub use isolate::{BoxPool, IsolateBox, IsolateError, prepare_cgroup};
+pub use isolate::{
+    BoxPool, InitInfo, IsolateBox, IsolateError, MultiPool, PoolMetrics, RESERVED_BOX_FILES,
+    prepare_cgroup,
+};
 pub use runner::{
-    CompileAndRunError, CompileAndRunRequest, CompileError, CompileResult, ExecuteError,
-    InteractiveError, InteractiveEvent, InteractiveEventStream, InteractiveSession,
-    InteractiveSessionHandle, Runner,
+    AdmissionError, BatchSummary, CheckerArgs, CheckerResult, ComparePolicy, CompileAndRunError,
+    CompileAndRunRequest, CompileError, CompileResult, DiffInfo, ExecuteError, InteractiveError,
+    InteractiveEvent, InteractiveEventStream, InteractiveSession, InteractiveSessionHandle,
+    LineEnding, OutputStream, PartialUsage, Phase, Runner, SessionRegistry, Verdict,
+};
+pub use types::{
+    ExecutionResult, ExecutionStatus, HashAlgo, LimitExceeded, MemoryReportMode, MountConfig,
+    ResourceLimits, TimingBreakdown,
 };
-pub use types::{ExecutionResult, ExecutionStatus, LimitExceeded, MountConfig, ResourceLimits};
 
 pub mod config;
 pub mod isolate;
@@ -0,0 +1,431 @@
+//! Checker step for grading submissions
+//!
+//! Runs a trusted checker program (testlib-style) against a submission's
+//! output and interprets its exit code as a verdict, or compares output
+//! against the expected answer directly under a [`ComparePolicy`].
+
+use tracing::{debug, instrument};
+
+use crate::config::{Config, Language};
+use crate::isolate::{BatchStdin, IsolateBox};
+use crate::runner::ExecuteError;
+use crate::runner::execute::execute_with_args;
+use crate::types::{ExecutionResult, ExecutionStatus, ResourceLimits};
+
+/// File name of the test input inside the sandbox
+const CHECKER_INPUT: &str = "checker_input.txt";
+/// File name of the submission's output inside the sandbox
+const CHECKER_OUTPUT: &str = "checker_output.txt";
+/// File name of the expected answer inside the sandbox
+const CHECKER_EXPECTED: &str = "checker_expected.txt";
+
+/// Files handed to a checker program
+#[derive(Debug, Clone, Copy)]
+pub struct CheckerArgs<'a> {
+    /// Input the submission was run with
+    pub input: &'a [u8],
+    /// Expected (jury) answer
+    pub expected: &'a [u8],
+    /// Output produced by the submission
+    pub output: &'a [u8],
+}
+
+/// Verdict reported by a checker
+///
+/// Follows testlib's exit code convention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Output is correct (exit code 0)
+    Accepted,
+
+    /// Output is incorrect (exit code 1)
+    WrongAnswer,
+
+    /// Output has the right answer but wrong formatting (exit code 2)
+    PresentationError,
+
+    /// The checker itself failed, crashed, or exited with an unknown code
+    CheckerFailed,
+}
+
+impl Verdict {
+    /// Interpret a checker's exit code
+    pub fn from_exit_code(code: i32) -> Self {
+        match code {
+            0 => Verdict::Accepted,
+            1 => Verdict::WrongAnswer,
+            2 => Verdict::PresentationError,
+            _ => Verdict::CheckerFailed,
+        }
+    }
+
+    /// Interpret a checker's execution result
+    ///
+    /// A checker that was killed, timed out, or hit an internal error is
+    /// reported as [`Verdict::CheckerFailed`] regardless of its exit code.
+    pub fn from_execution(result: &ExecutionResult) -> Self {
+        match (result.status, result.exit_code) {
+            (ExecutionStatus::Ok | ExecutionStatus::RuntimeError, Some(code)) => {
+                Self::from_exit_code(code)
+            }
+            _ => Verdict::CheckerFailed,
+        }
+    }
+
+    /// Check if the verdict is accepted
+    #[must_use]
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Verdict::Accepted)
+    }
+}
+
+/// Result of running a checker
+#[derive(Debug, Clone)]
+pub struct CheckerResult {
+    /// Verdict derived from the checker's exit status
+    pub verdict: Verdict,
+
+    /// Execution result from the checker process
+    pub execution: ExecutionResult,
+
+    /// Checker comment (testlib writes this to stderr)
+    pub message: String,
+}
+
+/// Run a checker program against a submission's output
+///
+/// The checker must already be compiled (or its source written) in the
+/// sandbox. The input, output, and expected files are written into the box
+/// and passed to the checker as `<input> <output> <expected>`.
+#[instrument(skip(sandbox, config, args), fields(label = sandbox.label()))]
+pub async fn run_checker(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+    args: CheckerArgs<'_>,
+    limits: Option<&ResourceLimits>,
+) -> Result<CheckerResult, ExecuteError> {
+    let extra_args =
+        [CHECKER_INPUT, CHECKER_OUTPUT, CHECKER_EXPECTED].map(|name| config.internal_file(name));
+    let [input, output, expected] = &extra_args;
+    sandbox.write_file(input, args.input).await?;
+    sandbox.write_file(output, args.output).await?;
+    sandbox.write_file(expected, args.expected).await?;
+
+    let execution = execute_with_args(
+        sandbox,
+        config,
+        language,
+        &extra_args,
+        BatchStdin::Data(None),
+        limits,
+        // The checker's message is its stderr
+        true,
+    )
+    .await?;
+
+    let verdict = Verdict::from_execution(&execution);
+    let message = execution
+        .stderr
+        .as_deref()
+        .map(|stderr| String::from_utf8_lossy(stderr).trim().to_owned())
+        .unwrap_or_default();
+
+    debug!(?verdict, exit_code = ?execution.exit_code, "checker complete");
+
+    Ok(CheckerResult {
+        verdict,
+        execution,
+        message,
+    })
+}
+
+/// How [`compare_output`] matches output against the expected answer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ComparePolicy {
+    /// Byte-for-byte equality
+    #[default]
+    Exact,
+
+    /// Ignore trailing whitespace on each line and trailing blank lines
+    IgnoreTrailingWhitespace,
+
+    /// Compare whitespace-separated tokens only
+    Tokens,
+}
+
+/// Compare a program's output against the expected answer
+///
+/// Returns [`Verdict::Accepted`] if they match under `policy`. A mismatch
+/// whose tokens still agree is a [`Verdict::PresentationError`]; anything
+/// else is a [`Verdict::WrongAnswer`].
+pub fn compare_output(output: &[u8], expected: &[u8], policy: ComparePolicy) -> Verdict {
+    let matches = match policy {
+        ComparePolicy::Exact => output == expected,
+        ComparePolicy::IgnoreTrailingWhitespace => {
+            trimmed_lines(output).eq(trimmed_lines(expected))
+        }
+        ComparePolicy::Tokens => tokens(output).eq(tokens(expected)),
+    };
+    if matches {
+        Verdict::Accepted
+    } else if tokens(output).eq(tokens(expected)) {
+        Verdict::PresentationError
+    } else {
+        Verdict::WrongAnswer
+    }
+}
+
+/// Location of the first difference between output and expected answer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffInfo {
+    /// 1-based line number
+    pub line: usize,
+
+    /// 1-based byte column of the first differing byte within the line
+    pub col: usize,
+
+    /// The output's line, or `None` if the output has fewer lines
+    pub actual_line: Option<String>,
+
+    /// The expected line, or `None` if the expected answer has fewer lines
+    pub expected_line: Option<String>,
+}
+
+/// Find the first line where `actual` and `expected` differ byte for byte
+///
+/// Returns `None` if they are identical. Lines are split on `\n`, so a
+/// missing trailing newline shows up as an extra empty line on one side.
+/// This is an exact comparison; check the [`ComparePolicy`] verdict first
+/// if whitespace differences should not be reported.
+pub fn first_diff(actual: &[u8], expected: &[u8]) -> Option<DiffInfo> {
+    let mut actual_lines = actual.split(|&b| b == b'\n');
+    let mut expected_lines = expected.split(|&b| b == b'\n');
+    let lossy = |line: &[u8]| String::from_utf8_lossy(line).into_owned();
+
+    let mut line = 0;
+    loop {
+        line += 1;
+        match (actual_lines.next(), expected_lines.next()) {
+            (None, None) => return None,
+            (Some(a), Some(e)) if a == e => {}
+            (a, e) => {
+                let col = match (a, e) {
+                    (Some(a), Some(e)) => a.iter().zip(e).take_while(|(x, y)| x == y).count() + 1,
+                    _ => 1,
+                };
+                return Some(DiffInfo {
+                    line,
+                    col,
+                    actual_line: a.map(lossy),
+                    expected_line: e.map(lossy),
+                });
+            }
+        }
+    }
+}
+
+/// Verdict counts and peak usage over a batch of test cases
+///
+/// Resource failures take precedence over the verdict: a case that hit the
+/// time limit counts as `tle` whatever its output, and one stopped by the
+/// output limit counts as `ole` rather than `re`. Cases that fit no
+/// category (checker or sandbox failures) only count toward `total`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchSummary {
+    /// Number of test cases
+    pub total: usize,
+
+    /// Cases that ran cleanly and were accepted
+    pub accepted: usize,
+
+    /// Cases that ran cleanly with a wrong answer or presentation error
+    pub wrong: usize,
+
+    /// Cases that exceeded the CPU or wall time limit
+    pub tle: usize,
+
+    /// Cases that exceeded the memory limit
+    pub mle: usize,
+
+    /// Cases stopped by the output limit
+    pub ole: usize,
+
+    /// Cases that exited non-zero or were killed by a signal
+    pub re: usize,
+
+    /// Highest CPU time in seconds
+    pub max_time: f64,
+
+    /// Highest memory usage in kilobytes
+    pub max_memory: u64,
+}
+
+impl BatchSummary {
+    /// Aggregate execution results with their verdicts
+    pub fn from_results(results: &[(ExecutionResult, Verdict)]) -> Self {
+        let mut summary = Self {
+            total: results.len(),
+            ..Self::default()
+        };
+        for (result, verdict) in results {
+            summary.max_time = summary.max_time.max(result.time);
+            summary.max_memory = summary.max_memory.max(result.memory);
+
+            if result.is_time_limit() {
+                summary.tle += 1;
+            } else if result.is_memory_limit() {
+                summary.mle += 1;
+            } else if result.is_output_limit() {
+                summary.ole += 1;
+            } else {
+                match (result.status, verdict) {
+                    (ExecutionStatus::RuntimeError | ExecutionStatus::Signaled, _) => {
+                        summary.re += 1;
+                    }
+                    (ExecutionStatus::Ok, Verdict::Accepted) => summary.accepted += 1,
+                    (ExecutionStatus::Ok, Verdict::WrongAnswer | Verdict::PresentationError) => {
+                        summary.wrong += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        summary
+    }
+
+    /// Whether every case was accepted
+    pub fn all_accepted(&self) -> bool {
+        self.accepted == self.total
+    }
+}
+
+/// Lines with trailing whitespace removed, ignoring trailing blank lines
+fn trimmed_lines(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let lines: Vec<&[u8]> = data
+        .split(|&b| b == b'\n')
+        .map(|line| line.trim_ascii_end())
+        .collect();
+    let len = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .map_or(0, |last| last + 1);
+    lines.into_iter().take(len)
+}
+
+/// Whitespace-separated tokens
+fn tokens(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    data.split(u8::is_ascii_whitespace)
+        .filter(|token| !token.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_diff_identical() {
+        assert_eq!(first_diff(b"1\n2\n3\n", b"1\n2\n3\n"), None);
+        assert_eq!(first_diff(b"", b""), None);
+    }
+
+    #[test]
+    fn first_diff_at_line_three() {
+        let diff = first_diff(b"1\n2\nabcd\n4\n", b"1\n2\nabxd\n4\n").unwrap();
+        assert_eq!(
+            diff,
+            DiffInfo {
+                line: 3,
+                col: 3,
+                actual_line: Some("abcd".to_owned()),
+                expected_line: Some("abxd".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn first_diff_length_mismatch() {
+        let diff = first_diff(b"1\n2", b"1\n2\n3").unwrap();
+        assert_eq!((diff.line, diff.col), (3, 1));
+        assert_eq!(diff.actual_line, None);
+        assert_eq!(diff.expected_line.as_deref(), Some("3"));
+
+        // A shorter line differs where it ends
+        let diff = first_diff(b"12\n", b"123\n").unwrap();
+        assert_eq!((diff.line, diff.col), (1, 3));
+    }
+
+    #[test]
+    fn compare_exact() {
+        let policy = ComparePolicy::Exact;
+        assert_eq!(
+            compare_output(b"1 2\n", b"1 2\n", policy),
+            Verdict::Accepted
+        );
+        assert_eq!(
+            compare_output(b"1 2", b"1 2\n", policy),
+            Verdict::PresentationError
+        );
+        assert_eq!(
+            compare_output(b"1 3\n", b"1 2\n", policy),
+            Verdict::WrongAnswer
+        );
+    }
+
+    #[test]
+    fn compare_ignore_trailing_whitespace() {
+        let policy = ComparePolicy::IgnoreTrailingWhitespace;
+        assert_eq!(
+            compare_output(b"1 2  \r\n3\n\n\n", b"1 2\n3", policy),
+            Verdict::Accepted
+        );
+        assert_eq!(
+            compare_output(b"1  2\n3\n", b"1 2\n3\n", policy),
+            Verdict::PresentationError
+        );
+        assert_eq!(compare_output(b"", b"\n\n", policy), Verdict::Accepted);
+    }
+
+    #[test]
+    fn compare_tokens() {
+        let policy = ComparePolicy::Tokens;
+        assert_eq!(
+            compare_output(b"1\n2 3", b"1 2\n3\n", policy),
+            Verdict::Accepted
+        );
+        assert_eq!(
+            compare_output(b"1 2", b"1 2 3", policy),
+            Verdict::WrongAnswer
+        );
+    }
+
+    #[test]
+    fn verdict_from_exit_code() {
+        assert_eq!(Verdict::from_exit_code(0), Verdict::Accepted);
+        assert_eq!(Verdict::from_exit_code(1), Verdict::WrongAnswer);
+        assert_eq!(Verdict::from_exit_code(2), Verdict::PresentationError);
+        assert_eq!(Verdict::from_exit_code(3), Verdict::CheckerFailed);
+        assert_eq!(Verdict::from_exit_code(-1), Verdict::CheckerFailed);
+    }
+
+    #[test]
+    fn verdict_from_execution_uses_exit_code() {
+        let result = ExecutionResult::default()
+            .with_status(ExecutionStatus::RuntimeError)
+            .with_exit_code(1);
+        assert_eq!(Verdict::from_execution(&result), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn verdict_from_execution_killed_checker_fails() {
+        let result = ExecutionResult {
+            status: ExecutionStatus::Signaled,
+            signal: Some(9),
+            ..Default::default()
+        };
+        assert_eq!(Verdict::from_execution(&result), Verdict::CheckerFailed);
+
+        let result = ExecutionResult::default().with_status(ExecutionStatus::TimeLimitExceeded);
+        assert_eq!(Verdict::from_execution(&result), Verdict::CheckerFailed);
+    }
+}
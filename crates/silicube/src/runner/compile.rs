@@ -2,13 +2,16 @@
 //!
 //! Handles compiling source code using language-specific compilers.
 
-use tracing::{debug, instrument};
+use tokio::io::AsyncWrite;
+use tracing::{debug, instrument, warn};
 
-use crate::config::language::DEFAULT_SANDBOX_PATH;
-use crate::config::{Config, Language};
-use crate::isolate::{IsolateAction, IsolateBox, IsolateCommand, resolve_command, run_with_output};
-use crate::runner::CompileError;
-use crate::types::{ExecutionResult, ResourceLimits};
+use crate::config::{CompileConfig, Config, Language};
+use crate::isolate::{
+    CapturedOutput, IsolateAction, IsolateBox, IsolateCommand, resolve_command,
+    run_with_split_output, run_with_split_output_streaming, validate_mounts,
+};
+use crate::runner::{CompileError, check_source_name};
+use crate::types::{ExecutionResult, ExecutionStatus, ResourceLimits};
 
 /// Result of a compilation
 #[derive(Debug, Clone)]
@@ -20,7 +23,15 @@ pub struct CompileResult {
     pub execution: ExecutionResult,
 
     /// Compiler output (usually stderr for error messages)
+    ///
+    /// Stdout followed by stderr, or isolate's message if both are empty.
     pub output: String,
+
+    /// Compiler stdout only
+    pub stdout: String,
+
+    /// Compiler stderr only
+    pub stderr: String,
 }
 
 impl CompileResult {
@@ -28,8 +39,39 @@ impl CompileResult {
     pub fn is_success(&self) -> bool {
         self.success && self.execution.exit_code == Some(0)
     }
+
+    /// Check whether the compiler most likely failed on the process limit
+    ///
+    /// Isolate does not report this directly: a compiler that cannot fork or
+    /// start a thread sees `EAGAIN` and fails with its own message. This
+    /// matches failed or signaled compiles against those messages. When it
+    /// returns true, raise the language's compile `max_processes`.
+    pub fn hit_process_limit(&self) -> bool {
+        if self.is_success()
+            || !matches!(
+                self.execution.status,
+                ExecutionStatus::RuntimeError | ExecutionStatus::Signaled
+            )
+        {
+            return false;
+        }
+        let output = self.output.to_lowercase();
+        PROCESS_LIMIT_MARKERS
+            .iter()
+            .any(|marker| output.contains(marker))
+    }
 }
 
+/// Lowercased compiler messages for a failed fork or thread creation
+const PROCESS_LIMIT_MARKERS: &[&str] = &[
+    "resource temporarily unavailable",
+    "cannot fork",
+    "fork: retry",
+    "vfork:",
+    "failed to create new os thread",
+    "pthread_create",
+];
+
 /// Default compilation limits
 fn default_compile_limits() -> ResourceLimits {
     ResourceLimits {
@@ -42,8 +84,28 @@ fn default_compile_limits() -> ResourceLimits {
     }
 }
 
+/// Layer compilation limits: compile defaults → language compile limits → user overrides
+///
+/// The base is [`default_compile_limits`], not `Config::default_limits`, so
+/// run-time defaults (e.g. a 2s CPU limit) never leak into compilation. Each
+/// layer only replaces the fields it sets, which lets a language override
+/// `wall_time_limit` alone while keeping the 30s compile CPU limit.
+pub(super) fn effective_compile_limits(
+    language: Option<&ResourceLimits>,
+    user: Option<&ResourceLimits>,
+) -> ResourceLimits {
+    let mut effective = default_compile_limits();
+    if let Some(lang) = language {
+        effective = effective.with_overrides(lang);
+    }
+    if let Some(user) = user {
+        effective = effective.with_overrides(user);
+    }
+    effective
+}
+
 /// Compile source code in an isolate box
-#[instrument(skip(sandbox, config, source))]
+#[instrument(skip(sandbox, config, source), fields(label = sandbox.label()))]
 pub async fn compile(
     sandbox: &IsolateBox,
     config: &Config,
@@ -51,14 +113,83 @@ pub async fn compile(
     source: &[u8],
     limits: Option<&ResourceLimits>,
 ) -> Result<CompileResult, CompileError> {
+    let command = compile_command(sandbox, config, language, source, None, limits).await?;
+    let (result, output) = run_with_split_output(sandbox, command)
+        .await
+        .map_err(CompileError::Isolate)?;
+    Ok(compile_result(config, result, output))
+}
+
+/// Compile source code written to `source_name` instead of the configured name
+///
+/// For toolchains that require the filename to match a name only known at
+/// runtime, such as a Java public class. `source_name` also replaces the
+/// `{source}` placeholder; the compile output name is unchanged.
+#[instrument(skip(sandbox, config, source), fields(label = sandbox.label()))]
+pub async fn compile_with_source_name(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+    source: &[u8],
+    source_name: &str,
+    limits: Option<&ResourceLimits>,
+) -> Result<CompileResult, CompileError> {
+    let command =
+        compile_command(sandbox, config, language, source, Some(source_name), limits).await?;
+    let (result, output) = run_with_split_output(sandbox, command)
+        .await
+        .map_err(CompileError::Isolate)?;
+    Ok(compile_result(config, result, output))
+}
+
+/// Compile source code, forwarding compiler output to `sink` as it is produced
+///
+/// See [`run_with_output_streaming`](crate::isolate::run_with_output_streaming)
+/// for the output latency. The returned [`CompileResult`] is the same as
+/// [`compile`] would return.
+#[instrument(skip(sandbox, config, source, sink), fields(label = sandbox.label()))]
+pub async fn compile_streaming<W: AsyncWrite + Unpin>(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+    source: &[u8],
+    limits: Option<&ResourceLimits>,
+    sink: &mut W,
+) -> Result<CompileResult, CompileError> {
+    let command = compile_command(sandbox, config, language, source, None, limits).await?;
+    let (result, output) = run_with_split_output_streaming(sandbox, command, sink)
+        .await
+        .map_err(CompileError::Isolate)?;
+    Ok(compile_result(config, result, output))
+}
+
+/// Write the source into the sandbox and build the compile command
+///
+/// `source_name` overrides the language's configured source file name.
+async fn compile_command(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+    source: &[u8],
+    source_name: Option<&str>,
+    limits: Option<&ResourceLimits>,
+) -> Result<IsolateCommand, CompileError> {
     // Check if language requires compilation
     let compile_config = language
         .compile
         .as_ref()
         .ok_or_else(|| CompileError::NotCompiled(language.name.clone()))?;
 
+    if let Some(limit) = config.source_size_exceeded(source.len()) {
+        return Err(CompileError::SourceTooLarge {
+            size: source.len(),
+            limit,
+        });
+    }
+
     // Write source file to sandbox
-    let source_name = &compile_config.source_name;
+    let source_name = source_name.unwrap_or(&compile_config.source_name);
+    check_source_name(config, language, source_name).map_err(CompileError::Isolate)?;
     sandbox
         .write_file(source_name, source)
         .await
@@ -67,14 +198,7 @@ pub async fn compile(
     debug!(source_name, "wrote source file");
 
     // Determine limits
-    let base_limits = default_compile_limits();
-    let lang_limits = compile_config.limits.as_ref();
-    let effective_limits = match (limits, lang_limits) {
-        (Some(user), Some(lang)) => base_limits.with_overrides(lang).with_overrides(user),
-        (Some(user), None) => base_limits.with_overrides(user),
-        (None, Some(lang)) => base_limits.with_overrides(lang),
-        (None, None) => base_limits,
-    };
+    let effective_limits = effective_compile_limits(compile_config.limits.as_ref(), limits);
 
     // Build compile command with resolved path (isolate uses execve, not execvp)
     let mut expanded_cmd = Language::expand_command(
@@ -84,24 +208,72 @@ pub async fn compile(
     );
     resolve_command(&mut expanded_cmd).map_err(CompileError::Isolate)?;
 
-    let mut command = IsolateCommand::new(config.isolate_binary(), sandbox.id())
+    // Validate mount source paths exist before compiling
+    validate_mounts(&config.resolve_mounts(&compile_config.mounts))
+        .map_err(CompileError::Isolate)?;
+
+    Ok(build_compile_command(
+        IsolateCommand::new(sandbox.isolate_path(), sandbox.id()),
+        config,
+        compile_config,
+        source_name,
+        effective_limits,
+        expanded_cmd,
+    ))
+}
+
+/// Add the compile settings and resolved `cmd` to `base`
+///
+/// Split from [`compile_command`] so the arguments can be checked without
+/// a box or an installed compiler.
+fn build_compile_command(
+    base: IsolateCommand,
+    config: &Config,
+    compile_config: &CompileConfig,
+    source_name: &str,
+    limits: ResourceLimits,
+    cmd: Vec<String>,
+) -> IsolateCommand {
+    let mut command = base
         .action(IsolateAction::Run)
         .cgroup(config.cgroup)
-        .limits(effective_limits)
+        .internal_file_prefix(config.internal_file_prefix.clone())
+        .limits(limits)
         .working_dir("/box")
-        .env("PATH", DEFAULT_SANDBOX_PATH)
+        .env("PATH", &compile_config.path)
+        .share_net(compile_config.share_net)
+        .mount_source_root(config.mount_source_root.clone())
         .mounts(config.sandbox_mounts.iter().cloned())
-        .command(expanded_cmd);
+        .mounts(compile_config.mounts.iter().cloned())
+        .command(cmd);
 
     // Add environment variables from compile config
-    for (key, value) in &compile_config.env {
+    let env = Language::expand_env(
+        &compile_config.env,
+        source_name,
+        &compile_config.output_name,
+        &config.placeholders,
+    );
+    for (key, value) in env {
         command = command.env(key, value);
     }
+    for key in &compile_config.inherit_env {
+        command = command.env_inherit(key);
+    }
 
-    // Run compilation
-    let (result, mut output) = run_with_output(sandbox, command)
-        .await
-        .map_err(CompileError::Isolate)?;
+    command
+}
+
+/// Build a [`CompileResult`] from the finished compile command
+fn compile_result(
+    config: &Config,
+    mut result: ExecutionResult,
+    captured: CapturedOutput,
+) -> CompileResult {
+    result.apply_memory_report(config.memory_report);
+    if !config.collect_timings {
+        result.timings = None;
+    }
 
     let success = result.exit_code == Some(0);
 
@@ -114,22 +286,102 @@ pub async fn compile(
     );
 
     // Include isolate's error message in output if the sandboxed process produced nothing
+    let mut output = captured.combined();
     if output.is_empty()
         && let Some(ref msg) = result.message
     {
         output = msg.clone();
     }
 
-    Ok(CompileResult {
+    let result = CompileResult {
         success,
         execution: result,
         output,
-    })
+        stdout: captured.stdout,
+        stderr: captured.stderr,
+    };
+    if result.hit_process_limit() {
+        warn!("compilation appears to have hit the process limit; raise compile max_processes");
+    }
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::isolate::MetaFile;
+
+    fn compile_args(config: &Config, id: &str) -> Vec<String> {
+        let compile_config = config.languages[id].compile.as_ref().unwrap();
+        build_compile_command(
+            IsolateCommand::new("isolate", 0),
+            config,
+            compile_config,
+            &compile_config.source_name,
+            default_compile_limits(),
+            vec!["/usr/bin/gcc".to_owned()],
+        )
+        .build()
+    }
+
+    #[test]
+    fn test_compile_command_uses_compile_path() {
+        let toml = r#"
+[languages.c]
+name = "C"
+extension = "c"
+
+[languages.c.compile]
+command = ["gcc", "{source}"]
+source_name = "main.c"
+output_name = "a.out"
+path = "/opt/gcc/bin:/usr/bin:/bin"
+
+[languages.c.run]
+command = ["./{binary}"]
+
+[languages.c_default]
+name = "C (default path)"
+extension = "c"
+
+[languages.c_default.compile]
+command = ["gcc", "{source}"]
+source_name = "main.c"
+output_name = "a.out"
+
+[languages.c_default.run]
+command = ["./{binary}"]
+"#;
+        let config = Config::parse_toml(toml).unwrap();
+
+        let args = compile_args(&config, "c");
+        assert!(args.contains(&"--env=PATH=/opt/gcc/bin:/usr/bin:/bin".to_owned()));
+        let args = compile_args(&config, "c_default");
+        assert!(args.contains(&"--env=PATH=/usr/bin:/bin".to_owned()));
+    }
+
+    #[test]
+    fn test_compile_command_share_net() {
+        let toml = r#"
+[languages.rust]
+name = "Rust"
+extension = "rs"
+
+[languages.rust.compile]
+command = ["cargo", "build"]
+source_name = "main.rs"
+output_name = "main"
+share_net = true
+
+[languages.rust.run]
+command = ["./{binary}"]
+"#;
+        let config = Config::parse_toml(toml).unwrap();
+        assert!(compile_args(&config, "rust").contains(&"--share-net".to_owned()));
+
+        let config = Config::parse_toml(&toml.replace("share_net = true\n", "")).unwrap();
+        assert!(!compile_args(&config, "rust").contains(&"--share-net".to_owned()));
+    }
 
     #[test]
     fn test_default_compile_limits() {
@@ -137,4 +389,128 @@ mod tests {
         assert_eq!(limits.time_limit, Some(30.0));
         assert_eq!(limits.memory_limit, Some(524288));
     }
+
+    /// Limits with every field unset, as parsed from a partial TOML table
+    fn unset_limits() -> ResourceLimits {
+        ResourceLimits {
+            time_limit: None,
+            wall_time_limit: None,
+            memory_limit: None,
+            stack_limit: None,
+            max_processes: None,
+            max_output: None,
+            max_open_files: None,
+            extra_time: None,
+        }
+    }
+
+    #[test]
+    fn test_compile_limits_without_overrides() {
+        let limits = effective_compile_limits(None, None);
+        assert_eq!(limits.time_limit, Some(30.0));
+        assert_eq!(limits.wall_time_limit, Some(60.0));
+    }
+
+    #[test]
+    fn test_compile_limits_language_wall_time_only() {
+        let lang = ResourceLimits {
+            wall_time_limit: Some(120.0),
+            ..unset_limits()
+        };
+        let limits = effective_compile_limits(Some(&lang), None);
+        assert_eq!(limits.wall_time_limit, Some(120.0));
+        // CPU time stays at the compile default
+        assert_eq!(limits.time_limit, Some(30.0));
+        assert_eq!(limits.memory_limit, Some(524288));
+    }
+
+    #[test]
+    fn test_compile_limits_user_overrides_language() {
+        let lang = ResourceLimits {
+            wall_time_limit: Some(120.0),
+            max_processes: Some(50),
+            ..unset_limits()
+        };
+        let user = ResourceLimits {
+            wall_time_limit: Some(10.0),
+            ..unset_limits()
+        };
+        let limits = effective_compile_limits(Some(&lang), Some(&user));
+        assert_eq!(limits.wall_time_limit, Some(10.0));
+        assert_eq!(limits.max_processes, Some(50));
+        assert_eq!(limits.time_limit, Some(30.0));
+    }
+
+    #[test]
+    fn test_compile_limits_ignore_run_defaults() {
+        // Config::default_limits is a run-time setting and must not be the base
+        let limits = effective_compile_limits(None, None);
+        assert_ne!(limits.time_limit, ResourceLimits::default().time_limit);
+    }
+
+    fn compile_result_from_meta(meta: &str, output: &str) -> CompileResult {
+        let result = MetaFile::parse(meta).to_execution_result();
+        let captured = CapturedOutput {
+            stdout: String::new(),
+            stderr: output.to_string(),
+        };
+        compile_result(&Config::default(), result, captured)
+    }
+
+    #[test]
+    fn test_compile_output_kept_separate() {
+        let result = MetaFile::parse("status:RE\nexitcode:1\ntime:0.050\n").to_execution_result();
+        let captured = CapturedOutput {
+            stdout: "Compiling main.rs\n".to_string(),
+            stderr: "error[E0425]: cannot find value `x`\n".to_string(),
+        };
+        let result = compile_result(&Config::default(), result, captured);
+        assert_eq!(
+            result.output,
+            "Compiling main.rs\n\nerror[E0425]: cannot find value `x`\n"
+        );
+        assert_eq!(result.stdout, "Compiling main.rs\n");
+        assert_eq!(result.stderr, "error[E0425]: cannot find value `x`\n");
+
+        // Isolate's message only fills the combined output
+        let result = MetaFile::parse("status:XX\nmessage:execve failed\n").to_execution_result();
+        let result = compile_result(&Config::default(), result, CapturedOutput::default());
+        assert_eq!(result.output, "execve failed");
+        assert!(result.stdout.is_empty() && result.stderr.is_empty());
+    }
+
+    #[test]
+    fn test_hit_process_limit_from_fork_failure() {
+        let result = compile_result_from_meta(
+            "status:RE\nexitcode:1\ntime:0.050\nmessage:Exited with error status 1\n",
+            "g++: fatal error: cannot execute 'cc1plus': vfork: Resource temporarily unavailable\n",
+        );
+        assert!(!result.is_success());
+        assert!(result.hit_process_limit());
+    }
+
+    #[test]
+    fn test_hit_process_limit_from_go_thread_failure() {
+        let result = compile_result_from_meta(
+            "status:SG\nexitsig:6\ntime:0.200\nmessage:Caught fatal signal 6\n",
+            "runtime: failed to create new OS thread (have 5 already; errno=11)\n",
+        );
+        assert!(result.hit_process_limit());
+    }
+
+    #[test]
+    fn test_hit_process_limit_ignores_ordinary_errors() {
+        let result = compile_result_from_meta(
+            "status:RE\nexitcode:1\ntime:0.050\n",
+            "main.cpp:1:1: error: 'foo' does not name a type\n",
+        );
+        assert!(!result.hit_process_limit());
+
+        // The message alone is not enough if the compile timed out
+        let result = compile_result_from_meta(
+            "status:TO\ntime:30.000\nmessage:Time limit exceeded\n",
+            "make: vfork: Resource temporarily unavailable\n",
+        );
+        assert!(!result.hit_process_limit());
+    }
 }
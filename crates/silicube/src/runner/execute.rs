@@ -2,31 +2,195 @@
 //!
 //! Handles running compiled or interpreted programs with input/output.
 
+use std::collections::HashMap;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::Path;
+use std::time::Duration;
+
+use tokio::io::AsyncRead;
 use tracing::{debug, instrument};
 
 use crate::config::{Config, Language};
 use crate::isolate::{
-    IsolateAction, IsolateBox, IsolateCommand, resolve_command, run_batch, validate_mounts,
+    BatchStdin, IsolateAction, IsolateBox, IsolateCommand, IsolateError, MAX_LIST_DEPTH,
+    WrapperTimeout, resolve_command, run_batch_with, run_with_output, validate_mounts,
+    validate_seccomp_profile, validate_working_dir,
 };
-use crate::runner::ExecuteError;
-use crate::types::{ExecutionResult, ResourceLimits};
+use crate::runner::compile::effective_compile_limits;
+use crate::runner::{CompileError, ExecuteError, check_source_name};
+use crate::types::{ExecutionResult, MountConfig, ResourceLimits};
 
 /// Execute a program in an Isolate box with batch I/O
-#[instrument(skip(sandbox, config, input))]
 pub async fn execute(
     sandbox: &IsolateBox,
     config: &Config,
     language: &Language,
     input: Option<&[u8]>,
     limits: Option<&ResourceLimits>,
+) -> Result<ExecutionResult, ExecuteError> {
+    execute_with_args(
+        sandbox,
+        config,
+        language,
+        &[],
+        BatchStdin::Data(input),
+        limits,
+        config.capture_stderr,
+    )
+    .await
+}
+
+/// Execute a program with the most restrictive settings, whatever the config
+///
+/// On top of the usual run, this guarantees:
+/// - a single process (`max_processes = 1`), so no forks or threads
+/// - time, wall time, memory, output and open-file limits no looser than
+///   [`locked_down_limits`]; tighter configured or requested limits still apply
+/// - no mounts beyond isolate's defaults (no config, interpreted or language mounts)
+/// - only the language's explicit environment, with no inherited host
+///   variables and no `--full-env`
+/// - no network, even if the language's run config shares it
+///
+/// Languages whose runtime needs threads or extra mounts (e.g. Java, Go)
+/// will fail under these settings.
+#[instrument(skip(sandbox, config, input), fields(label = sandbox.label()))]
+pub async fn execute_locked_down(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+    input: Option<&[u8]>,
+    limits: Option<&ResourceLimits>,
+) -> Result<ExecutionResult, ExecuteError> {
+    let (config, language, limits) = lock_down(config, language, limits);
+    execute_with_args(
+        sandbox,
+        &config,
+        &language,
+        &[],
+        BatchStdin::Data(input),
+        Some(&limits),
+        config.capture_stderr,
+    )
+    .await
+}
+
+/// Upper bounds applied by [`execute_locked_down`]
+pub fn locked_down_limits() -> ResourceLimits {
+    ResourceLimits {
+        time_limit: Some(2.0),
+        wall_time_limit: Some(5.0),
+        memory_limit: Some(256 * ResourceLimits::MB),
+        stack_limit: Some(64 * ResourceLimits::MB),
+        max_processes: Some(1),
+        max_output: Some(16 * ResourceLimits::MB),
+        max_open_files: Some(16),
+        extra_time: Some(0.0),
+    }
+}
+
+/// Strip permissive settings from `config` and `language` and clamp limits
+///
+/// The returned limits are fully populated, so they override every layer
+/// when passed as the user limits.
+fn lock_down(
+    config: &Config,
+    language: &Language,
+    limits: Option<&ResourceLimits>,
+) -> (Config, Language, ResourceLimits) {
+    let mut effective = config.default_limits.clone();
+    if let Some(ref lang_limits) = language.run.limits {
+        effective = effective.with_overrides(lang_limits);
+    }
+    if let Some(user_limits) = limits {
+        effective = effective.with_overrides(user_limits);
+    }
+
+    let cap = locked_down_limits();
+    let limits = ResourceLimits {
+        time_limit: tighter(effective.time_limit, cap.time_limit),
+        wall_time_limit: tighter(effective.wall_time_limit, cap.wall_time_limit),
+        memory_limit: tighter(effective.memory_limit, cap.memory_limit),
+        stack_limit: tighter(effective.stack_limit, cap.stack_limit),
+        max_processes: cap.max_processes,
+        max_output: tighter(effective.max_output, cap.max_output),
+        max_open_files: tighter(effective.max_open_files, cap.max_open_files),
+        extra_time: tighter(effective.extra_time, cap.extra_time),
+    };
+
+    let mut config = config.clone();
+    config.sandbox_mounts.clear();
+    config.interpreted_mounts.clear();
+
+    let mut language = language.clone();
+    language.run.mounts.clear();
+    language.run.inherit_env.clear();
+    language.run.share_net = false;
+    language.run.limits = None;
+
+    (config, language, limits)
+}
+
+/// The smaller of two optional limits, treating unset as unlimited
+fn tighter<T: PartialOrd + Copy>(value: Option<T>, cap: Option<T>) -> Option<T> {
+    match (value, cap) {
+        (Some(value), Some(cap)) if value < cap => Some(value),
+        (value, cap) => cap.or(value),
+    }
+}
+
+/// Execute a program with stdin streamed from a reader
+///
+/// The reader is copied into the box before the run instead of being
+/// collected in memory, so inputs of any size can be piped in.
+#[instrument(skip(sandbox, config, stdin), fields(label = sandbox.label()))]
+pub async fn execute_streaming<R: AsyncRead + Unpin>(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+    stdin: R,
+    limits: Option<&ResourceLimits>,
+) -> Result<ExecutionResult, ExecuteError> {
+    let len = sandbox
+        .write_file_from(&config.internal_file("stdin.txt"), stdin)
+        .await?;
+    debug!(len, "streamed stdin into box");
+    execute_with_args(
+        sandbox,
+        config,
+        language,
+        &[],
+        BatchStdin::Written,
+        limits,
+        config.capture_stderr,
+    )
+    .await
+}
+
+/// Execute a program with extra arguments appended to its run command
+///
+/// `capture_stderr` overrides [`Config::capture_stderr`] for callers that
+/// always need stderr.
+#[instrument(skip(sandbox, config, stdin), fields(label = sandbox.label()))]
+pub(crate) async fn execute_with_args(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+    extra_args: &[String],
+    stdin: BatchStdin<'_>,
+    limits: Option<&ResourceLimits>,
+    capture_stderr: bool,
 ) -> Result<ExecutionResult, ExecuteError> {
     // Determine effective limits: config defaults → language run limits → user overrides
     let mut effective_limits = config.default_limits.clone();
     if let Some(ref lang_limits) = language.run.limits {
-        effective_limits = effective_limits.with_overrides(lang_limits);
+        let layered = effective_limits.with_overrides(lang_limits);
+        debug!(changes = ?effective_limits.diff(&layered), "language run limits applied");
+        effective_limits = layered;
     }
     if let Some(user_limits) = limits {
-        effective_limits = effective_limits.with_overrides(user_limits);
+        let layered = effective_limits.with_overrides(user_limits);
+        debug!(changes = ?effective_limits.diff(&layered), "requested limits applied");
+        effective_limits = layered;
     }
 
     // Determine the command based on whether it's compiled or interpreted
@@ -36,13 +200,12 @@ pub async fn execute(
 
         // Check if binary exists
         if !sandbox.file_exists(binary).await? {
-            return Err(ExecuteError::NotStarted(format!(
-                "binary '{}' not found in sandbox - was compilation run?",
-                binary
-            )));
+            return Err(ExecuteError::BinaryMissing {
+                name: binary.clone(),
+            });
         }
 
-        Language::expand_command(&language.run.command, &compile_config.source_name, binary)
+        language.resolved_run_command()?
     } else {
         // Interpreted language - source should already be in sandbox
         let source_name = language.source_name();
@@ -55,44 +218,39 @@ pub async fn execute(
             )));
         }
 
-        Language::expand_command(&language.run.command, &source_name, &source_name)
+        language.resolved_run_command()?
     };
-
-    // Resolve command path (isolate uses execve, not execvp)
-    resolve_command(&mut run_cmd).map_err(ExecuteError::Isolate)?;
+    run_cmd.extend(extra_args.iter().cloned());
 
     debug!(?run_cmd, "executing program");
 
     // Validate mount source paths exist before running
-    validate_mounts(&language.run.mounts).map_err(ExecuteError::Isolate)?;
+    validate_mounts(&config.resolve_mounts(&language.run.mounts)).map_err(ExecuteError::Isolate)?;
+    validate_working_dir(&language.run.working_dir).map_err(ExecuteError::Isolate)?;
+    validate_seccomp_profile(language.run.seccomp_profile.as_deref())
+        .map_err(ExecuteError::Isolate)?;
 
     // Save memory limit before effective_limits is moved
     let memory_limit = effective_limits.memory_limit;
 
-    // Build execute command
-    let mut command = IsolateCommand::new(config.isolate_binary(), sandbox.id())
-        .action(IsolateAction::Run)
-        .cgroup(config.cgroup)
-        .limits(effective_limits)
-        .working_dir("/box")
-        .env("PATH", &language.run.path)
-        .mounts(config.sandbox_mounts.iter().cloned())
-        .mounts(language.run.mounts.iter().cloned())
-        .command(run_cmd);
-
-    // Add environment variables from language config
-    for (key, value) in &language.run.env {
-        command = command.env(key, value);
-    }
+    let command = run_command(sandbox, config, language, effective_limits, run_cmd)
+        .capture_stderr(capture_stderr);
 
     // Run the program
-    let mut result = run_batch(sandbox, command, input)
+    let mut result = run_batch_with(sandbox, command, stdin)
         .await
         .map_err(ExecuteError::Isolate)?;
 
+    result.apply_memory_report(config.memory_report);
+    if !config.collect_timings {
+        result.timings = None;
+    }
     if let Some(mem_limit) = memory_limit {
         result.detect_memory_limit(mem_limit);
     }
+    if config.report_disk_usage {
+        result.disk_usage = Some(sandbox.disk_usage().await?);
+    }
 
     debug!(
         status = ?result.status,
@@ -105,8 +263,241 @@ pub async fn execute(
     Ok(result)
 }
 
+/// Build an isolate command running `cmd` with the language's run settings
+fn run_command(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+    limits: ResourceLimits,
+    cmd: Vec<String>,
+) -> IsolateCommand {
+    build_run_command(
+        IsolateCommand::new(sandbox.isolate_path(), sandbox.id()),
+        config,
+        language,
+        limits,
+        cmd,
+    )
+}
+
+/// Apply the language's run settings to `base`
+fn build_run_command(
+    base: IsolateCommand,
+    config: &Config,
+    language: &Language,
+    limits: ResourceLimits,
+    cmd: Vec<String>,
+) -> IsolateCommand {
+    let mut command = base
+        .action(IsolateAction::Run)
+        .cgroup(config.cgroup)
+        .internal_file_prefix(config.internal_file_prefix.clone())
+        .limits(limits)
+        .working_dir(&language.run.working_dir)
+        .seccomp_profile(language.run.seccomp_profile.clone())
+        .share_net(language.run.share_net)
+        .env("PATH", language.run.sandbox_path())
+        .mount_source_root(config.mount_source_root.clone())
+        .mounts(config.run_mounts(language))
+        .mounts(language.run.mounts.iter().cloned())
+        .wrapper_timeout(config.wrapper_timeout.map(|timeout| WrapperTimeout {
+            timeout: Duration::from_secs_f64(timeout),
+            kill_grace: Duration::from_secs_f64(config.wrapper_kill_grace),
+        }))
+        .command(cmd);
+
+    // Add environment variables from language config
+    let env = Language::expand_env(
+        &language.run.env,
+        &language.source_name(),
+        &language.binary_name(),
+        &config.placeholders,
+    );
+    for (key, value) in env {
+        command = command.env(key, value);
+    }
+    for key in &language.run.inherit_env {
+        command = command.env_inherit(key);
+    }
+    command
+}
+
+/// Execute a program and collect the files it writes into a read-write mount
+///
+/// The mount's host `source` directory must be missing or empty; it is
+/// created if needed and handed to the box user. Returns the execution
+/// result and the regular files found there afterward, keyed by path
+/// relative to the mount. Symlinks and other special files the program
+/// leaves behind are skipped, and collecting more than
+/// [`OUTPUT_MOUNT_READ_LIMIT`] bytes fails with [`ExecuteError::OutputTooLarge`].
+#[instrument(skip(sandbox, config, input), fields(label = sandbox.label()))]
+pub async fn execute_with_output_mount(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+    input: Option<&[u8]>,
+    limits: Option<&ResourceLimits>,
+    output_mount: &MountConfig,
+) -> Result<(ExecutionResult, HashMap<String, Vec<u8>>), ExecuteError> {
+    if !output_mount.writable || output_mount.tmp || output_mount.source.is_empty() {
+        return Err(ExecuteError::NotStarted(format!(
+            "output mount '{}' must be a writable host directory",
+            output_mount.target
+        )));
+    }
+
+    let output_mount = match config.mount_source_root {
+        Some(ref root) => output_mount.resolve_source(root),
+        None => output_mount.clone(),
+    };
+
+    // Start from an empty directory so only this run's files are collected
+    let host_dir = Path::new(&output_mount.source);
+    prepare_output_dir(sandbox, host_dir).await?;
+
+    let mut language = language.clone();
+    language.run.mounts.push(output_mount.clone());
+    let result = execute(sandbox, config, &language, input, limits).await?;
+
+    let files = read_output_files(host_dir, OUTPUT_MOUNT_READ_LIMIT).await?;
+    debug!(count = files.len(), "collected output mount files");
+
+    Ok((result, files))
+}
+
+/// Run the language's prepare command in the box, if it has one
+///
+/// Limits are the compile defaults overridden by `run.prepare_limits`.
+pub(super) async fn run_prepare_step(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+) -> Result<(), CompileError> {
+    let Some(mut prepare_cmd) = language.expand_prepare_command() else {
+        return Ok(());
+    };
+    resolve_command(&mut prepare_cmd)?;
+    validate_mounts(&config.resolve_mounts(&language.run.mounts))?;
+    debug!(?prepare_cmd, "running prepare step");
+
+    let limits = effective_compile_limits(language.run.prepare_limits.as_ref(), None);
+    let command = run_command(sandbox, config, language, limits, prepare_cmd);
+    let (result, output) = run_with_output(sandbox, command).await?;
+    if !result.is_success() {
+        return Err(CompileError::PrepareFailed {
+            status: result.status,
+            output,
+        });
+    }
+    Ok(())
+}
+
+/// Most bytes [`execute_with_output_mount`] reads back from the mount
+pub const OUTPUT_MOUNT_READ_LIMIT: u64 = 64 * 1024 * 1024;
+
+/// Create `dir` for an output mount, or check an existing one is empty
+///
+/// The directory is owned by the box user (the owner of the box directory)
+/// and private to it, rather than world-writable.
+async fn prepare_output_dir(sandbox: &IsolateBox, dir: &Path) -> Result<(), ExecuteError> {
+    match tokio::fs::symlink_metadata(dir).await {
+        Ok(meta) if !meta.is_dir() => {
+            return Err(ExecuteError::NotStarted(format!(
+                "output mount source '{}' is not a directory",
+                dir.display()
+            )));
+        }
+        Ok(_) => {
+            let mut entries = tokio::fs::read_dir(dir).await.map_err(IsolateError::from)?;
+            if entries
+                .next_entry()
+                .await
+                .map_err(IsolateError::from)?
+                .is_some()
+            {
+                return Err(ExecuteError::NotStarted(format!(
+                    "output mount source '{}' is not empty",
+                    dir.display()
+                )));
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(IsolateError::from)?;
+        }
+        Err(e) => return Err(IsolateError::from(e).into()),
+    }
+
+    let owner = tokio::fs::metadata(sandbox.path().join("box"))
+        .await
+        .map_err(IsolateError::from)?;
+    std::os::unix::fs::chown(dir, Some(owner.uid()), Some(owner.gid()))
+        .map_err(IsolateError::from)?;
+    tokio::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+        .await
+        .map_err(IsolateError::from)?;
+    Ok(())
+}
+
+/// Read the regular files under `root`, keyed by relative path
+///
+/// Symlinks are neither followed nor returned, so a program cannot point
+/// an entry at a host file. Fails once more than `limit` bytes are read.
+async fn read_output_files(
+    root: &Path,
+    limit: u64,
+) -> Result<HashMap<String, Vec<u8>>, ExecuteError> {
+    use tokio::io::AsyncReadExt;
+
+    let mut files = HashMap::new();
+    let mut total = 0u64;
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(&dir)
+            .await
+            .map_err(IsolateError::from)?;
+        while let Some(entry) = entries.next_entry().await.map_err(IsolateError::from)? {
+            let path = entry.path();
+            // Does not follow symlinks
+            let file_type = entry.file_type().await.map_err(IsolateError::from)?;
+            if file_type.is_dir() {
+                if depth < MAX_LIST_DEPTH {
+                    pending.push((path, depth + 1));
+                }
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+
+            let file = tokio::fs::OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NOFOLLOW)
+                .open(&path)
+                .await
+                .map_err(IsolateError::from)?;
+            let mut content = Vec::new();
+            // Read one byte past the budget to detect overflow
+            file.take(limit - total + 1)
+                .read_to_end(&mut content)
+                .await
+                .map_err(IsolateError::from)?;
+            total += content.len() as u64;
+            if total > limit {
+                return Err(ExecuteError::OutputTooLarge { limit });
+            }
+            files.insert(relative.to_string_lossy().into_owned(), content);
+        }
+    }
+    Ok(files)
+}
+
 /// Execute an interpreted program by writing source and running
-#[instrument(skip(sandbox, config, source, input))]
+#[instrument(skip(sandbox, config, source, input), fields(label = sandbox.label()))]
 pub async fn execute_interpreted(
     sandbox: &IsolateBox,
     config: &Config,
@@ -115,8 +506,16 @@ pub async fn execute_interpreted(
     input: Option<&[u8]>,
     limits: Option<&ResourceLimits>,
 ) -> Result<ExecutionResult, ExecuteError> {
+    if let Some(limit) = config.source_size_exceeded(source.len()) {
+        return Err(ExecuteError::SourceTooLarge {
+            size: source.len(),
+            limit,
+        });
+    }
+
     // Write source file
     let source_name = language.source_name();
+    check_source_name(config, language, &source_name)?;
     sandbox
         .write_file(&source_name, source)
         .await
@@ -127,3 +526,116 @@ pub async fn execute_interpreted(
     // Execute
     execute(sandbox, config, language, input, limits).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_output_files_skips_symlinks_and_caps_size() {
+        let dir = std::env::temp_dir().join(format!("silicube-output-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("a.txt"), b"hello").unwrap();
+        std::fs::write(dir.join("nested/b.txt"), b"world").unwrap();
+        std::os::unix::fs::symlink("/etc/hostname", dir.join("leak")).unwrap();
+
+        let files = read_output_files(&dir, 1024).await.unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files["a.txt"], b"hello");
+        assert_eq!(files["nested/b.txt"], b"world");
+
+        let err = read_output_files(&dir, 8).await.unwrap_err();
+        assert!(matches!(err, ExecuteError::OutputTooLarge { limit: 8 }));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn mount(path: &str) -> MountConfig {
+        MountConfig {
+            source: path.to_string(),
+            target: path.to_string(),
+            writable: false,
+            optional: false,
+            tmp: false,
+            dev: false,
+        }
+    }
+
+    #[test]
+    fn test_run_command_share_net_is_separate_from_compile() {
+        let toml = r#"
+[languages.rust]
+name = "Rust"
+extension = "rs"
+
+[languages.rust.compile]
+command = ["cargo", "build"]
+source_name = "main.rs"
+output_name = "main"
+share_net = true
+
+[languages.rust.run]
+command = ["/box/{binary}"]
+"#;
+        let run_args = |config: &Config| {
+            let language = &config.languages["rust"];
+            build_run_command(
+                IsolateCommand::new("isolate", 0),
+                config,
+                language,
+                ResourceLimits::default(),
+                language.expand_run_command(),
+            )
+            .build()
+        };
+
+        // Network during the build does not carry over to the run
+        let config = Config::parse_toml(toml).unwrap();
+        assert!(!run_args(&config).contains(&"--share-net".to_string()));
+
+        let toml = format!("{toml}share_net = true\n");
+        let config = Config::parse_toml(&toml).unwrap();
+        assert!(run_args(&config).contains(&"--share-net".to_string()));
+    }
+
+    #[test]
+    fn test_locked_down_args() {
+        let config = Config {
+            cgroup: true,
+            sandbox_mounts: vec![mount("/opt/host-lib")],
+            ..Config::default()
+        };
+        let mut language = config.languages["python3"].clone();
+        language.run.mounts = vec![mount("/etc")];
+        language.run.inherit_env = vec!["HOME".to_string()];
+        language.run.share_net = true;
+        language.run.limits = Some(ResourceLimits {
+            max_processes: Some(64),
+            memory_limit: Some(4 * ResourceLimits::GB),
+            time_limit: Some(0.5),
+            ..Default::default()
+        });
+
+        let (config, language, limits) = lock_down(&config, &language, None);
+        let args = build_run_command(
+            IsolateCommand::new("isolate", 0),
+            &config,
+            &language,
+            limits,
+            language.expand_run_command(),
+        )
+        .build();
+
+        assert!(args.contains(&"--processes=1".to_string()));
+        assert!(args.contains(&format!("--cg-mem={}", 256 * ResourceLimits::MB)));
+        // Limits already tighter than the cap are kept
+        assert!(args.contains(&"--time=0.5".to_string()));
+        assert!(args.contains(&"--wall-time=5".to_string()));
+
+        assert!(!args.iter().any(|a| a.starts_with("--dir=")));
+        assert!(!args.contains(&"--full-env".to_string()));
+        assert!(!args.contains(&"--share-net".to_string()));
+        assert!(!args.contains(&"--env=HOME".to_string()));
+    }
+}
@@ -1,22 +1,26 @@
 //! Interactive I/O handling for code execution
 //!
-//! Provides FIFO-based interactive sessions for programs that require
+//! Provides pipe-based interactive sessions for programs that require
 //! back-and-forth communication (e.g., interactive problems, REPLs).
 
-use std::sync::Arc;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
-use tokio::process::{ChildStderr, ChildStdout};
-use tokio::sync::{Notify, mpsc};
+use tokio::process::{ChildStderr, ChildStdin, ChildStdout};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore, mpsc, watch};
+use tokio::time::Instant;
 use tracing::{debug, instrument, warn};
 
 use crate::config::{Config, Language};
 use crate::isolate::{
-    IsolateAction, IsolateBox, IsolateCommand, IsolateProcess, resolve_command, validate_mounts,
+    IsolateAction, IsolateBox, IsolateCommand, IsolateError, IsolateProcess, validate_mounts,
+    validate_seccomp_profile, validate_working_dir,
 };
 use crate::runner::InteractiveError;
-use crate::types::{ExecutionResult, ResourceLimits};
+use crate::types::{ExecutionResult, ExecutionStatus, MemoryReportMode, ResourceLimits};
 
 /// Event from an interactive session
 #[derive(Debug, Clone)]
@@ -33,10 +37,58 @@ pub enum InteractiveEvent {
     /// A complete line was received on stderr
     StderrLine(String),
 
+    /// A write of this many bytes was flushed to stdin
+    ///
+    /// Only emitted by streams created with
+    /// [`InteractiveEventStream::new_with_acks`].
+    StdinWritten(usize),
+
     /// The process exited
     Exited(ExecutionResult),
 }
 
+/// Line terminator appended by `write_line`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+
+    /// `\r\n`
+    CrLf,
+}
+
+impl LineEnding {
+    /// Get the terminator bytes
+    pub fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+
+    /// Append `line` and this terminator to a new buffer
+    fn terminate(self, line: &str) -> Vec<u8> {
+        let mut data = Vec::with_capacity(line.len() + 2);
+        data.extend_from_slice(line.as_bytes());
+        data.extend_from_slice(self.as_bytes());
+        data
+    }
+}
+
+/// Resource usage of a still-running interactive program
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PartialUsage {
+    /// Wall clock time since the session started, in seconds
+    pub wall_time: f64,
+
+    /// CPU time used so far in seconds, if cgroup accounting is available
+    pub cpu_time: Option<f64>,
+
+    /// Current cgroup memory in kilobytes, if cgroup accounting is available
+    pub memory: Option<u64>,
+}
+
 /// An interactive execution session
 pub struct InteractiveSession {
     process: IsolateProcess,
@@ -44,12 +96,28 @@ pub struct InteractiveSession {
     stdout_reader: Option<BufReader<ChildStdout>>,
     /// Buffered reader for stderr - stored to preserve buffered data between reads
     stderr_reader: Option<BufReader<ChildStderr>>,
+    /// Memory measurement reported in the final result
+    memory_report: MemoryReportMode,
+    /// Overall deadline for reads and writes, if any
+    deadline: Option<Instant>,
+    /// Whether the deadline has been exceeded
+    deadline_exceeded: bool,
+    /// When the process was spawned
+    started_at: Instant,
+    /// Isolate's cgroup for this box, when cgroups are enabled
+    cgroup_dir: Option<PathBuf>,
+    /// Terminator appended by `write_line`
+    line_ending: LineEnding,
+    /// Bound on the response read in `exchange`
+    exchange_timeout: Option<Duration>,
+    /// Shutdown requests from a [`SessionRegistry`], if registered
+    shutdown: Option<watch::Receiver<ShutdownStage>>,
     terminated: bool,
 }
 
 impl InteractiveSession {
     /// Start a new interactive session
-    #[instrument(skip(sandbox, config))]
+    #[instrument(skip(sandbox, config), fields(label = sandbox.label()))]
     pub async fn start(
         sandbox: &IsolateBox,
         config: &Config,
@@ -59,53 +127,57 @@ impl InteractiveSession {
         // Determine effective limits: config defaults → language run limits → user overrides
         let mut effective_limits = config.default_limits.clone();
         if let Some(ref lang_limits) = language.run.limits {
-            effective_limits = effective_limits.with_overrides(lang_limits);
+            let layered = effective_limits.with_overrides(lang_limits);
+            debug!(changes = ?effective_limits.diff(&layered), "language run limits applied");
+            effective_limits = layered;
         }
         if let Some(user_limits) = limits {
-            effective_limits = effective_limits.with_overrides(user_limits);
+            let layered = effective_limits.with_overrides(user_limits);
+            debug!(changes = ?effective_limits.diff(&layered), "requested limits applied");
+            effective_limits = layered;
         }
 
         // Determine command
-        let (mut run_cmd, _source_name) = if let Some(ref compile_config) = language.compile {
-            let binary = &compile_config.output_name;
-            (
-                Language::expand_command(
-                    &language.run.command,
-                    &compile_config.source_name,
-                    binary,
-                ),
-                compile_config.source_name.clone(),
-            )
-        } else {
-            let source_name = language.source_name();
-            (
-                Language::expand_command(&language.run.command, &source_name, &source_name),
-                source_name,
-            )
-        };
-
-        // Resolve command path (isolate uses execve, not execvp)
-        resolve_command(&mut run_cmd).map_err(InteractiveError::Isolate)?;
+        let run_cmd = language
+            .resolved_run_command()
+            .map_err(InteractiveError::Isolate)?;
 
         debug!(?run_cmd, "starting interactive session");
 
         // Validate mount source paths exist before running
-        validate_mounts(&language.run.mounts).map_err(InteractiveError::Isolate)?;
+        validate_mounts(&config.resolve_mounts(&language.run.mounts))
+            .map_err(InteractiveError::Isolate)?;
+        validate_working_dir(&language.run.working_dir).map_err(InteractiveError::Isolate)?;
+        validate_seccomp_profile(language.run.seccomp_profile.as_deref())
+            .map_err(InteractiveError::Isolate)?;
 
         // Build command
-        let mut command = IsolateCommand::new(config.isolate_binary(), sandbox.id())
+        let mut command = IsolateCommand::new(sandbox.isolate_path(), sandbox.id())
             .action(IsolateAction::Run)
             .cgroup(config.cgroup)
+            .internal_file_prefix(config.internal_file_prefix.clone())
             .limits(effective_limits)
-            .working_dir("/box")
-            .env("PATH", &language.run.path)
-            .mounts(config.sandbox_mounts.iter().cloned())
+            .working_dir(&language.run.working_dir)
+            .seccomp_profile(language.run.seccomp_profile.clone())
+            .share_net(language.run.share_net)
+            .env("PATH", language.run.sandbox_path())
+            .mount_source_root(config.mount_source_root.clone())
+            .mounts(config.run_mounts(language))
             .mounts(language.run.mounts.iter().cloned())
             .command(run_cmd);
 
-        for (key, value) in &language.run.env {
+        let env = Language::expand_env(
+            &language.run.env,
+            &language.source_name(),
+            &language.binary_name(),
+            &config.placeholders,
+        );
+        for (key, value) in env {
             command = command.env(key, value);
         }
+        for key in &language.run.inherit_env {
+            command = command.env_inherit(key);
+        }
 
         // Spawn process
         let mut process = IsolateProcess::spawn(sandbox, command)
@@ -113,54 +185,231 @@ impl InteractiveSession {
             .map_err(InteractiveError::Isolate)?;
 
         // Take ownership of stdout/stderr and wrap in buffered readers
-        let stdout_reader = process.take_stdout().map(BufReader::new);
-        let stderr_reader = process.take_stderr().map(BufReader::new);
+        let stdout = require_pipe(process.take_stdout(), "stdout")?;
+        let stderr = require_pipe(process.take_stderr(), "stderr")?;
 
         Ok(Self {
             process,
-            stdout_reader,
-            stderr_reader,
+            stdout_reader: Some(BufReader::new(stdout)),
+            stderr_reader: Some(BufReader::new(stderr)),
+            memory_report: config.memory_report,
+            deadline: None,
+            deadline_exceeded: false,
+            started_at: Instant::now(),
+            cgroup_dir: config
+                .cgroup
+                .then(|| config.cg_root.join(format!("box-{}", sandbox.id()))),
+            line_ending: LineEnding::default(),
+            exchange_timeout: None,
+            shutdown: None,
             terminated: false,
         })
     }
 
+    /// Enforce an overall wall-clock deadline on reads and writes
+    ///
+    /// The deadline starts now and spans every subsequent read and write.
+    /// Once it is exceeded the process is killed and every further read or
+    /// write returns [`InteractiveError::Deadline`].
+    #[must_use]
+    pub fn with_deadline(mut self, total: Duration) -> Self {
+        self.deadline = Some(Instant::now() + total);
+        self
+    }
+
+    /// Register this session so [`SessionRegistry::terminate_all`] can stop it
+    #[must_use]
+    pub fn with_registry(mut self, registry: &SessionRegistry) -> Self {
+        self.shutdown = Some(registry.subscribe());
+        self
+    }
+
+    /// Run an I/O operation bounded by the session deadline and shutdown requests
+    ///
+    /// A request to close stdin is applied to `process` without interrupting
+    /// `op`; operations that borrow the process themselves pass `None` and
+    /// are interrupted instead.
+    async fn guarded<T>(
+        deadline: Option<Instant>,
+        shutdown: Option<&mut watch::Receiver<ShutdownStage>>,
+        mut process: Option<&mut IsolateProcess>,
+        op: impl Future<Output = T>,
+    ) -> Result<T, Interrupted> {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(Interrupted::Deadline);
+        }
+
+        let expired = async {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(op, expired);
+
+        let mut shutdown = shutdown;
+        loop {
+            tokio::select! {
+                output = &mut op => return Ok(output),
+                () = &mut expired => return Err(Interrupted::Deadline),
+                stage = next_stage(shutdown.as_deref_mut()) => match stage {
+                    ShutdownStage::Kill => return Err(Interrupted::Shutdown),
+                    ShutdownStage::CloseStdin => {
+                        let Some(process) = process.as_deref_mut() else {
+                            return Err(Interrupted::StdinClosed);
+                        };
+                        process.close_stdin();
+                        debug!("closed stdin on shutdown request");
+                    }
+                    ShutdownStage::Running => {}
+                },
+            }
+        }
+    }
+
+    /// Kill the process after a guarded operation was interrupted
+    async fn interrupted(&mut self, reason: Interrupted) -> InteractiveError {
+        match reason {
+            Interrupted::Deadline => self.deadline_exceeded().await,
+            Interrupted::StdinClosed => {
+                self.close_stdin();
+                InteractiveError::Isolate(IsolateError::StdinClosed)
+            }
+            Interrupted::Shutdown => {
+                debug!("interactive session terminated by registry");
+                if let Err(e) = self.kill().await {
+                    warn!(?e, "failed to kill process on shutdown");
+                }
+                InteractiveError::Terminated
+            }
+        }
+    }
+
+    /// Kill the process after the deadline passed and report it
+    async fn deadline_exceeded(&mut self) -> InteractiveError {
+        debug!("interactive session deadline exceeded");
+        self.deadline_exceeded = true;
+        if let Err(e) = self.kill().await {
+            warn!(?e, "failed to kill process after deadline");
+        }
+        InteractiveError::Deadline
+    }
+
     /// Write data to the process stdin
     pub async fn write(&mut self, data: &[u8]) -> Result<(), InteractiveError> {
+        if self.deadline_exceeded {
+            return Err(InteractiveError::Deadline);
+        }
         if self.terminated {
             return Err(InteractiveError::Terminated);
         }
 
-        self.process
-            .write(data)
-            .await
-            .map_err(InteractiveError::Isolate)?;
+        let result = Self::guarded(
+            self.deadline,
+            self.shutdown.as_mut(),
+            None,
+            self.process.write(data),
+        )
+        .await;
+        match result {
+            Ok(result) => result.map_err(write_error)?,
+            Err(reason) => return Err(self.interrupted(reason).await),
+        }
 
         debug!(len = data.len(), "wrote to stdin");
         Ok(())
     }
 
-    /// Write a line to the process stdin (adds newline)
+    /// Write bytes to the process stdin exactly as given
+    ///
+    /// Same as [`write`](Self::write), which never adds a line ending or
+    /// otherwise transforms data; NUL and other binary bytes pass through.
+    /// Use this name where that guarantee matters to the reader.
+    pub async fn write_raw(&mut self, data: &[u8]) -> Result<(), InteractiveError> {
+        self.write(data).await
+    }
+
+    /// Set the terminator [`write_line`](Self::write_line) appends
+    ///
+    /// Defaults to [`LineEnding::Lf`]. Event stream handles created from
+    /// this session use the ending set at the time they are created.
+    pub fn set_line_ending(&mut self, ending: LineEnding) {
+        self.line_ending = ending;
+    }
+
+    /// Write a line to the process stdin (adds the configured line ending)
     pub async fn write_line(&mut self, line: &str) -> Result<(), InteractiveError> {
-        let mut data = line.as_bytes().to_vec();
-        data.push(b'\n');
+        let data = self.line_ending.terminate(line);
         self.write(&data).await
     }
 
+    /// Bound how long [`exchange`](Self::exchange) waits for a response
+    ///
+    /// `None` (the default) waits indefinitely, subject to any session deadline.
+    pub fn set_exchange_timeout(&mut self, timeout: Option<Duration>) {
+        self.exchange_timeout = timeout;
+    }
+
+    /// Write `input` as a line and read exactly one response line
+    ///
+    /// Returns [`InteractiveError::Timeout`] if no full line arrives within
+    /// the exchange timeout; the process keeps running, but any partial
+    /// line read so far is lost. Returns [`InteractiveError::Terminated`] if
+    /// stdout closes before a response.
+    pub async fn exchange(&mut self, input: &str) -> Result<String, InteractiveError> {
+        self.write_line(input).await?;
+        let line = match self.exchange_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.read_line())
+                .await
+                .map_err(|_| InteractiveError::Timeout)??,
+            None => self.read_line().await?,
+        };
+        line.ok_or(InteractiveError::Terminated)
+    }
+
     /// Close stdin to signal EOF
     pub fn close_stdin(&mut self) {
         self.process.close_stdin();
         debug!("closed stdin");
     }
 
+    /// Take the process stdin, to write it from another task
+    ///
+    /// Writes through the returned pipe bypass the session deadline.
+    pub(crate) fn take_stdin(&mut self) -> Option<ChildStdin> {
+        self.process.take_stdin()
+    }
+
+    /// Take the stderr reader, to drain it from another task
+    pub(crate) fn take_stderr(&mut self) -> Option<BufReader<ChildStderr>> {
+        self.stderr_reader.take()
+    }
+
     /// Read available data from stdout
     pub async fn read_stdout(&mut self, buf: &mut [u8]) -> Result<usize, InteractiveError> {
+        if self.deadline_exceeded {
+            return Err(InteractiveError::Deadline);
+        }
         if self.terminated {
             return Ok(0);
         }
 
         if let Some(ref mut reader) = self.stdout_reader {
-            let n = reader.read(buf).await?;
-            Ok(n)
+            let result = Self::guarded(
+                self.deadline,
+                self.shutdown.as_mut(),
+                Some(&mut self.process),
+                reader.read(buf),
+            )
+            .await;
+            match result {
+                Ok(result) => Ok(result?),
+                Err(Interrupted::Shutdown) => {
+                    self.interrupted(Interrupted::Shutdown).await;
+                    Ok(0)
+                }
+                Err(reason) => Err(self.interrupted(reason).await),
+            }
         } else {
             Ok(0)
         }
@@ -168,13 +417,29 @@ impl InteractiveSession {
 
     /// Read available data from stderr
     pub async fn read_stderr(&mut self, buf: &mut [u8]) -> Result<usize, InteractiveError> {
+        if self.deadline_exceeded {
+            return Err(InteractiveError::Deadline);
+        }
         if self.terminated {
             return Ok(0);
         }
 
         if let Some(ref mut reader) = self.stderr_reader {
-            let n = reader.read(buf).await?;
-            Ok(n)
+            let result = Self::guarded(
+                self.deadline,
+                self.shutdown.as_mut(),
+                Some(&mut self.process),
+                reader.read(buf),
+            )
+            .await;
+            match result {
+                Ok(result) => Ok(result?),
+                Err(Interrupted::Shutdown) => {
+                    self.interrupted(Interrupted::Shutdown).await;
+                    Ok(0)
+                }
+                Err(reason) => Err(self.interrupted(reason).await),
+            }
         } else {
             Ok(0)
         }
@@ -185,13 +450,31 @@ impl InteractiveSession {
     /// The internal BufReader is preserved between calls, so buffered data
     /// is not lost.
     pub async fn read_line(&mut self) -> Result<Option<String>, InteractiveError> {
+        if self.deadline_exceeded {
+            return Err(InteractiveError::Deadline);
+        }
         if self.terminated {
             return Ok(None);
         }
 
         if let Some(ref mut reader) = self.stdout_reader {
             let mut line = String::new();
-            match reader.read_line(&mut line).await {
+            let result = Self::guarded(
+                self.deadline,
+                self.shutdown.as_mut(),
+                Some(&mut self.process),
+                reader.read_line(&mut line),
+            )
+            .await;
+            let result = match result {
+                Ok(result) => result,
+                Err(Interrupted::Shutdown) => {
+                    self.interrupted(Interrupted::Shutdown).await;
+                    return Ok(None);
+                }
+                Err(reason) => return Err(self.interrupted(reason).await),
+            };
+            match result {
                 Ok(0) => Ok(None),
                 Ok(_) => {
                     // Remove trailing newline
@@ -236,7 +519,30 @@ impl InteractiveSession {
         }
 
         self.terminated = true;
-        self.process.wait().await.map_err(InteractiveError::Isolate)
+        self.process.close_stdin();
+        let exited = loop {
+            let exited =
+                Self::guarded(None, self.shutdown.as_mut(), None, self.process.wait_exit()).await;
+            // Stdin is already closed
+            if !matches!(exited, Err(Interrupted::StdinClosed)) {
+                break exited;
+            }
+        };
+        if let Err(Interrupted::Shutdown) = exited {
+            debug!("interactive session terminated by registry");
+            self.process
+                .kill()
+                .await
+                .map_err(InteractiveError::Isolate)?;
+        }
+
+        let mut result = self
+            .process
+            .wait()
+            .await
+            .map_err(InteractiveError::Isolate)?;
+        result.apply_memory_report(self.memory_report);
+        Ok(result)
     }
 
     /// Kill the process
@@ -251,6 +557,98 @@ impl InteractiveSession {
         Ok(())
     }
 
+    /// Sample the program's resource usage without waiting for it to exit
+    ///
+    /// CPU time and memory are read from isolate's cgroup and are `None`
+    /// when cgroups are disabled or the values cannot be read.
+    pub async fn peek_usage(&self) -> PartialUsage {
+        let (cpu_time, memory) = match self.cgroup_dir {
+            Some(ref dir) => (
+                read_cgroup_file(dir, "cpu.stat")
+                    .await
+                    .and_then(|stat| parse_cpu_usage(&stat)),
+                read_cgroup_file(dir, "memory.current")
+                    .await
+                    .and_then(|current| parse_memory_current(&current)),
+            ),
+            None => (None, None),
+        };
+        PartialUsage {
+            wall_time: self.started_at.elapsed().as_secs_f64(),
+            cpu_time,
+            memory,
+        }
+    }
+
+    /// Get a best-effort result for a process that was killed
+    ///
+    /// Intended for after [`kill`](Self::kill), e.g. when a wait timed out.
+    /// Uses isolate's interactive meta file if one was written; killing
+    /// isolate usually prevents that, so otherwise the result is built from
+    /// [`peek_usage`](Self::peek_usage), with `wall_time` measured from
+    /// session start and `killed` set. Either way the values are
+    /// approximate. Returns `None` while the process is still running.
+    pub async fn force_result(&self) -> Option<ExecutionResult> {
+        if !self.terminated {
+            return None;
+        }
+
+        let mut result = match self.process.current_result().await {
+            Some(result) => result,
+            None => {
+                let usage = self.peek_usage().await;
+                ExecutionResult {
+                    status: ExecutionStatus::Signaled,
+                    time: usage.cpu_time.unwrap_or_default(),
+                    wall_time: usage.wall_time,
+                    cg_memory: usage.memory,
+                    signal: Some(9),
+                    killed: true,
+                    cgroup_used: self.cgroup_dir.is_some(),
+                    message: Some("killed before isolate reported a result".to_string()),
+                    ..Default::default()
+                }
+            }
+        };
+        result.apply_memory_report(self.memory_report);
+        Some(result)
+    }
+
+    /// Wait for the process to exit, reporting usage every `interval`
+    ///
+    /// Stdin is closed first, as with [`wait`](Self::wait). `on_tick` is
+    /// called with [`peek_usage`](Self::peek_usage) after each full interval
+    /// the process is still running, so short-lived programs may never
+    /// trigger it.
+    pub async fn wait_with_progress(
+        mut self,
+        interval: Duration,
+        mut on_tick: impl FnMut(PartialUsage),
+    ) -> Result<ExecutionResult, InteractiveError> {
+        if self.terminated {
+            return Err(InteractiveError::Terminated);
+        }
+
+        self.process.close_stdin();
+        let mut ticker = tokio::time::interval_at(Instant::now() + interval, interval);
+        loop {
+            tokio::select! {
+                exited = self.process.wait_exit() => {
+                    exited.map_err(InteractiveError::Isolate)?;
+                    break;
+                }
+                _ = ticker.tick() => on_tick(self.peek_usage().await),
+                stage = next_stage(self.shutdown.as_mut()) => {
+                    if stage == ShutdownStage::Kill {
+                        debug!("interactive session terminated by registry");
+                        self.process.kill().await.map_err(InteractiveError::Isolate)?;
+                    }
+                }
+            }
+        }
+        self.wait().await
+    }
+
     /// Wait for the process with a timeout
     pub async fn wait_timeout(
         self,
@@ -263,6 +661,152 @@ impl InteractiveSession {
     }
 }
 
+/// Why a guarded session operation stopped early
+enum Interrupted {
+    /// The session deadline passed
+    Deadline,
+    /// A [`SessionRegistry`] asked for stdin to be closed
+    StdinClosed,
+    /// A [`SessionRegistry`] asked for the session to be killed
+    Shutdown,
+}
+
+/// Progress of a [`SessionRegistry::terminate_all`] request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShutdownStage {
+    Running,
+    CloseStdin,
+    Kill,
+}
+
+/// Wait for the next shutdown stage, or forever if the session is unregistered
+async fn next_stage(shutdown: Option<&mut watch::Receiver<ShutdownStage>>) -> ShutdownStage {
+    if let Some(rx) = shutdown
+        && rx.changed().await.is_ok()
+    {
+        return *rx.borrow_and_update();
+    }
+    std::future::pending().await
+}
+
+/// Tracks interactive sessions so they can be stopped together
+///
+/// Sessions join with [`InteractiveSession::with_registry`], or automatically
+/// when started through a `Runner` configured with
+/// `Runner::with_session_registry`. Clones share the same set of sessions.
+///
+/// Termination is cooperative: a session acts on a request while one of its
+/// reads, writes or waits is pending, or at the start of the next one. A
+/// session driven by an [`InteractiveEventStream`] is always being read and
+/// so stops promptly; a session held idle is only stopped once it is used
+/// again. Dropped sessions leave the registry automatically.
+#[derive(Debug, Clone, Default)]
+pub struct SessionRegistry {
+    sessions: Arc<Mutex<Vec<watch::Sender<ShutdownStage>>>>,
+}
+
+impl SessionRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of registered sessions that have not been dropped
+    pub fn active_sessions(&self) -> usize {
+        let mut sessions = self.sessions.lock().expect("session registry poisoned");
+        sessions.retain(|tx| !tx.is_closed());
+        sessions.len()
+    }
+
+    /// Register a new session and return its shutdown receiver
+    fn subscribe(&self) -> watch::Receiver<ShutdownStage> {
+        let (tx, rx) = watch::channel(ShutdownStage::Running);
+        let mut sessions = self.sessions.lock().expect("session registry poisoned");
+        sessions.retain(|tx| !tx.is_closed());
+        sessions.push(tx);
+        rx
+    }
+
+    /// Stop every registered session
+    ///
+    /// Closes each session's stdin, waits up to `grace` for the sessions to
+    /// be dropped, then kills those still alive. Sessions registered while
+    /// this runs are not affected. Returns how many sessions were signalled.
+    pub async fn terminate_all(&self, grace: Duration) -> usize {
+        let sessions: Vec<_> = {
+            let mut sessions = self.sessions.lock().expect("session registry poisoned");
+            sessions.retain(|tx| !tx.is_closed());
+            sessions.drain(..).collect()
+        };
+        debug!(
+            count = sessions.len(),
+            ?grace,
+            "terminating interactive sessions"
+        );
+
+        for tx in &sessions {
+            tx.send_replace(ShutdownStage::CloseStdin);
+        }
+        let all_closed = async {
+            for tx in &sessions {
+                tx.closed().await;
+            }
+        };
+        let _ = tokio::time::timeout(grace, all_closed).await;
+
+        for tx in sessions.iter().filter(|tx| !tx.is_closed()) {
+            tx.send_replace(ShutdownStage::Kill);
+        }
+        sessions.len()
+    }
+}
+
+/// Read a file from a cgroup directory, returning `None` if it is unreadable
+async fn read_cgroup_file(dir: &Path, name: &str) -> Option<String> {
+    tokio::fs::read_to_string(dir.join(name)).await.ok()
+}
+
+/// Parse CPU time in seconds from a cgroup v2 `cpu.stat` file
+fn parse_cpu_usage(stat: &str) -> Option<f64> {
+    stat.lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))
+        .and_then(|usec| usec.trim().parse::<u64>().ok())
+        .map(|usec| usec as f64 / 1_000_000.0)
+}
+
+/// Unwrap a pipe handle taken from the isolate process
+///
+/// Handles are only missing if the process was not spawned with piped
+/// I/O, which would leave the session unable to talk to the program.
+fn require_pipe<T>(handle: Option<T>, name: &'static str) -> Result<T, InteractiveError> {
+    handle.ok_or(InteractiveError::PipeSetup(name))
+}
+
+/// Map a failed stdin write, reporting a closed pipe as [`InteractiveError::Terminated`]
+///
+/// The pipe breaks when the program (and with it isolate) exited before
+/// reading everything, which callers need to tell apart from real I/O errors.
+/// The session result is still available from [`InteractiveSession::wait`].
+fn write_error(err: IsolateError) -> InteractiveError {
+    match err {
+        IsolateError::Io(ref e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::WouldBlock
+            ) =>
+        {
+            debug!(error = %e, "stdin closed by exited process");
+            InteractiveError::Terminated
+        }
+        err => InteractiveError::Isolate(err),
+    }
+}
+
+/// Parse kilobytes from a cgroup v2 `memory.current` file
+fn parse_memory_current(current: &str) -> Option<u64> {
+    current.trim().parse::<u64>().ok().map(|bytes| bytes / 1024)
+}
+
 /// Stream events from an interactive session
 pub struct InteractiveEventStream {
     rx: mpsc::Receiver<InteractiveEvent>,
@@ -275,9 +819,23 @@ impl InteractiveEventStream {
     /// The event stream spawns a background task that reads from stdout and
     /// signals when the process terminates. Uses `Notify` for efficient
     /// termination detection instead of polling.
-    pub fn new(mut session: InteractiveSession) -> (Self, InteractiveSessionHandle) {
+    pub fn new(session: InteractiveSession) -> (Self, InteractiveSessionHandle) {
+        Self::spawn(session, false)
+    }
+
+    /// Create an event stream that acknowledges stdin writes
+    ///
+    /// Like [`new`](Self::new), but emits [`InteractiveEvent::StdinWritten`]
+    /// after each write is flushed to the process, so callers can sequence
+    /// interactions deterministically.
+    pub fn new_with_acks(session: InteractiveSession) -> (Self, InteractiveSessionHandle) {
+        Self::spawn(session, true)
+    }
+
+    fn spawn(mut session: InteractiveSession, acks: bool) -> (Self, InteractiveSessionHandle) {
+        let line_ending = session.line_ending;
         let (event_tx, event_rx) = mpsc::channel(100);
-        let (stdin_tx, mut stdin_rx) = mpsc::channel::<Vec<u8>>(100);
+        let (stdin_tx, mut stdin_rx) = mpsc::channel::<StdinChunk>(100);
 
         // Notify for signaling termination - more efficient than polling
         let termination_notify = Arc::new(Notify::new());
@@ -292,11 +850,17 @@ impl InteractiveEventStream {
                     biased;
 
                     // Handle stdin writes - prioritize writes
-                    Some(data) = stdin_rx.recv() => {
-                        if let Err(e) = session.write(&data).await {
+                    Some(chunk) = stdin_rx.recv() => {
+                        if let Err(e) = session.write(&chunk.data).await {
                             warn!(?e, "failed to write to stdin");
                             break;
                         }
+                        let len = chunk.data.len();
+                        // Release the in-flight budget before acknowledging
+                        drop(chunk);
+                        if acks {
+                            let _ = event_tx.send(InteractiveEvent::StdinWritten(len)).await;
+                        }
                     }
 
                     // Read stdout (only if not closed)
@@ -344,9 +908,7 @@ impl InteractiveEventStream {
             _handle: handle,
         };
 
-        let session_handle = InteractiveSessionHandle { stdin_tx };
-
-        (stream, session_handle)
+        (stream, InteractiveSessionHandle::new(stdin_tx, line_ending))
     }
 
     /// Receive the next event
@@ -355,25 +917,191 @@ impl InteractiveEventStream {
     }
 }
 
+/// A queued stdin write and the in-flight budget it holds
+struct StdinChunk {
+    data: Vec<u8>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
 /// Handle for writing to an interactive session
 #[derive(Clone)]
 pub struct InteractiveSessionHandle {
-    stdin_tx: mpsc::Sender<Vec<u8>>,
+    stdin_tx: mpsc::Sender<StdinChunk>,
+    line_ending: LineEnding,
+    in_flight: Option<(Arc<Semaphore>, u32)>,
 }
 
 impl InteractiveSessionHandle {
+    fn new(stdin_tx: mpsc::Sender<StdinChunk>, line_ending: LineEnding) -> Self {
+        Self {
+            stdin_tx,
+            line_ending,
+            in_flight: None,
+        }
+    }
+
+    /// Cap the bytes queued for stdin but not yet written to the process
+    ///
+    /// Once `bytes` are in flight, [`write`](Self::write) and
+    /// [`write_line`](Self::write_line) wait until earlier writes have been
+    /// flushed to the sandbox. A single write larger than the cap waits for
+    /// the whole budget and is then sent in one piece. Clones made after
+    /// this call share the same budget; earlier clones are not limited.
+    #[must_use]
+    pub fn with_max_in_flight(mut self, bytes: usize) -> Self {
+        let cap = bytes
+            .min(Semaphore::MAX_PERMITS)
+            .min(u32::MAX as usize)
+            .max(1);
+        self.in_flight = Some((Arc::new(Semaphore::new(cap)), cap as u32));
+        self
+    }
+
     /// Write data to stdin
+    ///
+    /// Waits for buffer space, and for in-flight budget if
+    /// [`with_max_in_flight`](Self::with_max_in_flight) was set.
     pub async fn write(&self, data: &[u8]) -> Result<(), InteractiveError> {
+        let permit = match &self.in_flight {
+            Some((semaphore, cap)) => {
+                let needed = u32::try_from(data.len()).unwrap_or(u32::MAX).min(*cap);
+                let permit = semaphore
+                    .clone()
+                    .acquire_many_owned(needed)
+                    .await
+                    .map_err(|_| InteractiveError::Terminated)?;
+                Some(permit)
+            }
+            None => None,
+        };
         self.stdin_tx
-            .send(data.to_vec())
+            .send(StdinChunk {
+                data: data.to_vec(),
+                _permit: permit,
+            })
             .await
             .map_err(|_| InteractiveError::Terminated)
     }
 
-    /// Write a line to stdin
+    /// Write a line to stdin (adds the session's line ending)
     pub async fn write_line(&self, line: &str) -> Result<(), InteractiveError> {
-        let mut data = line.as_bytes().to_vec();
-        data.push(b'\n');
-        self.write(&data).await
+        self.write(&self.line_ending.terminate(line)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_ending_terminate() {
+        assert_eq!(LineEnding::default().terminate("42"), b"42\n");
+        assert_eq!(LineEnding::CrLf.terminate("42"), b"42\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_handle_waits_past_in_flight_cap() {
+        let (tx, mut rx) = mpsc::channel(100);
+        let handle = InteractiveSessionHandle::new(tx, LineEnding::Lf).with_max_in_flight(4);
+
+        handle.write(b"abcd").await.unwrap();
+        let blocked = handle.write_line("x");
+        tokio::pin!(blocked);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut blocked)
+                .await
+                .is_err(),
+            "write past the cap should wait"
+        );
+
+        // Draining the first chunk releases its budget
+        let first = rx.recv().await.unwrap();
+        assert_eq!(first.data, b"abcd");
+        drop(first);
+        blocked.await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().data, b"x\n");
+
+        // Oversized writes take the whole budget instead of deadlocking
+        handle.write(&[0; 16]).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().data.len(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_registry_forgets_dropped_sessions() {
+        let registry = SessionRegistry::new();
+        let kept = registry.subscribe();
+        drop(registry.subscribe());
+        assert_eq!(registry.active_sessions(), 1);
+
+        drop(kept);
+        assert_eq!(registry.active_sessions(), 0);
+        assert_eq!(registry.terminate_all(Duration::ZERO).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_terminate_all_kills_after_grace() {
+        let registry = SessionRegistry::new();
+        let mut rx = registry.subscribe();
+
+        let terminate = tokio::spawn({
+            let registry = registry.clone();
+            async move { registry.terminate_all(Duration::from_millis(20)).await }
+        });
+        assert_eq!(next_stage(Some(&mut rx)).await, ShutdownStage::CloseStdin);
+        assert_eq!(next_stage(Some(&mut rx)).await, ShutdownStage::Kill);
+        assert_eq!(terminate.await.unwrap(), 1);
+        assert_eq!(registry.active_sessions(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_terminate_all_skips_kill_for_finished_sessions() {
+        let registry = SessionRegistry::new();
+        let mut rx = registry.subscribe();
+
+        let terminate = tokio::spawn({
+            let registry = registry.clone();
+            async move { registry.terminate_all(Duration::from_secs(60)).await }
+        });
+        assert_eq!(next_stage(Some(&mut rx)).await, ShutdownStage::CloseStdin);
+        drop(rx);
+        let signalled = tokio::time::timeout(Duration::from_secs(5), terminate)
+            .await
+            .expect("terminate_all should return once sessions are gone");
+        assert_eq!(signalled.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_require_pipe_reports_missing_handle() {
+        assert_eq!(require_pipe(Some(3), "stdout").unwrap(), 3);
+
+        let err = require_pipe::<ChildStdout>(None, "stdout").unwrap_err();
+        assert!(matches!(err, InteractiveError::PipeSetup("stdout")));
+        assert_eq!(err.to_string(), "isolate process has no stdout pipe");
+    }
+
+    #[test]
+    fn test_write_error_maps_broken_pipe() {
+        let broken = IsolateError::Io(std::io::ErrorKind::BrokenPipe.into());
+        assert!(matches!(write_error(broken), InteractiveError::Terminated));
+
+        let other = IsolateError::Io(std::io::ErrorKind::PermissionDenied.into());
+        assert!(matches!(write_error(other), InteractiveError::Isolate(_)));
+        assert!(matches!(
+            write_error(IsolateError::StdinClosed),
+            InteractiveError::Isolate(IsolateError::StdinClosed)
+        ));
+    }
+
+    #[test]
+    fn test_parse_cpu_usage() {
+        let stat = "usage_usec 1500000\nuser_usec 1000000\nsystem_usec 500000\n";
+        assert_eq!(parse_cpu_usage(stat), Some(1.5));
+        assert_eq!(parse_cpu_usage("user_usec 10\n"), None);
+    }
+
+    #[test]
+    fn test_parse_memory_current() {
+        assert_eq!(parse_memory_current("2097152\n"), Some(2048));
+        assert_eq!(parse_memory_current("max\n"), None);
     }
 }
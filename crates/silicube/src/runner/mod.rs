@@ -2,20 +2,47 @@
 //!
 //! Provides high-level APIs for compiling and executing code in isolate sandboxes.
 
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock};
+
 use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, warn};
 
-use crate::config::{Config, Language};
-use crate::isolate::{IsolateBox, IsolateError};
-pub use crate::runner::compile::{CompileResult, compile};
-pub use crate::runner::execute::{execute, execute_interpreted};
+use crate::config::{Config, ConfigError, Language};
+use crate::isolate::{BatchStdin, BoxPool, IsolateBox, IsolateError, validate_box_file_name};
+pub use crate::runner::checker::{
+    BatchSummary, CheckerArgs, CheckerResult, ComparePolicy, DiffInfo, Verdict, compare_output,
+    first_diff, run_checker,
+};
+pub use crate::runner::compile::{
+    CompileResult, compile, compile_streaming, compile_with_source_name,
+};
+pub use crate::runner::execute::{
+    OUTPUT_MOUNT_READ_LIMIT, execute, execute_interpreted, execute_locked_down, execute_streaming,
+    execute_with_output_mount, locked_down_limits,
+};
 pub use crate::runner::interactive::{
     InteractiveEvent, InteractiveEventStream, InteractiveSession, InteractiveSessionHandle,
+    LineEnding, PartialUsage, SessionRegistry,
 };
-use crate::types::{ExecutionResult, ResourceLimits};
+pub use crate::runner::stream::OutputStream;
+use crate::types::{ExecutionResult, ExecutionStatus, MountConfig, ResourceLimits};
 
+mod checker;
 mod compile;
 mod execute;
 mod interactive;
+mod stream;
+mod toolchain;
+
+/// First box ID used for one-shot boxes when a runner has no pool
+const ONE_SHOT_BOX_START: u32 = 900;
+
+/// Number of box IDs reserved for one-shot boxes
+const ONE_SHOT_BOX_COUNT: u32 = 100;
 
 /// Request for compiling and running code in one step
 #[derive(Debug)]
@@ -46,6 +73,15 @@ pub enum CompileError {
     #[error("language '{0}' does not support compilation")]
     NotCompiled(String),
 
+    #[error("source too large: {size} bytes exceeds the limit of {limit} bytes")]
+    SourceTooLarge { size: usize, limit: usize },
+
+    #[error("prepare step failed ({status:?}): {output}")]
+    PrepareFailed {
+        status: ExecutionStatus,
+        output: String,
+    },
+
     #[error("isolate error: {0}")]
     Isolate(#[from] IsolateError),
 }
@@ -56,10 +92,59 @@ pub enum ExecuteError {
     #[error("execution not started: {0}")]
     NotStarted(String),
 
+    #[error("binary '{name}' not found in sandbox - was compilation run?")]
+    BinaryMissing { name: String },
+
+    #[error("source too large: {size} bytes exceeds the limit of {limit} bytes")]
+    SourceTooLarge { size: usize, limit: usize },
+
+    #[error("output mount files exceed the limit of {limit} bytes")]
+    OutputTooLarge { limit: u64 },
+
     #[error("isolate error: {0}")]
     Isolate(#[from] IsolateError),
 }
 
+impl CompileError {
+    /// The compiler that could not be found on the host, if that caused this
+    ///
+    /// A missing compiler is a host or config problem, not a fault in the
+    /// submission.
+    pub fn missing_command(&self) -> Option<&str> {
+        match self {
+            CompileError::Isolate(IsolateError::CommandNotFound(command)) => Some(command),
+            _ => None,
+        }
+    }
+}
+
+impl ExecuteError {
+    /// The program that could not be found on the host, if that caused this
+    ///
+    /// A missing interpreter is a host or config problem, not a fault in
+    /// the submission.
+    pub fn missing_command(&self) -> Option<&str> {
+        match self {
+            ExecuteError::Isolate(IsolateError::CommandNotFound(command)) => Some(command),
+            _ => None,
+        }
+    }
+}
+
+/// Reasons a request is rejected by [`Runner::can_admit`]
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum AdmissionError {
+    #[error("requested {limit} of {requested} exceeds the maximum of {ceiling}")]
+    AboveCeiling {
+        limit: &'static str,
+        requested: f64,
+        ceiling: f64,
+    },
+
+    #[error("no boxes available in the pool")]
+    PoolExhausted,
+}
+
 /// Errors that occur during interactive sessions
 #[derive(Debug, Error)]
 pub enum InteractiveError {
@@ -69,8 +154,8 @@ pub enum InteractiveError {
     #[error("session already terminated")]
     Terminated,
 
-    #[error("failed to create FIFO: {0}")]
-    FifoCreation(#[source] std::io::Error),
+    #[error("isolate process has no {0} pipe")]
+    PipeSetup(&'static str),
 
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
@@ -80,6 +165,9 @@ pub enum InteractiveError {
 
     #[error("wait timed out")]
     Timeout,
+
+    #[error("session deadline exceeded")]
+    Deadline,
 }
 
 /// Errors that occur during compile-and-run operations
@@ -95,33 +183,163 @@ pub enum CompileAndRunError {
     /// Error during execution phase (compilation succeeded)
     #[error("execution error: {0}")]
     Execute(#[from] ExecuteError),
+
+    /// The requested language is not configured
+    #[error("configuration error: {0}")]
+    Config(#[from] ConfigError),
+
+    /// Acquiring or cleaning up the sandbox failed
+    #[error("sandbox error: {0}")]
+    Sandbox(#[from] IsolateError),
+}
+
+impl CompileAndRunError {
+    /// Get the phase the error occurred in
+    pub fn phase(&self) -> Phase {
+        match self {
+            CompileAndRunError::Compile(_) => Phase::Compile,
+            CompileAndRunError::Execute(_) => Phase::Execute,
+            CompileAndRunError::Config(_) | CompileAndRunError::Sandbox(_) => Phase::Setup,
+        }
+    }
+}
+
+/// Phase of a compile-and-run operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Resolving the language or acquiring/cleaning up the sandbox
+    Setup,
+
+    /// Compiling (or writing) the source
+    Compile,
+
+    /// Running the program
+    Execute,
 }
 
 /// High-level runner for code execution
+///
+/// Clones share the configuration, so [`set_config`](Runner::set_config) on
+/// any clone affects all of them.
 #[derive(Debug, Clone)]
 pub struct Runner {
-    config: Config,
+    /// Current configuration, replaced whole by `set_config`
+    config: Arc<RwLock<Arc<Config>>>,
+    /// Pool used by [`run_source`](Self::run_source), if any
+    pool: Option<Arc<BoxPool>>,
+    /// Bytes of stdout/stderr to log at debug level after a run (0 disables)
+    log_output_preview: usize,
+    /// Limits concurrent compiles (`max_concurrent_compiles`), shared by clones
+    compile_slots: Option<Arc<Semaphore>>,
+    /// Limits concurrent runs (`max_concurrent_runs`), shared by clones
+    run_slots: Option<Arc<Semaphore>>,
+    /// Registry interactive sessions join, if any
+    session_registry: Option<SessionRegistry>,
 }
 
 impl Runner {
     /// Create a new runner with the given configuration
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let slots = |limit: Option<usize>| limit.map(|n| Arc::new(Semaphore::new(n)));
+        Self {
+            compile_slots: slots(config.max_concurrent_compiles),
+            run_slots: slots(config.max_concurrent_runs),
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            pool: None,
+            log_output_preview: 0,
+            session_registry: None,
+        }
     }
 
     /// Create a new runner with default configuration
     pub fn with_defaults() -> Self {
-        Self {
-            config: Config::default(),
+        Self::new(Config::default())
+    }
+
+    /// Acquire boxes for [`run_source`](Self::run_source) from `pool`
+    ///
+    /// Without a pool, each call uses a one-shot box with an ID from 900-999.
+    #[must_use]
+    pub fn with_pool(mut self, pool: Arc<BoxPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Log the first `bytes` of each run's stdout and stderr at debug level
+    ///
+    /// Output is decoded as lossy UTF-8 and logged escaped, so binary output
+    /// is safe to preview. Zero (the default) disables the preview.
+    #[must_use]
+    pub fn with_log_output_preview(mut self, bytes: usize) -> Self {
+        self.log_output_preview = bytes;
+        self
+    }
+
+    /// Register sessions started by [`run_interactive`](Self::run_interactive)
+    ///
+    /// Keep a clone of the registry to stop them all with
+    /// [`SessionRegistry::terminate_all`].
+    #[must_use]
+    pub fn with_session_registry(mut self, registry: SessionRegistry) -> Self {
+        self.session_registry = Some(registry);
+        self
+    }
+
+    /// Get the current configuration
+    ///
+    /// Returns a snapshot: a later [`set_config`](Self::set_config) does not
+    /// change it.
+    pub fn config(&self) -> Arc<Config> {
+        Arc::clone(&self.config.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Replace the configuration at runtime
+    ///
+    /// Safe to call while other tasks use the runner: each call takes a
+    /// snapshot of the config when it starts, so in-flight compiles and runs
+    /// finish with the old config and later calls see the new one.
+    /// `max_concurrent_compiles` and `max_concurrent_runs` are fixed when
+    /// the runner is created and are not changed by this.
+    pub fn set_config(&self, config: Config) {
+        *self.config.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+    }
+
+    /// Check whether a request with `requested` limits can be admitted now
+    ///
+    /// Rejects limits above the configured `max_limits` and, when the runner
+    /// has a pool, requests while no box is free. Pool availability can
+    /// change right after this returns, so acquiring may still wait.
+    pub fn can_admit(&self, requested: &ResourceLimits) -> Result<(), AdmissionError> {
+        if let Some(ref ceiling) = self.config().max_limits
+            && let Some((limit, requested, ceiling)) = requested.first_above(ceiling)
+        {
+            return Err(AdmissionError::AboveCeiling {
+                limit,
+                requested,
+                ceiling,
+            });
         }
+        if let Some(ref pool) = self.pool
+            && pool.available() == 0
+        {
+            return Err(AdmissionError::PoolExhausted);
+        }
+        Ok(())
     }
 
-    /// Get the configuration
-    pub fn config(&self) -> &Config {
-        &self.config
+    /// Check whether runs use cgroup limits
+    ///
+    /// Reflects the config as given to the runner, including any fallback
+    /// that disabled cgroups before it was built. Results carry the same
+    /// value in [`ExecutionResult::cgroup_used`].
+    pub fn cgroup_active(&self) -> bool {
+        self.config().cgroup
     }
 
     /// Compile source code
+    ///
+    /// After a successful compile, runs the language's `run.prepare`
+    /// command, if any.
     pub async fn compile(
         &self,
         sandbox: &IsolateBox,
@@ -129,10 +347,74 @@ impl Runner {
         language: &Language,
         limits: Option<&ResourceLimits>,
     ) -> Result<CompileResult, CompileError> {
-        compile::compile(sandbox, &self.config, language, source, limits).await
+        self.compile_using(&self.config(), sandbox, source, language, limits)
+            .await
+    }
+
+    /// Compile source code written to `source_name` instead of the configured name
+    ///
+    /// See [`compile::compile_with_source_name`].
+    pub async fn compile_with_source_name(
+        &self,
+        sandbox: &IsolateBox,
+        source: &[u8],
+        source_name: &str,
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<CompileResult, CompileError> {
+        let _slot = acquire_slot(self.compile_slots.as_deref()).await;
+        compile::compile_with_source_name(
+            sandbox,
+            &self.config(),
+            language,
+            source,
+            source_name,
+            limits,
+        )
+        .await
+    }
+
+    /// Compile source code, streaming compiler output to `sink` as it is produced
+    ///
+    /// Output is forwarded with up to ~50ms latency; see
+    /// [`compile::compile_streaming`].
+    pub async fn compile_streaming<W: AsyncWrite + Unpin>(
+        &self,
+        sandbox: &IsolateBox,
+        source: &[u8],
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+        sink: &mut W,
+    ) -> Result<CompileResult, CompileError> {
+        let _slot = acquire_slot(self.compile_slots.as_deref()).await;
+        compile::compile_streaming(sandbox, &self.config(), language, source, limits, sink).await
+    }
+
+    /// Prepare a sandbox to run `source`
+    ///
+    /// Compiles the source for compiled languages, returning the compile
+    /// result, and writes it into the sandbox for interpreted languages,
+    /// returning `None`. Either way the language's `run.prepare` command then
+    /// runs once. After a successful prepare, [`run`](Self::run) can be called
+    /// regardless of the language kind.
+    pub async fn prepare(
+        &self,
+        sandbox: &IsolateBox,
+        source: &[u8],
+        language: &Language,
+    ) -> Result<Option<CompileResult>, CompileError> {
+        self.prepare_using(&self.config(), sandbox, source, language)
+            .await
     }
 
     /// Run a program with batch I/O
+    ///
+    /// # Errors
+    ///
+    /// When the configured `wrapper_timeout` fires, returns
+    /// [`IsolateError::WrapperTimeout`] wrapped in [`ExecuteError::Isolate`].
+    /// The box is then re-initialized, which wipes every file in it,
+    /// including a compiled binary; compile again before the next run.
     pub async fn run(
         &self,
         sandbox: &IsolateBox,
@@ -140,7 +422,169 @@ impl Runner {
         language: &Language,
         limits: Option<&ResourceLimits>,
     ) -> Result<ExecutionResult, ExecuteError> {
-        execute::execute(sandbox, &self.config, language, input, limits).await
+        self.run_using(&self.config(), sandbox, input, language, limits)
+            .await
+    }
+
+    /// Run untrusted code with the most restrictive safe settings
+    ///
+    /// Like [`run`](Self::run), but forces a single process, no extra
+    /// mounts, no inherited environment and limits no looser than
+    /// [`locked_down_limits`], overriding laxer language config. See
+    /// [`execute_locked_down`] for the full list of guarantees.
+    pub async fn run_locked_down(
+        &self,
+        sandbox: &IsolateBox,
+        input: Option<&[u8]>,
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<ExecutionResult, ExecuteError> {
+        let _slot = acquire_slot(self.run_slots.as_deref()).await;
+        let result =
+            execute::execute_locked_down(sandbox, &self.config(), language, input, limits).await?;
+        self.log_output(&result);
+        Ok(result)
+    }
+
+    /// Run a program with stdin streamed from `stdin`
+    ///
+    /// Like [`run`](Self::run), but copies the reader into the box instead
+    /// of taking the whole input in memory.
+    pub async fn run_streaming<R: AsyncRead + Unpin>(
+        &self,
+        sandbox: &IsolateBox,
+        stdin: R,
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<ExecutionResult, ExecuteError> {
+        let _slot = acquire_slot(self.run_slots.as_deref()).await;
+        let result =
+            execute::execute_streaming(sandbox, &self.config(), language, stdin, limits).await?;
+        self.log_output(&result);
+        Ok(result)
+    }
+
+    /// Run a program and judge its stdout against `expected`
+    ///
+    /// The verdict only describes the output, compared with
+    /// [`compare_output`]; check the [`ExecutionResult`] for time limits,
+    /// crashes, and other failures before trusting an accepted verdict.
+    pub async fn run_and_judge(
+        &self,
+        sandbox: &IsolateBox,
+        input: Option<&[u8]>,
+        expected: &[u8],
+        policy: ComparePolicy,
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<(ExecutionResult, Verdict), ExecuteError> {
+        let config = self.config();
+        let result = self
+            .run_using(&config, sandbox, input, language, limits)
+            .await?;
+        let verdict = compare_output(
+            result.stdout.as_deref().unwrap_or_default(),
+            expected,
+            policy,
+        );
+        debug!(?verdict, ?policy, "judged output");
+        Ok((result, verdict))
+    }
+
+    /// Summarize judged results from a batch of test cases
+    ///
+    /// Pure aggregation; see [`BatchSummary`] for how cases are counted.
+    pub fn summarize(results: &[(ExecutionResult, Verdict)]) -> BatchSummary {
+        BatchSummary::from_results(results)
+    }
+
+    /// Run a program with gzip-compressed input
+    ///
+    /// The input is decompressed before the run; otherwise like
+    /// [`run`](Self::run). Invalid gzip data is reported as an
+    /// [`IsolateError::Io`], and input larger than
+    /// [`MAX_GUNZIP_BYTES`](crate::isolate::MAX_GUNZIP_BYTES) once
+    /// decompressed as [`IsolateError::DecompressedTooLarge`].
+    #[cfg(feature = "gzip")]
+    pub async fn run_with_gzip_input(
+        &self,
+        sandbox: &IsolateBox,
+        gz_input: &[u8],
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<ExecutionResult, ExecuteError> {
+        let input = crate::isolate::gunzip(gz_input, crate::isolate::MAX_GUNZIP_BYTES).await?;
+        self.run(sandbox, Some(&input), language, limits).await
+    }
+
+    /// Run a program with deterministic environment settings
+    ///
+    /// Like [`run`](Self::run), but with the environment from
+    /// [`Language::deterministic`]. True determinism also needs
+    /// single-process limits; pass `max_processes = 1` in `limits`.
+    pub async fn run_deterministic(
+        &self,
+        sandbox: &IsolateBox,
+        input: Option<&[u8]>,
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<ExecutionResult, ExecuteError> {
+        self.run(sandbox, input, &language.deterministic(), limits)
+            .await
+    }
+
+    /// Run a program and archive isolate's raw meta file to `meta_out`
+    ///
+    /// The meta file is copied after the run finishes; the parsed result is
+    /// returned as with [`run`](Self::run).
+    pub async fn run_with_meta_path(
+        &self,
+        sandbox: &IsolateBox,
+        input: Option<&[u8]>,
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+        meta_out: &Path,
+    ) -> Result<ExecutionResult, ExecuteError> {
+        let config = self.config();
+        let result = self
+            .run_using(&config, sandbox, input, language, limits)
+            .await?;
+        let meta = config.internal_file("meta.txt");
+        tokio::fs::copy(sandbox.file_path(&meta)?, meta_out)
+            .await
+            .map_err(IsolateError::from)?;
+        Ok(result)
+    }
+
+    /// Run a program and archive its stderr to `stderr_out`
+    ///
+    /// Stderr is captured even if [`Config::capture_stderr`] is off, and is
+    /// both returned in the result and written to `stderr_out` (empty if
+    /// the program wrote nothing).
+    pub async fn run_archiving_stderr(
+        &self,
+        sandbox: &IsolateBox,
+        input: Option<&[u8]>,
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+        stderr_out: &Path,
+    ) -> Result<ExecutionResult, ExecuteError> {
+        let _slot = acquire_slot(self.run_slots.as_deref()).await;
+        let result = execute::execute_with_args(
+            sandbox,
+            &self.config(),
+            language,
+            &[],
+            BatchStdin::Data(input),
+            limits,
+            true,
+        )
+        .await?;
+        self.log_output(&result);
+        tokio::fs::write(stderr_out, result.stderr.as_deref().unwrap_or_default())
+            .await
+            .map_err(IsolateError::from)?;
+        Ok(result)
     }
 
     /// Run an interpreted program (writes source and executes)
@@ -152,17 +596,94 @@ impl Runner {
         language: &Language,
         limits: Option<&ResourceLimits>,
     ) -> Result<ExecutionResult, ExecuteError> {
-        execute::execute_interpreted(sandbox, &self.config, language, source, input, limits).await
+        let _slot = acquire_slot(self.run_slots.as_deref()).await;
+        let result =
+            execute::execute_interpreted(sandbox, &self.config(), language, source, input, limits)
+                .await?;
+        self.log_output(&result);
+        Ok(result)
+    }
+
+    /// Run a program and collect the files it writes into `output_mount`
+    ///
+    /// `output_mount` must be writable, and its host source directory must
+    /// be missing or empty. The returned map holds the regular files written
+    /// there, keyed by path relative to the mount.
+    pub async fn run_with_output_mount(
+        &self,
+        sandbox: &IsolateBox,
+        input: Option<&[u8]>,
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+        output_mount: &MountConfig,
+    ) -> Result<(ExecutionResult, HashMap<String, Vec<u8>>), ExecuteError> {
+        let _slot = acquire_slot(self.run_slots.as_deref()).await;
+        let (result, files) = execute::execute_with_output_mount(
+            sandbox,
+            &self.config(),
+            language,
+            input,
+            limits,
+            output_mount,
+        )
+        .await?;
+        self.log_output(&result);
+        Ok((result, files))
+    }
+
+    /// Run a checker program against a submission's output
+    ///
+    /// The checker must already be compiled (or its source written) in the
+    /// sandbox. It waits for a run slot like [`run`](Self::run).
+    pub async fn run_checker(
+        &self,
+        sandbox: &IsolateBox,
+        checker_language: &Language,
+        args: CheckerArgs<'_>,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<CheckerResult, ExecuteError> {
+        let _slot = acquire_slot(self.run_slots.as_deref()).await;
+        checker::run_checker(sandbox, &self.config(), checker_language, args, limits).await
     }
 
     /// Start an interactive session
+    ///
+    /// Sessions outlive this call, so they don't count toward
+    /// [`Config::max_concurrent_runs`].
     pub async fn run_interactive(
         &self,
         sandbox: &IsolateBox,
         language: &Language,
         limits: Option<&ResourceLimits>,
     ) -> Result<InteractiveSession, InteractiveError> {
-        InteractiveSession::start(sandbox, &self.config, language, limits).await
+        let session = InteractiveSession::start(sandbox, &self.config(), language, limits).await?;
+        Ok(match &self.session_registry {
+            Some(registry) => session.with_registry(registry),
+            None => session,
+        })
+    }
+
+    /// Run a program, streaming its stdout as it is produced
+    ///
+    /// `input` is fed to the program's stdin, which is then closed. The
+    /// returned [`OutputStream`] yields stdout chunks and ends when the
+    /// program exits, after which it holds the [`ExecutionResult`]. Stderr
+    /// is drained as the program runs and returned in the result.
+    ///
+    /// Like [`run_interactive`](Self::run_interactive), this does not count
+    /// toward [`Config::max_concurrent_runs`].
+    pub async fn run_stream(
+        &self,
+        sandbox: &IsolateBox,
+        language: &Language,
+        input: &[u8],
+        limits: Option<&ResourceLimits>,
+    ) -> Result<OutputStream, ExecuteError> {
+        let session = self
+            .run_interactive(sandbox, language, limits)
+            .await
+            .map_err(stream::execute_error)?;
+        Ok(OutputStream::spawn(session, input.to_vec()))
     }
 
     /// Compile and run in one step (for compiled languages)
@@ -179,9 +700,12 @@ impl Runner {
         &self,
         request: CompileAndRunRequest<'_>,
     ) -> Result<(CompileResult, Option<ExecutionResult>), CompileAndRunError> {
+        let config = self.config();
+
         // Compile first
         let compile_result = self
-            .compile(
+            .compile_using(
+                &config,
                 request.sandbox,
                 request.source,
                 request.language,
@@ -192,7 +716,8 @@ impl Runner {
         // Only run if compilation succeeded
         if compile_result.success {
             let run_result = self
-                .run(
+                .run_using(
+                    &config,
                     request.sandbox,
                     request.input,
                     request.language,
@@ -204,11 +729,728 @@ impl Runner {
             Ok((compile_result, None))
         }
     }
+
+    /// Compile in one sandbox and run the resulting binary in another
+    ///
+    /// Allows compiling in a box with toolchain access and running in a
+    /// locked-down one. The compile output is copied from `compile_box` to
+    /// `run_box` after a successful compilation. Compilation uses the
+    /// language's compile limits; `limits` applies to execution only.
+    ///
+    /// Returns the same tuple as [`compile_and_run`](Self::compile_and_run).
+    pub async fn compile_in_then_run_in(
+        &self,
+        compile_box: &IsolateBox,
+        run_box: &IsolateBox,
+        source: &[u8],
+        input: Option<&[u8]>,
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<(CompileResult, Option<ExecutionResult>), CompileAndRunError> {
+        let config = self.config();
+        let compile_result = self
+            .compile_using(&config, compile_box, source, language, None)
+            .await?;
+        if !compile_result.success {
+            return Ok((compile_result, None));
+        }
+
+        let binary = language.binary_name();
+        compile_box
+            .copy_file_to(&binary, run_box)
+            .await
+            .map_err(ExecuteError::from)?;
+
+        let run_result = self
+            .run_using(&config, run_box, input, language, limits)
+            .await?;
+        Ok((compile_result, Some(run_result)))
+    }
+
+    /// Compile (if needed) and run `source` without managing a box
+    ///
+    /// Resolves `language_id` against the runner's config, acquires a box
+    /// from the runner's pool (or a one-shot box), runs the program, and
+    /// cleans the box up. A one-shot box is created with
+    /// [`Config::isolate_binary`]; either way, every command for the box uses
+    /// the binary that created it. A failed compilation is reported as
+    /// [`CompileError::Failed`] or [`CompileError::Timeout`].
+    pub async fn run_source(
+        &self,
+        source: &[u8],
+        language_id: &str,
+        input: Option<&[u8]>,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<ExecutionResult, CompileAndRunError> {
+        let config = self.config();
+        let language = config.get_language(language_id)?;
+        let sandbox = match self.pool {
+            Some(ref pool) => pool.acquire().await?,
+            None => {
+                BoxPool::new(
+                    ONE_SHOT_BOX_START,
+                    ONE_SHOT_BOX_COUNT,
+                    config.isolate_binary(),
+                    config.cgroup,
+                )
+                .with_init_clean_retry(config.init_clean_retry)
+                .acquire()
+                .await?
+            }
+        };
+        self.run_source_on(&config, sandbox, source, language, input, limits)
+            .await
+    }
+
+    /// Run the same source under several languages concurrently
+    ///
+    /// For polyglot programs valid in more than one language. Each language
+    /// gets its own box from `pool` and is compiled (if needed) and run as
+    /// by [`run_source`](Self::run_source), so concurrency is bounded by the
+    /// pool size. Results are returned in the order of `language_ids`.
+    pub async fn run_across(
+        &self,
+        pool: &Arc<BoxPool>,
+        source: &[u8],
+        language_ids: &[&str],
+        input: Option<&[u8]>,
+        limits: Option<&ResourceLimits>,
+    ) -> Vec<(String, Result<ExecutionResult, CompileAndRunError>)> {
+        let source: Arc<[u8]> = source.into();
+        let input: Option<Arc<[u8]>> = input.map(Into::into);
+
+        let config = self.config();
+        let mut tasks = tokio::task::JoinSet::new();
+        for (index, id) in language_ids.iter().enumerate() {
+            let runner = self.clone();
+            let config = Arc::clone(&config);
+            let pool = Arc::clone(pool);
+            let source = Arc::clone(&source);
+            let input = input.clone();
+            let limits = limits.cloned();
+            let id = id.to_string();
+            tasks.spawn(async move {
+                let result = async {
+                    let language = config.get_language(&id)?;
+                    let sandbox = pool.acquire().await?;
+                    runner
+                        .run_source_on(
+                            &config,
+                            sandbox,
+                            &source,
+                            language,
+                            input.as_deref(),
+                            limits.as_ref(),
+                        )
+                        .await
+                }
+                .await;
+                (index, id, result)
+            });
+        }
+
+        let mut results = Vec::with_capacity(language_ids.len());
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(result) => results.push(result),
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            }
+        }
+        results.sort_by_key(|(index, _, _)| *index);
+        results
+            .into_iter()
+            .map(|(_, id, result)| (id, result))
+            .collect()
+    }
+
+    /// Compile (if needed) and run `source` in `sandbox`, then clean it up
+    async fn run_source_on(
+        &self,
+        config: &Config,
+        mut sandbox: IsolateBox,
+        source: &[u8],
+        language: &Language,
+        input: Option<&[u8]>,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<ExecutionResult, CompileAndRunError> {
+        let result = self
+            .run_source_in(config, &sandbox, source, language, input, limits)
+            .await;
+        match (sandbox.cleanup().await, result) {
+            (Ok(()), result) => result,
+            (Err(e), Ok(_)) => Err(e.into()),
+            (Err(e), Err(err)) => {
+                warn!(box_id = sandbox.id(), error = %e, "failed to clean up box");
+                Err(err)
+            }
+        }
+    }
+
+    /// Log a preview of a run's output if enabled
+    fn log_output(&self, result: &ExecutionResult) {
+        if self.log_output_preview == 0 {
+            return;
+        }
+        let limit = self.log_output_preview;
+        let stdout = result.stdout.as_deref().map(|s| output_preview(s, limit));
+        let stderr = result.stderr.as_deref().map(|s| output_preview(s, limit));
+        debug!(?stdout, ?stderr, "output preview");
+    }
+
+    /// Probe every language's version command for a readiness report
+    ///
+    /// Returns `(language_id, version)` pairs sorted by language ID, where
+    /// `version` is the first line printed by the language's
+    /// `version_command` or a description of why probing failed. Languages
+    /// without a version command are skipped. Each probe uses its own box
+    /// from `pool`, and one failure does not stop the others.
+    pub async fn toolchain_report(&self, pool: &BoxPool) -> Vec<(String, Result<String, String>)> {
+        let config = self.config();
+        let mut languages: Vec<_> = config
+            .languages
+            .iter()
+            .filter(|(_, language)| !language.version_command.is_empty())
+            .collect();
+        languages.sort_by_key(|(id, _)| id.as_str());
+
+        let mut report = Vec::with_capacity(languages.len());
+        for (id, language) in languages {
+            let version = match pool.acquire().await {
+                Ok(mut sandbox) => {
+                    let version = toolchain::probe_version(&sandbox, &config, language).await;
+                    if let Err(e) = sandbox.cleanup().await {
+                        warn!(box_id = sandbox.id(), error = %e, "failed to clean up box");
+                    }
+                    version
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            report.push((id.clone(), version));
+        }
+        report
+    }
+
+    /// Prepare and run `source` in an already acquired box
+    async fn run_source_in(
+        &self,
+        config: &Config,
+        sandbox: &IsolateBox,
+        source: &[u8],
+        language: &Language,
+        input: Option<&[u8]>,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<ExecutionResult, CompileAndRunError> {
+        if let Some(compile_result) = self
+            .prepare_using(config, sandbox, source, language)
+            .await?
+            && !compile_result.success
+        {
+            return Err(
+                if compile_result.execution.status == ExecutionStatus::TimeLimitExceeded {
+                    CompileError::Timeout
+                } else {
+                    CompileError::Failed {
+                        exit_code: compile_result.execution.exit_code.unwrap_or(-1),
+                        stderr: compile_result.output,
+                    }
+                }
+                .into(),
+            );
+        }
+
+        Ok(self
+            .run_using(config, sandbox, input, language, limits)
+            .await?)
+    }
+
+    /// [`compile`](Self::compile) against a config snapshot
+    async fn compile_using(
+        &self,
+        config: &Config,
+        sandbox: &IsolateBox,
+        source: &[u8],
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<CompileResult, CompileError> {
+        let _slot = acquire_slot(self.compile_slots.as_deref()).await;
+        let result = compile::compile(sandbox, config, language, source, limits).await?;
+        if result.success {
+            execute::run_prepare_step(sandbox, config, language).await?;
+        }
+        Ok(result)
+    }
+
+    /// [`prepare`](Self::prepare) against a config snapshot
+    async fn prepare_using(
+        &self,
+        config: &Config,
+        sandbox: &IsolateBox,
+        source: &[u8],
+        language: &Language,
+    ) -> Result<Option<CompileResult>, CompileError> {
+        if language.is_compiled() {
+            return self
+                .compile_using(config, sandbox, source, language, None)
+                .await
+                .map(Some);
+        }
+        if let Some(limit) = config.source_size_exceeded(source.len()) {
+            return Err(CompileError::SourceTooLarge {
+                size: source.len(),
+                limit,
+            });
+        }
+        let source_name = language.source_name();
+        check_source_name(config, language, &source_name)?;
+        sandbox.write_file(&source_name, source).await?;
+        execute::run_prepare_step(sandbox, config, language).await?;
+        Ok(None)
+    }
+
+    /// [`run`](Self::run) against a config snapshot
+    async fn run_using(
+        &self,
+        config: &Config,
+        sandbox: &IsolateBox,
+        input: Option<&[u8]>,
+        language: &Language,
+        limits: Option<&ResourceLimits>,
+    ) -> Result<ExecutionResult, ExecuteError> {
+        let _slot = acquire_slot(self.run_slots.as_deref()).await;
+        let result = execute::execute(sandbox, config, language, input, limits).await?;
+        self.log_output(&result);
+        Ok(result)
+    }
+}
+
+/// Lossy UTF-8 preview of at most `limit` bytes of `data`
+fn output_preview(data: &[u8], limit: usize) -> String {
+    String::from_utf8_lossy(&data[..data.len().min(limit)]).into_owned()
+}
+
+/// Wait for a concurrency slot, or return immediately if unlimited
+async fn acquire_slot(slots: Option<&Semaphore>) -> Option<SemaphorePermit<'_>> {
+    // `acquire` only fails on a closed semaphore, and runner semaphores are never closed
+    slots?.acquire().await.ok()
+}
+
+/// Check that a source file name is safe to write into a box
+///
+/// Rejects [`RESERVED_BOX_FILES`](crate::isolate::RESERVED_BOX_FILES) under
+/// the configured prefix and, for compiled languages, the compile output
+/// name, so a source cannot overwrite the binary it is compiled into.
+pub(crate) fn check_source_name(
+    config: &Config,
+    language: &Language,
+    name: &str,
+) -> Result<(), IsolateError> {
+    validate_box_file_name(name, &config.internal_file_prefix)?;
+    if let Some(ref compile) = language.compile
+        && name == compile.output_name
+    {
+        return Err(IsolateError::ReservedFileName(name.to_owned()));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use tracing_test::traced_test;
+
     use super::*;
+    use crate::types::LimitExceeded;
+
+    #[test]
+    fn test_missing_command_is_distinct() {
+        let mut command = vec!["silicube-bogus-compiler".to_owned(), "-v".to_owned()];
+        let err = crate::isolate::resolve_command(&mut command).unwrap_err();
+        assert!(matches!(
+            &err,
+            IsolateError::CommandNotFound(name) if name == "silicube-bogus-compiler"
+        ));
+
+        let compile_err = CompileError::from(err);
+        assert_eq!(
+            compile_err.missing_command(),
+            Some("silicube-bogus-compiler")
+        );
+        let other = CompileError::Isolate(IsolateError::CommandFailed("exit 1".to_owned()));
+        assert_eq!(other.missing_command(), None);
+
+        let execute_err = ExecuteError::Isolate(IsolateError::CommandNotFound("python3".into()));
+        assert_eq!(execute_err.missing_command(), Some("python3"));
+        let other = ExecuteError::NotStarted("source missing".to_owned());
+        assert_eq!(other.missing_command(), None);
+    }
+
+    #[test]
+    fn test_check_source_name_rejects_collisions() {
+        let config = Config::default();
+        let cpp = config.get_language("cpp17").unwrap();
+        let python = config.get_language("python3").unwrap();
+
+        assert!(check_source_name(&config, cpp, &cpp.source_name()).is_ok());
+        assert!(check_source_name(&config, python, &python.source_name()).is_ok());
+        for name in ["meta.txt", "./stdin.txt", "compile_stderr.txt"] {
+            assert!(
+                matches!(
+                    check_source_name(&config, python, name),
+                    Err(IsolateError::ReservedFileName(_))
+                ),
+                "{name}"
+            );
+        }
+        assert!(matches!(
+            check_source_name(&config, cpp, &cpp.binary_name()),
+            Err(IsolateError::ReservedFileName(_))
+        ));
+        // Interpreted languages run the source itself
+        assert!(check_source_name(&config, python, "solution").is_ok());
+
+        // With a prefix, the prefixed names are the reserved ones
+        let prefixed = Config {
+            internal_file_prefix: ".silicube_".to_string(),
+            ..Config::default()
+        };
+        assert!(matches!(
+            check_source_name(&prefixed, python, "./.silicube_meta.txt"),
+            Err(IsolateError::ReservedFileName(_))
+        ));
+        assert!(check_source_name(&prefixed, python, "meta.txt").is_ok());
+    }
+
+    #[test]
+    fn test_summarize_counts_each_category() {
+        let case = |status, time, memory| ExecutionResult {
+            status,
+            time,
+            memory,
+            ..ExecutionResult::default()
+        };
+        let mut oom = case(ExecutionStatus::Signaled, 0.2, 65536);
+        oom.cg_oom_killed = true;
+        let mut slow = case(ExecutionStatus::TimeLimitExceeded, 2.0, 2048);
+        slow.limit_exceeded = LimitExceeded::Time;
+        let mut flood = case(ExecutionStatus::Signaled, 0.1, 1024);
+        flood.limit_exceeded = LimitExceeded::Output;
+
+        let results = [
+            (case(ExecutionStatus::Ok, 0.1, 1024), Verdict::Accepted),
+            (case(ExecutionStatus::Ok, 0.3, 1024), Verdict::Accepted),
+            (case(ExecutionStatus::Ok, 0.1, 1024), Verdict::WrongAnswer),
+            (
+                case(ExecutionStatus::Ok, 0.1, 1024),
+                Verdict::PresentationError,
+            ),
+            (slow, Verdict::Accepted),
+            (oom, Verdict::WrongAnswer),
+            (
+                case(ExecutionStatus::RuntimeError, 0.1, 1024),
+                Verdict::WrongAnswer,
+            ),
+            (
+                case(ExecutionStatus::Signaled, 0.1, 1024),
+                Verdict::WrongAnswer,
+            ),
+            (flood, Verdict::WrongAnswer),
+            (case(ExecutionStatus::Ok, 0.1, 1024), Verdict::CheckerFailed),
+        ];
+        let summary = Runner::summarize(&results);
+        assert_eq!(
+            summary,
+            BatchSummary {
+                total: 10,
+                accepted: 2,
+                wrong: 2,
+                tle: 1,
+                mle: 1,
+                ole: 1,
+                re: 2,
+                max_time: 2.0,
+                max_memory: 65536,
+            }
+        );
+        assert!(!summary.all_accepted());
+
+        let empty = Runner::summarize(&[]);
+        assert_eq!(empty, BatchSummary::default());
+        assert!(empty.all_accepted());
+    }
+
+    #[test]
+    fn test_can_admit_rejects_memory_above_ceiling() {
+        let mut config = Config::empty();
+        config.max_limits = Some(ResourceLimits::new().with_memory_limit(524288));
+        let runner = Runner::new(config);
+
+        assert!(runner.can_admit(&ResourceLimits::default()).is_ok());
+        let err = runner
+            .can_admit(&ResourceLimits::default().with_memory_limit(1048576))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            AdmissionError::AboveCeiling {
+                limit: "memory_limit",
+                requested: 1048576.0,
+                ceiling: 524288.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_can_admit_rejects_exhausted_pool() {
+        let pool = Arc::new(BoxPool::new(9200, 0, "isolate", false));
+        let runner = Runner::new(Config::empty()).with_pool(pool);
+        assert_eq!(
+            runner.can_admit(&ResourceLimits::default()),
+            Err(AdmissionError::PoolExhausted)
+        );
+
+        let pool = Arc::new(BoxPool::new(9200, 1, "isolate", false));
+        let runner = Runner::new(Config::empty()).with_pool(pool);
+        assert!(runner.can_admit(&ResourceLimits::default()).is_ok());
+    }
+
+    /// Config with a compiled C language and a shell language, for the fake isolate
+    fn fake_isolate_config() -> Config {
+        let toml = r#"
+[languages.c]
+name = "C"
+extension = "c"
+
+[languages.c.compile]
+command = ["gcc", "{source}", "-o", "{output}"]
+source_name = "main.c"
+output_name = "main"
+
+[languages.c.run]
+command = ["./{binary}"]
+
+[languages.sh]
+name = "Shell"
+extension = "sh"
+
+[languages.sh.run]
+command = ["/bin/sh", "{source}"]
+"#;
+        let mut config = Config::parse_toml(toml).unwrap();
+        config.cgroup = false;
+        config
+    }
+
+    #[tokio::test]
+    async fn test_compile_slots_limit_concurrency() {
+        let mut config = fake_isolate_config();
+        config.max_concurrent_compiles = Some(1);
+        let c = config.languages["c"].clone();
+        let sh = config.languages["sh"].clone();
+        let runner = Runner::new(config);
+
+        let isolate = crate::isolate::fake_isolate("compile-slots", false);
+        let dir = isolate.parent().unwrap().to_path_buf();
+        std::fs::write(dir.join("delay"), "0.2").unwrap();
+        let mut sandbox = IsolateBox::init(9430, &isolate, false).await.unwrap();
+
+        let (first, second) = tokio::join!(
+            runner.compile(&sandbox, b"int main(){}", &c, None),
+            runner.compile(&sandbox, b"int main(){}", &c, None),
+        );
+        first.unwrap();
+        second.unwrap();
+        let spans = std::fs::read_to_string(dir.join("spans")).unwrap();
+        assert_eq!(spans, "start\nend\nstart\nend\n");
+
+        // Runs are not limited by the compile cap
+        std::fs::remove_file(dir.join("spans")).unwrap();
+        sandbox.write_file("main.sh", b"").await.unwrap();
+        let (compiled, ran) = tokio::join!(
+            runner.compile(&sandbox, b"int main(){}", &c, None),
+            runner.run(&sandbox, None, &sh, None),
+        );
+        compiled.unwrap();
+        ran.unwrap();
+        let spans = std::fs::read_to_string(dir.join("spans")).unwrap();
+        assert_eq!(spans, "start\nstart\nend\nend\n");
+
+        sandbox.cleanup().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cgroup_active_matches_config() {
+        let mut config = Config::empty();
+        assert!(!Runner::new(config.clone()).cgroup_active());
+
+        config.cgroup = true;
+        assert!(Runner::new(config).cgroup_active());
+    }
+
+    #[test]
+    fn test_source_too_large_message() {
+        let err = CompileError::SourceTooLarge {
+            size: 2048,
+            limit: 1024,
+        };
+        assert_eq!(
+            err.to_string(),
+            "source too large: 2048 bytes exceeds the limit of 1024 bytes"
+        );
+    }
+
+    #[test]
+    fn test_binary_missing_message() {
+        let err = ExecuteError::BinaryMissing {
+            name: "main".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "binary 'main' not found in sandbox - was compilation run?"
+        );
+    }
+
+    #[test]
+    fn test_compile_and_run_error_phase_compile() {
+        let err = CompileAndRunError::from(CompileError::Timeout);
+        assert_eq!(err.phase(), Phase::Compile);
+    }
+
+    #[test]
+    fn test_compile_and_run_error_phase_execute() {
+        let err = CompileAndRunError::from(ExecuteError::NotStarted("no binary".to_string()));
+        assert_eq!(err.phase(), Phase::Execute);
+    }
+
+    #[test]
+    fn test_compile_and_run_error_phase_setup() {
+        let err = CompileAndRunError::from(ConfigError::LanguageNotFound("cobol".to_string()));
+        assert_eq!(err.phase(), Phase::Setup);
+        let err = CompileAndRunError::from(IsolateError::BoxInUse(0));
+        assert_eq!(err.phase(), Phase::Setup);
+    }
+
+    #[test]
+    fn test_output_preview_truncates() {
+        assert_eq!(output_preview(b"hello world", 5), "hello");
+        assert_eq!(output_preview(b"hi", 5), "hi");
+        assert_eq!(output_preview(b"", 5), "");
+    }
+
+    #[test]
+    #[traced_test]
+    fn test_log_output_logs_truncated_preview() {
+        let result = ExecutionResult {
+            stdout: Some(b"hello world".to_vec()),
+            stderr: Some(b"warning: unused".to_vec()),
+            ..Default::default()
+        };
+
+        Runner::with_defaults().log_output(&result);
+        assert!(!logs_contain("output preview"));
+
+        Runner::with_defaults()
+            .with_log_output_preview(5)
+            .log_output(&result);
+        assert!(logs_contain(r#"stdout=Some("hello")"#));
+        assert!(logs_contain(r#"stderr=Some("warni")"#));
+        assert!(!logs_contain("hello world"));
+    }
+
+    #[test]
+    fn test_output_preview_binary() {
+        let preview = output_preview(&[0xff, 0xfe, b'a', b'b'], 3);
+        assert_eq!(preview, "\u{fffd}\u{fffd}a");
+    }
+
+    #[tokio::test]
+    async fn test_commands_use_the_box_isolate_binary() {
+        let toml = r#"
+[languages.sh]
+name = "Shell"
+extension = "sh"
+
+[languages.sh.run]
+command = ["/bin/sh", "{source}"]
+"#;
+        let mut config = Config::parse_toml(toml).unwrap();
+        config.cgroup = false;
+
+        // A pooled box runs with the pool's binary, not the configured one
+        let isolate = crate::isolate::fake_isolate("runner-pool", false);
+        let log = isolate.with_file_name("log");
+        config.isolate_path = Some("/nonexistent/isolate".into());
+        let pool = Arc::new(BoxPool::new(9400, 1, &isolate, false));
+        let runner = Runner::new(config.clone()).with_pool(pool);
+        let _ = runner.run_source(b"echo hi", "sh", None, None).await;
+        let actions = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(actions, "init\nrun\ncleanup\n");
+        std::fs::remove_dir_all(isolate.parent().unwrap()).unwrap();
+
+        // A one-shot box is created with, and then run by, the configured binary
+        let isolate = crate::isolate::fake_isolate("runner-one-shot", false);
+        let log = isolate.with_file_name("log");
+        config.isolate_path = Some(isolate.clone());
+        let runner = Runner::new(config);
+        let _ = runner.run_source(b"echo hi", "sh", None, None).await;
+        let actions = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(actions, "init\nrun\ncleanup\n");
+        std::fs::remove_dir_all(isolate.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_checker_waits_for_run_slot() {
+        let mut config = fake_isolate_config();
+        config.max_concurrent_runs = Some(1);
+        let language = config.languages["sh"].clone();
+        let runner = Runner::new(config);
+
+        let isolate = crate::isolate::fake_isolate("checker-run-slot", false);
+        let dir = isolate.parent().unwrap().to_path_buf();
+        std::fs::write(dir.join("delay"), "0.2").unwrap();
+        let mut sandbox = IsolateBox::init(9420, &isolate, false).await.unwrap();
+        sandbox.write_file("main.sh", b"").await.unwrap();
+        let args = CheckerArgs {
+            input: b"",
+            expected: b"",
+            output: b"",
+        };
+        let (run, checker) = tokio::join!(
+            runner.run(&sandbox, None, &language, None),
+            runner.run_checker(&sandbox, &language, args, None),
+        );
+        run.unwrap();
+        checker.unwrap();
+
+        // The checker ran only after the program released its slot
+        let spans = std::fs::read_to_string(dir.join("spans")).unwrap();
+        assert_eq!(spans, "start\nend\nstart\nend\n");
+
+        sandbox.cleanup().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compile_streaming_keeps_timings() {
+        let mut config = fake_isolate_config();
+        config.collect_timings = true;
+        let language = config.languages["c"].clone();
+        let runner = Runner::new(config);
+
+        let isolate = crate::isolate::fake_isolate("compile-streaming-timings", false);
+        let mut sandbox = IsolateBox::init(9410, &isolate, false).await.unwrap();
+        let compiled = runner
+            .compile(&sandbox, b"int main(){}", &language, None)
+            .await
+            .unwrap();
+        let streamed = runner
+            .compile_streaming(&sandbox, b"int main(){}", &language, None, &mut Vec::new())
+            .await
+            .unwrap();
+        assert!(compiled.execution.timings.is_some());
+        assert!(streamed.execution.timings.is_some());
+
+        sandbox.cleanup().await.unwrap();
+        std::fs::remove_dir_all(isolate.parent().unwrap()).unwrap();
+    }
 
     #[test]
     fn test_runner_creation() {
@@ -217,4 +1459,18 @@ mod tests {
         assert!(runner.config().languages.contains_key("cpp17"));
         assert!(runner.config().languages.contains_key("python3"));
     }
+
+    #[test]
+    fn test_set_config_replaces_config() {
+        let runner = Runner::with_defaults();
+        let clone = runner.clone();
+        let before = runner.config();
+
+        runner.set_config(Config::empty());
+
+        assert!(runner.config().languages.is_empty());
+        assert!(clone.config().languages.is_empty());
+        // Snapshots taken before the swap keep the old config
+        assert!(before.languages.contains_key("cpp17"));
+    }
 }
@@ -0,0 +1,182 @@
+//! Program output as an async stream
+//!
+//! Wraps an [`InteractiveSession`] so stdout can be consumed with the
+//! `futures` ecosystem, e.g. forwarded as server-sent events.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{ChildStderr, ChildStdin};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::isolate::IsolateError;
+use crate::runner::{ExecuteError, InteractiveError, InteractiveSession};
+use crate::types::ExecutionResult;
+
+/// Size of each read from the program's stdout
+const READ_CHUNK: usize = 4096;
+
+/// Most stderr bytes kept in the final result
+///
+/// Stderr is drained past this point so the program never blocks on it.
+const MAX_STDERR: usize = 1024 * 1024;
+
+/// Message from the session task to the stream
+enum Message {
+    Chunk(Bytes),
+    Exited(ExecutionResult),
+    Failed(ExecuteError),
+}
+
+/// Stdout of a running program, chunk by chunk
+///
+/// Created by [`Runner::run_stream`](crate::runner::Runner::run_stream).
+/// Yields stdout as it is produced and ends once the program has exited;
+/// the final result, including stderr, is then available from
+/// [`result`](Self::result). Dropping the stream kills the program.
+pub struct OutputStream {
+    rx: mpsc::Receiver<Message>,
+    result: Option<ExecutionResult>,
+    _handle: tokio::task::JoinHandle<()>,
+}
+
+impl OutputStream {
+    /// Feed `input` to `session` and stream its stdout
+    pub(crate) fn spawn(session: InteractiveSession, input: Vec<u8>) -> Self {
+        let (tx, rx) = mpsc::channel(100);
+        let handle = tokio::spawn(pump(session, input, tx));
+        Self {
+            rx,
+            result: None,
+            _handle: handle,
+        }
+    }
+
+    /// Result of the program, once the stream has ended
+    pub fn result(&self) -> Option<&ExecutionResult> {
+        self.result.as_ref()
+    }
+
+    /// Take the result of the program, once the stream has ended
+    pub fn into_result(self) -> Option<ExecutionResult> {
+        self.result
+    }
+}
+
+impl Stream for OutputStream {
+    type Item = Result<Bytes, ExecuteError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.rx.poll_recv(cx) {
+            Poll::Ready(Some(Message::Chunk(data))) => Poll::Ready(Some(Ok(data))),
+            Poll::Ready(Some(Message::Exited(result))) => {
+                self.result = Some(result);
+                self.rx.close();
+                Poll::Ready(None)
+            }
+            Poll::Ready(Some(Message::Failed(e))) => Poll::Ready(Some(Err(e))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Drive `session` until it exits, forwarding stdout to `tx`
+///
+/// Stdin is written and stderr drained by their own tasks, so neither pipe
+/// filling up can stall the stdout reads here.
+async fn pump(mut session: InteractiveSession, input: Vec<u8>, tx: mpsc::Sender<Message>) {
+    let writer = session
+        .take_stdin()
+        .map(|stdin| tokio::spawn(feed(stdin, input)));
+    let stderr = session
+        .take_stderr()
+        .map(|stderr| tokio::spawn(drain(stderr)));
+    let mut buf = vec![0u8; READ_CHUNK];
+
+    loop {
+        tokio::select! {
+            biased;
+
+            () = tx.closed() => {
+                debug!("output stream dropped, killing program");
+                if let Err(e) = session.kill().await {
+                    warn!(?e, "failed to kill streamed program");
+                }
+                if let Some(writer) = writer {
+                    writer.abort();
+                }
+                return;
+            }
+
+            read = session.read_stdout(&mut buf) => match read {
+                Ok(0) => break,
+                Ok(n) => {
+                    let chunk = Bytes::copy_from_slice(&buf[..n]);
+                    // A dropped receiver is handled by the branch above
+                    let _ = tx.send(Message::Chunk(chunk)).await;
+                }
+                Err(e) => {
+                    let _ = tx.send(Message::Failed(execute_error(e))).await;
+                    break;
+                }
+            },
+        }
+    }
+
+    let result = session.wait().await;
+    if let Some(writer) = writer {
+        writer.abort();
+    }
+    let message = match result {
+        Ok(mut result) => {
+            if let Some(stderr) = stderr {
+                result.stderr = stderr.await.ok();
+            }
+            Message::Exited(result)
+        }
+        Err(e) => Message::Failed(execute_error(e)),
+    };
+    let _ = tx.send(message).await;
+}
+
+/// Write `input` to the program's stdin, then close it
+async fn feed(mut stdin: ChildStdin, input: Vec<u8>) {
+    // The program may exit without reading all of its input
+    if let Err(e) = stdin.write_all(&input).await {
+        debug!(?e, "stopped writing streamed program input");
+    }
+}
+
+/// Read stderr to the end, keeping at most [`MAX_STDERR`] bytes
+async fn drain(mut stderr: BufReader<ChildStderr>) -> Vec<u8> {
+    let mut kept = Vec::new();
+    let mut buf = vec![0u8; READ_CHUNK];
+    loop {
+        match stderr.read(&mut buf).await {
+            Ok(0) => break,
+            Ok(n) => {
+                let room = MAX_STDERR - kept.len();
+                kept.extend_from_slice(&buf[..n.min(room)]);
+            }
+            Err(e) => {
+                debug!(?e, "stopped reading streamed program stderr");
+                break;
+            }
+        }
+    }
+    kept
+}
+
+/// Express an interactive session failure as an execution error
+pub(crate) fn execute_error(err: InteractiveError) -> ExecuteError {
+    match err {
+        InteractiveError::Isolate(e) => ExecuteError::Isolate(e),
+        InteractiveError::Io(e) => ExecuteError::Isolate(IsolateError::Io(e)),
+        e => ExecuteError::NotStarted(e.to_string()),
+    }
+}
@@ -0,0 +1,77 @@
+//! Toolchain version probing
+//!
+//! Runs each language's version command inside a sandbox to report which
+//! compilers and interpreters are usable.
+
+use tracing::{debug, instrument};
+
+use crate::config::language::DEFAULT_WORKING_DIR;
+use crate::config::{Config, Language};
+use crate::isolate::{
+    IsolateAction, IsolateBox, IsolateCommand, resolve_command, run_with_output, validate_mounts,
+};
+use crate::runner::compile::effective_compile_limits;
+
+/// Run a language's version command and return the first line it prints
+///
+/// Version commands run with the compile limits, since they usually invoke
+/// the compiler. Errors are returned as display strings so a report can
+/// carry them without a shared error type.
+#[instrument(skip(sandbox, config, language), fields(language = %language.name, label = sandbox.label()))]
+pub(crate) async fn probe_version(
+    sandbox: &IsolateBox,
+    config: &Config,
+    language: &Language,
+) -> Result<String, String> {
+    let mut version_cmd = language.version_command.clone();
+    resolve_command(&mut version_cmd).map_err(|e| e.to_string())?;
+
+    let compile_mounts = language
+        .compile
+        .iter()
+        .flat_map(|compile| compile.mounts.iter().cloned());
+    let mounts: Vec<_> = config
+        .run_mounts(language)
+        .into_iter()
+        .chain(compile_mounts)
+        .chain(language.run.mounts.iter().cloned())
+        .collect();
+    validate_mounts(&config.resolve_mounts(&mounts)).map_err(|e| e.to_string())?;
+
+    let limits = effective_compile_limits(
+        language.compile.as_ref().and_then(|c| c.limits.as_ref()),
+        None,
+    );
+    let command = IsolateCommand::new(sandbox.isolate_path(), sandbox.id())
+        .action(IsolateAction::Run)
+        .cgroup(config.cgroup)
+        .internal_file_prefix(config.internal_file_prefix.clone())
+        .limits(limits)
+        .working_dir(DEFAULT_WORKING_DIR)
+        .env("PATH", language.run.sandbox_path())
+        .mount_source_root(config.mount_source_root.clone())
+        .mounts(mounts)
+        .command(version_cmd);
+
+    let (result, output) = run_with_output(sandbox, command)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let first_line = output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string();
+    debug!(exit_code = ?result.exit_code, version = %first_line, "probed version");
+
+    if result.exit_code == Some(0) {
+        Ok(first_line)
+    } else if !first_line.is_empty() {
+        Err(first_line)
+    } else {
+        Err(result
+            .message
+            .unwrap_or_else(|| format!("version command failed with status {:?}", result.status)))
+    }
+}
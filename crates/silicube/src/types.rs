@@ -1,4 +1,7 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceLimits {
@@ -100,6 +103,129 @@ impl ResourceLimits {
             extra_time: overrides.extra_time.or(self.extra_time),
         }
     }
+
+    /// Check the limits for values isolate would reject or misapply
+    ///
+    /// Flags nonpositive time and memory limits, a negative extra time and a
+    /// wall time limit below the CPU time limit. Unset limits are not checked.
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |t: f64| t.is_finite() && t > 0.0;
+        if let Some(t) = self.time_limit.filter(|&t| !positive(t)) {
+            return Err(format!("time_limit must be positive, got {t}"));
+        }
+        if let Some(t) = self.wall_time_limit.filter(|&t| !positive(t)) {
+            return Err(format!("wall_time_limit must be positive, got {t}"));
+        }
+        if self.memory_limit == Some(0) {
+            return Err("memory_limit must be positive, got 0".to_string());
+        }
+        if let Some(t) = self.extra_time.filter(|&t| !t.is_finite() || t < 0.0) {
+            return Err(format!("extra_time must not be negative, got {t}"));
+        }
+        if let (Some(time), Some(wall)) = (self.time_limit, self.wall_time_limit)
+            && wall < time
+        {
+            return Err(format!(
+                "wall_time_limit ({wall}) is below time_limit ({time})"
+            ));
+        }
+        Ok(())
+    }
+
+    /// List the limits that differ between `self` and `other`
+    ///
+    /// Returns `(name, before, after)` for each changed field, with `self`
+    /// as before and unset values shown as `"unset"`.
+    pub fn diff(&self, other: &ResourceLimits) -> Vec<(&'static str, String, String)> {
+        fn show<T: std::fmt::Display>(value: Option<T>) -> String {
+            value.map_or_else(|| "unset".to_owned(), |v| v.to_string())
+        }
+        let mut changes = Vec::new();
+        let mut compare = |name, before: String, after: String| {
+            if before != after {
+                changes.push((name, before, after));
+            }
+        };
+        compare("time_limit", show(self.time_limit), show(other.time_limit));
+        compare(
+            "wall_time_limit",
+            show(self.wall_time_limit),
+            show(other.wall_time_limit),
+        );
+        compare(
+            "memory_limit",
+            show(self.memory_limit),
+            show(other.memory_limit),
+        );
+        compare(
+            "stack_limit",
+            show(self.stack_limit),
+            show(other.stack_limit),
+        );
+        compare(
+            "max_processes",
+            show(self.max_processes),
+            show(other.max_processes),
+        );
+        compare("max_output", show(self.max_output), show(other.max_output));
+        compare(
+            "max_open_files",
+            show(self.max_open_files),
+            show(other.max_open_files),
+        );
+        compare("extra_time", show(self.extra_time), show(other.extra_time));
+        changes
+    }
+
+    /// Find the first limit that is above the matching limit in `ceiling`
+    ///
+    /// Returns `(name, requested, ceiling)` with values converted to `f64`.
+    /// Limits unset on either side are not compared.
+    pub fn first_above(&self, ceiling: &ResourceLimits) -> Option<(&'static str, f64, f64)> {
+        let as_f64 = |v: Option<u64>| v.map(|v| v as f64);
+        let count = |v: Option<u32>| v.map(f64::from);
+        [
+            ("time_limit", self.time_limit, ceiling.time_limit),
+            (
+                "wall_time_limit",
+                self.wall_time_limit,
+                ceiling.wall_time_limit,
+            ),
+            (
+                "memory_limit",
+                as_f64(self.memory_limit),
+                as_f64(ceiling.memory_limit),
+            ),
+            (
+                "stack_limit",
+                as_f64(self.stack_limit),
+                as_f64(ceiling.stack_limit),
+            ),
+            (
+                "max_processes",
+                count(self.max_processes),
+                count(ceiling.max_processes),
+            ),
+            (
+                "max_output",
+                as_f64(self.max_output),
+                as_f64(ceiling.max_output),
+            ),
+            (
+                "max_open_files",
+                count(self.max_open_files),
+                count(ceiling.max_open_files),
+            ),
+            ("extra_time", self.extra_time, ceiling.extra_time),
+        ]
+        .into_iter()
+        .find_map(|(name, requested, ceiling)| match (requested, ceiling) {
+            (Some(requested), Some(ceiling)) if requested > ceiling => {
+                Some((name, requested, ceiling))
+            }
+            _ => None,
+        })
+    }
 }
 
 impl Default for ResourceLimits {
@@ -117,6 +243,15 @@ impl Default for ResourceLimits {
     }
 }
 
+/// Bytes of stderr included in [`ExecutionResult::assert_success`] failures
+const STDERR_PREVIEW_BYTES: usize = 200;
+
+/// `cg-mem` to `max-rss` ratio above which reporting cg-mem is logged as misleading
+const MEMORY_DISCREPANCY_LOG_RATIO: f64 = 4.0;
+
+/// CPU to wall time ratio below which a wall-time timeout counts as idle
+const IDLE_CPU_RATIO: f64 = 0.5;
+
 /// Result of an execution
 #[derive(Debug, Clone)]
 pub struct ExecutionResult {
@@ -148,7 +283,8 @@ pub struct ExecutionResult {
     pub cg_memory: Option<u64>,
 
     /// Peak resident set size in kilobytes (process-only).
-    /// None if isolate didn't report max-rss.
+    /// None if isolate didn't report max-rss, which some isolate versions
+    /// do in cgroup mode; [`MemoryReportMode::Preferred`] then reports `cg-mem`.
     ///
     /// Unlike [`cg_memory`](Self::cg_memory), this measures only the
     /// process's own resident memory and is not affected by leftover page
@@ -162,9 +298,41 @@ pub struct ExecutionResult {
     /// Signal number if the program was killed by a signal
     pub signal: Option<i32>,
 
+    /// Whether isolate killed the program (e.g. on a time or memory limit)
+    ///
+    /// Distinguishes an isolate kill from the program exiting on its own,
+    /// even when the status alone is ambiguous.
+    pub killed: bool,
+
+    /// Whether the cgroup OOM killer terminated the program (`cg-oom-killed`)
+    pub cg_oom_killed: bool,
+
+    /// Whether the run used cgroup limits (`--cg`)
+    ///
+    /// When true, [`memory`](Self::memory) normally comes from cg-mem;
+    /// otherwise only max-rss is available and memory was limited with
+    /// RLIMIT_AS.
+    pub cgroup_used: bool,
+
     /// Additional message from isolate
     pub message: Option<String>,
 
+    /// Bytes used under `/box` after the run
+    ///
+    /// Only measured for batch runs when `Config::report_disk_usage` is set.
+    pub disk_usage: Option<u64>,
+
+    /// Host-side time spent in each phase of the isolate command
+    ///
+    /// Kept by runner calls only when `Config::collect_timings` is set.
+    /// Boxed to keep results small when timings are off.
+    pub timings: Option<Box<TimingBreakdown>>,
+
+    /// Whether the output limit stopped the program mid-write
+    ///
+    /// `stdout` then holds the partial output written before the kill.
+    pub output_truncated: bool,
+
     /// Standard output (if captured)
     pub stdout: Option<Vec<u8>>,
 
@@ -174,9 +342,192 @@ pub struct ExecutionResult {
 
 impl ExecutionResult {
     /// Check if the execution was successful (exited with code 0)
+    ///
+    /// A reported signal makes the run unsuccessful even if isolate also
+    /// reported exit code 0, since the program did not finish on its own.
     #[must_use]
     pub fn is_success(&self) -> bool {
-        matches!(self.status, ExecutionStatus::Ok) && self.exit_code == Some(0)
+        matches!(self.status, ExecutionStatus::Ok)
+            && self.exit_code == Some(0)
+            && self.signal.is_none()
+    }
+
+    /// Check for success, describing the failure otherwise
+    ///
+    /// Intended for test assertions: `result.assert_success().unwrap()`
+    /// panics with the status, exit code, signal, message, and the start of
+    /// stderr instead of a bare `false`.
+    pub fn assert_success(&self) -> Result<(), String> {
+        if self.is_success() {
+            return Ok(());
+        }
+
+        let mut description = format!(
+            "execution failed: status={:?}, exit_code={:?}, signal={:?}",
+            self.status, self.exit_code, self.signal
+        );
+        if let Some(ref message) = self.message {
+            description.push_str(&format!(", message={message:?}"));
+        }
+        if let Some(ref stderr) = self.stderr {
+            let preview = &stderr[..stderr.len().min(STDERR_PREVIEW_BYTES)];
+            description.push_str(&format!(", stderr={:?}", String::from_utf8_lossy(preview)));
+        }
+        Err(description)
+    }
+
+    /// Check if the program wrote anything to stdout
+    ///
+    /// Combine with [`is_success`](Self::is_success) to tell "ran but
+    /// produced empty output" apart from "didn't run" (no stdout captured).
+    #[must_use]
+    pub fn produced_output(&self) -> bool {
+        self.stdout
+            .as_ref()
+            .is_some_and(|stdout| !stdout.is_empty())
+    }
+
+    /// Check if the program was terminated by a signal
+    ///
+    /// Isolate can report both `exitcode` and `exitsig`, so this checks the
+    /// signal independently of [`exit_code`](Self::exit_code).
+    #[must_use]
+    pub fn terminated_by_signal(&self) -> bool {
+        self.signal.is_some() || self.status == ExecutionStatus::Signaled
+    }
+
+    /// Check if the program was killed for running out of memory
+    ///
+    /// A cgroup OOM is reported as SIGKILL, the same signal used for time
+    /// limit kills, so check this before treating signal 9 as a timeout.
+    /// True when isolate set `cg-oom-killed`, or on SIGKILL with a message
+    /// mentioning memory.
+    #[must_use]
+    pub fn is_oom(&self) -> bool {
+        self.cg_oom_killed
+            || (self.signal == Some(9)
+                && self
+                    .message
+                    .as_deref()
+                    .is_some_and(|msg| msg.to_lowercase().contains("memory")))
+    }
+
+    /// Check whether a CPU or wall-clock time limit was exceeded
+    #[must_use]
+    pub fn is_time_limit(&self) -> bool {
+        self.status == ExecutionStatus::TimeLimitExceeded
+            || matches!(
+                self.limit_exceeded,
+                LimitExceeded::Time | LimitExceeded::WallTime
+            )
+    }
+
+    /// Check whether the wall-clock time limit was exceeded
+    #[must_use]
+    pub fn is_wall_time_limit(&self) -> bool {
+        self.limit_exceeded == LimitExceeded::WallTime
+    }
+
+    /// Heuristic: the wall-time limit was hit while the program mostly idled
+    ///
+    /// True for wall-time timeouts where CPU time is under half the wall
+    /// time, as with a program that sleeps or waits on input rather than
+    /// spinning. CPU-bound timeouts usually trip the CPU limit first.
+    #[must_use]
+    pub fn likely_idle_wall_timeout(&self) -> bool {
+        self.is_wall_time_limit() && self.time < self.wall_time * IDLE_CPU_RATIO
+    }
+
+    /// Check whether the memory limit was exceeded, including cgroup OOM kills
+    #[must_use]
+    pub fn is_memory_limit(&self) -> bool {
+        self.limit_exceeded == LimitExceeded::Memory || self.is_oom()
+    }
+
+    /// Check whether the output limit was exceeded
+    ///
+    /// See [`output_truncated`](Self::output_truncated) for what this means
+    /// for the captured stdout.
+    #[must_use]
+    pub fn is_output_limit(&self) -> bool {
+        self.limit_exceeded == LimitExceeded::Output
+    }
+
+    /// Iterate over the lines of captured stdout without copying
+    ///
+    /// Lines are split on `\n` with the terminator (and a preceding `\r`)
+    /// removed. A trailing newline does not produce an extra empty line.
+    pub fn stdout_line_iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.stdout
+            .as_deref()
+            .unwrap_or_default()
+            .split_inclusive(|&b| b == b'\n')
+            .map(|line| {
+                let line = line.strip_suffix(b"\n").unwrap_or(line);
+                line.strip_suffix(b"\r").unwrap_or(line)
+            })
+    }
+
+    /// Set the captured standard output
+    pub fn with_stdout(mut self, stdout: Vec<u8>) -> Self {
+        self.stdout = Some(stdout);
+        self
+    }
+
+    /// Set the captured standard error
+    pub fn with_stderr(mut self, stderr: Vec<u8>) -> Self {
+        self.stderr = Some(stderr);
+        self
+    }
+
+    /// Set the execution status
+    pub fn with_status(mut self, status: ExecutionStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Set the exit code
+    pub fn with_exit_code(mut self, code: i32) -> Self {
+        self.exit_code = Some(code);
+        self
+    }
+
+    /// Repopulate [`memory`](Self::memory) according to `mode`
+    ///
+    /// The detailed [`cg_memory`](Self::cg_memory) and
+    /// [`max_rss`](Self::max_rss) fields are left untouched.
+    pub fn apply_memory_report(&mut self, mode: MemoryReportMode) {
+        if mode != MemoryReportMode::MaxRss
+            && let Some(ratio) = self.memory_discrepancy()
+            && ratio >= MEMORY_DISCREPANCY_LOG_RATIO
+        {
+            debug!(
+                ratio,
+                cg_memory = self.cg_memory,
+                max_rss = self.max_rss,
+                "cg-mem far exceeds max-rss, likely page cache; consider memory_report = \"max_rss\""
+            );
+        }
+        self.memory = match mode {
+            MemoryReportMode::CgMem => self.cg_memory,
+            MemoryReportMode::MaxRss => self.max_rss,
+            MemoryReportMode::Preferred => self.cg_memory.or(self.max_rss),
+        }
+        .unwrap_or(0);
+    }
+
+    /// Ratio of cgroup memory to the process's peak RSS
+    ///
+    /// `None` unless both were measured and RSS is non-zero. Values well
+    /// above 1 mean `cg-mem` is dominated by page cache rather than the
+    /// program's own memory, so [`MemoryReportMode::MaxRss`] reports usage
+    /// more faithfully.
+    #[must_use]
+    pub fn memory_discrepancy(&self) -> Option<f64> {
+        match (self.cg_memory, self.max_rss) {
+            (Some(cg_mem), Some(rss)) if rss > 0 => Some(cg_mem as f64 / rss as f64),
+            _ => None,
+        }
     }
 
     /// If the process was killed and cgroup memory reached the configured limit,
@@ -209,13 +560,43 @@ impl Default for ExecutionResult {
             max_rss: None,
             exit_code: None,
             signal: None,
+            killed: false,
+            cg_oom_killed: false,
+            cgroup_used: false,
             message: None,
+            disk_usage: None,
+            timings: None,
+            output_truncated: false,
             stdout: None,
             stderr: None,
         }
     }
 }
 
+/// Host wall-clock time spent in each phase of running an isolate command
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimingBreakdown {
+    /// Starting the isolate process
+    pub spawn: Duration,
+
+    /// Waiting for isolate to exit, including sandbox setup and teardown
+    pub execute: Duration,
+
+    /// Waiting for and parsing the meta file
+    pub meta_parse: Duration,
+
+    /// Reading captured stdout and stderr back from the box
+    pub read_output: Duration,
+}
+
+impl TimingBreakdown {
+    /// Sum of all phases
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.spawn + self.execute + self.meta_parse + self.read_output
+    }
+}
+
 /// Status of an execution
 /// Corresponds to IOI Isolate two-letter status codes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -312,10 +693,44 @@ impl LimitExceeded {
     }
 }
 
+/// Which isolate measurement populates [`ExecutionResult::memory`]
+///
+/// In cgroup mode `cg-mem` includes page cache, which can wildly overcount
+/// for short programs; `max-rss` measures only the process itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryReportMode {
+    /// Always report `cg-mem` (0 if isolate didn't report it)
+    CgMem,
+
+    /// Always report `max-rss` (0 if isolate didn't report it)
+    MaxRss,
+
+    /// Report `cg-mem` when available, falling back to `max-rss`
+    ///
+    /// Use this rather than [`MaxRss`](Self::MaxRss) when isolate may omit
+    /// `max-rss`, as some versions do in cgroup mode.
+    #[default]
+    Preferred,
+}
+
+/// Hash algorithm for [`IsolateBox::file_hash`](crate::isolate::IsolateBox::file_hash)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgo {
+    /// SHA-256
+    #[default]
+    Sha256,
+
+    /// SHA-512
+    Sha512,
+}
+
 /// Configuration for a directory mount in Isolate
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MountConfig {
-    /// Source path on the host
+    /// Source path on the host (ignored for `tmp` mounts)
+    #[serde(default)]
     pub source: String,
 
     /// Target path in the sandbox
@@ -329,6 +744,56 @@ pub struct MountConfig {
     /// Maps to isolate's `:maybe` flag
     #[serde(default)]
     pub optional: bool,
+
+    /// Mount a fresh writable temporary directory instead of `source`
+    /// Maps to isolate's `:tmp` flag
+    #[serde(default)]
+    pub tmp: bool,
+
+    /// Allow access to device nodes under the mount (e.g. `/dev/dri` for GPUs)
+    /// Maps to isolate's `:dev` flag; requires a host `source` and cannot be
+    /// combined with `tmp`
+    #[serde(default)]
+    pub dev: bool,
+}
+
+impl MountConfig {
+    /// Resolve a relative `source` against `root`
+    ///
+    /// Absolute sources, `tmp` mounts, and mounts without a source are
+    /// returned unchanged.
+    pub fn resolve_source(&self, root: &std::path::Path) -> MountConfig {
+        let mut mount = self.clone();
+        if !self.tmp && !self.source.is_empty() && std::path::Path::new(&self.source).is_relative()
+        {
+            mount.source = root.join(&self.source).to_string_lossy().into_owned();
+        }
+        mount
+    }
+
+    /// Build the isolate `--dir` argument for this mount
+    ///
+    /// Returns `None` for optional mounts whose source doesn't exist, which
+    /// are skipped entirely rather than passed to isolate.
+    pub fn to_isolate_arg(&self) -> Option<String> {
+        if self.tmp {
+            return Some(format!("--dir={}:tmp", self.target));
+        }
+        if self.optional && !std::path::Path::new(&self.source).exists() {
+            return None;
+        }
+        let mut opts = String::new();
+        if self.writable {
+            opts.push_str(":rw");
+        }
+        if self.optional {
+            opts.push_str(":maybe");
+        }
+        if self.dev {
+            opts.push_str(":dev");
+        }
+        Some(format!("--dir={}={}{}", self.target, self.source, opts))
+    }
 }
 
 #[cfg(test)]
@@ -350,6 +815,79 @@ mod tests {
         assert!(limits.extra_time.is_some());
     }
 
+    #[test]
+    fn resource_limits_default_is_valid() {
+        assert_eq!(ResourceLimits::default().validate(), Ok(()));
+        let unset = ResourceLimits {
+            time_limit: None,
+            wall_time_limit: None,
+            memory_limit: None,
+            extra_time: None,
+            ..Default::default()
+        };
+        assert_eq!(unset.validate(), Ok(()));
+    }
+
+    #[test]
+    fn resource_limits_validate_rejects_zero_time() {
+        let err = ResourceLimits::new().with_time_limit(0.0).validate();
+        assert!(err.unwrap_err().contains("time_limit must be positive"));
+        let err = ResourceLimits::new().with_time_limit(f64::NAN).validate();
+        assert!(err.unwrap_err().contains("time_limit"));
+    }
+
+    #[test]
+    fn resource_limits_validate_rejects_zero_wall_time() {
+        let err = ResourceLimits::new().with_wall_time_limit(-1.0).validate();
+        assert!(
+            err.unwrap_err()
+                .contains("wall_time_limit must be positive")
+        );
+    }
+
+    #[test]
+    fn resource_limits_validate_rejects_zero_memory() {
+        let err = ResourceLimits::new().with_memory_limit(0).validate();
+        assert!(err.unwrap_err().contains("memory_limit"));
+    }
+
+    #[test]
+    fn resource_limits_validate_rejects_negative_extra_time() {
+        let limits = ResourceLimits {
+            extra_time: Some(-0.5),
+            ..Default::default()
+        };
+        assert!(limits.validate().unwrap_err().contains("extra_time"));
+    }
+
+    #[test]
+    fn resource_limits_validate_rejects_wall_below_time() {
+        let limits = ResourceLimits::new()
+            .with_time_limit(3.0)
+            .with_wall_time_limit(2.0);
+        let err = limits.validate().unwrap_err();
+        assert!(err.contains("below time_limit"), "{err}");
+    }
+
+    #[test]
+    fn resource_limits_diff_lists_changed_fields() {
+        let base = ResourceLimits::default();
+        assert!(base.diff(&base).is_empty());
+
+        let changed = ResourceLimits {
+            extra_time: None,
+            ..base.clone().with_time_limit(5.0).with_memory_limit(1024)
+        };
+        assert_eq!(
+            base.diff(&changed),
+            [
+                ("time_limit", "2".to_owned(), "5".to_owned()),
+                ("memory_limit", "262144".to_owned(), "1024".to_owned()),
+                ("extra_time", "0.5".to_owned(), "unset".to_owned()),
+            ]
+        );
+    }
+
     #[test]
     fn resource_limits_new_equals_default() {
         let new = ResourceLimits::new();
@@ -582,6 +1120,26 @@ mod tests {
         assert!(LimitExceeded::Output.is_exceeded());
     }
 
+    #[test]
+    fn first_above_reports_exceeded_limit() {
+        let ceiling = ResourceLimits {
+            time_limit: None,
+            wall_time_limit: None,
+            memory_limit: Some(524288),
+            stack_limit: None,
+            max_processes: None,
+            max_output: None,
+            max_open_files: None,
+            extra_time: None,
+        };
+        let requested = ResourceLimits::default().with_memory_limit(1048576);
+        assert_eq!(
+            requested.first_above(&ceiling),
+            Some(("memory_limit", 1048576.0, 524288.0))
+        );
+        assert_eq!(ResourceLimits::default().first_above(&ceiling), None);
+    }
+
     // ExecutionResult tests
 
     #[test]
@@ -624,6 +1182,40 @@ mod tests {
         assert!(!result.is_success());
     }
 
+    #[test]
+    fn execution_result_assert_success_ok() {
+        let result = ExecutionResult::default().with_exit_code(0);
+        assert_eq!(result.assert_success(), Ok(()));
+    }
+
+    #[test]
+    fn execution_result_assert_success_segfault() {
+        let result = ExecutionResult {
+            status: ExecutionStatus::Signaled,
+            signal: Some(11),
+            message: Some("Caught fatal signal 11".to_string()),
+            ..Default::default()
+        }
+        .with_stderr(b"Segmentation fault".to_vec());
+
+        let err = result.assert_success().unwrap_err();
+        assert!(err.contains("Signaled"), "{err}");
+        assert!(err.contains("signal=Some(11)"), "{err}");
+        assert!(err.contains("Caught fatal signal 11"), "{err}");
+        assert!(err.contains("Segmentation fault"), "{err}");
+    }
+
+    #[test]
+    fn execution_result_assert_success_truncates_stderr() {
+        let result = ExecutionResult::default()
+            .with_exit_code(1)
+            .with_stderr(vec![b'x'; STDERR_PREVIEW_BYTES + 50]);
+
+        let err = result.assert_success().unwrap_err();
+        assert!(err.contains(&"x".repeat(STDERR_PREVIEW_BYTES)));
+        assert!(!err.contains(&"x".repeat(STDERR_PREVIEW_BYTES + 1)));
+    }
+
     #[test]
     fn execution_result_default() {
         let result = ExecutionResult::default();
@@ -634,11 +1226,175 @@ mod tests {
         assert_eq!(result.memory, 0);
         assert!(result.exit_code.is_none());
         assert!(result.signal.is_none());
+        assert!(!result.killed);
         assert!(result.message.is_none());
         assert!(result.stdout.is_none());
         assert!(result.stderr.is_none());
     }
 
+    #[test]
+    fn produced_output_none() {
+        assert!(!ExecutionResult::default().produced_output());
+    }
+
+    #[test]
+    fn produced_output_empty() {
+        let result = ExecutionResult::default().with_stdout(Vec::new());
+        assert!(!result.produced_output());
+    }
+
+    #[test]
+    fn produced_output_non_empty() {
+        let result = ExecutionResult::default().with_stdout(b"42\n".to_vec());
+        assert!(result.produced_output());
+    }
+
+    #[test]
+    fn execution_result_terminated_by_signal() {
+        let result = ExecutionResult {
+            exit_code: Some(0),
+            signal: Some(9),
+            ..Default::default()
+        };
+        assert!(result.terminated_by_signal());
+
+        let result = ExecutionResult::default().with_status(ExecutionStatus::Signaled);
+        assert!(result.terminated_by_signal());
+
+        let result = ExecutionResult::default().with_exit_code(1);
+        assert!(!result.terminated_by_signal());
+    }
+
+    #[test]
+    fn execution_result_builder_methods() {
+        let result = ExecutionResult::default()
+            .with_stdout(b"hello\n".to_vec())
+            .with_stderr(b"warning\n".to_vec())
+            .with_status(ExecutionStatus::RuntimeError)
+            .with_exit_code(1);
+
+        assert_eq!(result.stdout.as_deref(), Some(&b"hello\n"[..]));
+        assert_eq!(result.stderr.as_deref(), Some(&b"warning\n"[..]));
+        assert_eq!(result.status, ExecutionStatus::RuntimeError);
+        assert_eq!(result.exit_code, Some(1));
+        // Untouched fields keep their defaults
+        assert_eq!(result.limit_exceeded, LimitExceeded::NotExceeded);
+        assert!(result.signal.is_none());
+    }
+
+    #[test]
+    fn execution_result_builder_success() {
+        let result = ExecutionResult::default().with_exit_code(0);
+        assert!(result.is_success());
+    }
+
+    #[test]
+    fn stdout_line_iter_trailing_newline() {
+        let result = ExecutionResult::default().with_stdout(b"first\nsecond\r\nthird\n".to_vec());
+        let lines: Vec<&[u8]> = result.stdout_line_iter().collect();
+        assert_eq!(lines, vec![&b"first"[..], b"second", b"third"]);
+    }
+
+    #[test]
+    fn stdout_line_iter_no_trailing_newline() {
+        let result = ExecutionResult::default().with_stdout(b"a\n\nb".to_vec());
+        let lines: Vec<&[u8]> = result.stdout_line_iter().collect();
+        assert_eq!(lines, vec![&b"a"[..], b"", b"b"]);
+    }
+
+    #[test]
+    fn stdout_line_iter_empty() {
+        assert_eq!(ExecutionResult::default().stdout_line_iter().count(), 0);
+        let result = ExecutionResult::default().with_stdout(Vec::new());
+        assert_eq!(result.stdout_line_iter().count(), 0);
+    }
+
+    #[test]
+    fn apply_memory_report_missing_values() {
+        let mut result = ExecutionResult {
+            max_rss: Some(1000),
+            ..Default::default()
+        };
+        result.apply_memory_report(MemoryReportMode::Preferred);
+        assert_eq!(result.memory, 1000);
+        result.apply_memory_report(MemoryReportMode::CgMem);
+        assert_eq!(result.memory, 0);
+    }
+
+    #[test]
+    fn likely_idle_wall_timeout_compares_cpu_to_wall_time() {
+        let wall_tle = |time| ExecutionResult {
+            status: ExecutionStatus::TimeLimitExceeded,
+            limit_exceeded: LimitExceeded::WallTime,
+            time,
+            wall_time: 5.0,
+            ..Default::default()
+        };
+        assert!(wall_tle(0.01).likely_idle_wall_timeout());
+        assert!(!wall_tle(4.9).likely_idle_wall_timeout());
+
+        let cpu_tle = ExecutionResult {
+            limit_exceeded: LimitExceeded::Time,
+            ..wall_tle(0.01)
+        };
+        assert!(!cpu_tle.likely_idle_wall_timeout());
+    }
+
+    #[test]
+    fn memory_discrepancy_page_cache_dominated() {
+        let result = ExecutionResult {
+            cg_memory: Some(40960),
+            max_rss: Some(2048),
+            ..Default::default()
+        };
+        assert_eq!(result.memory_discrepancy(), Some(20.0));
+    }
+
+    #[test]
+    fn memory_discrepancy_needs_nonzero_rss() {
+        let result = ExecutionResult {
+            cg_memory: Some(40960),
+            max_rss: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(result.memory_discrepancy(), None);
+    }
+
+    #[test]
+    fn is_oom_cgroup_kill() {
+        let result = ExecutionResult {
+            status: ExecutionStatus::Signaled,
+            signal: Some(9),
+            cg_oom_killed: true,
+            message: Some("Caught fatal signal 9".to_string()),
+            ..Default::default()
+        };
+        assert!(result.is_oom());
+    }
+
+    #[test]
+    fn is_oom_signal_with_memory_message() {
+        let result = ExecutionResult {
+            status: ExecutionStatus::Signaled,
+            signal: Some(9),
+            message: Some("Out of memory".to_string()),
+            ..Default::default()
+        };
+        assert!(result.is_oom());
+    }
+
+    #[test]
+    fn is_oom_false_for_wall_time_kill() {
+        let result = ExecutionResult {
+            status: ExecutionStatus::TimeLimitExceeded,
+            signal: Some(9),
+            killed: true,
+            message: Some("Time limit exceeded (wall clock)".to_string()),
+            ..Default::default()
+        };
+        assert!(!result.is_oom());
+    }
+
     // detect_memory_limit tests
 
     #[test]
@@ -698,9 +1454,70 @@ mod tests {
             target: "/dest".to_string(),
             writable: false,
             optional: false,
+            tmp: false,
+            dev: false,
         };
         assert!(!mount.writable);
     }
+
+    fn mount(source: &str, target: &str) -> MountConfig {
+        MountConfig {
+            source: source.to_string(),
+            target: target.to_string(),
+            writable: false,
+            optional: false,
+            tmp: false,
+            dev: false,
+        }
+    }
+
+    #[test]
+    fn mount_to_isolate_arg_read_only() {
+        let arg = mount("/usr/lib", "/lib").to_isolate_arg();
+        assert_eq!(arg.as_deref(), Some("--dir=/lib=/usr/lib"));
+    }
+
+    #[test]
+    fn mount_to_isolate_arg_read_write() {
+        let arg = MountConfig {
+            writable: true,
+            ..mount("/tmp/work", "/work")
+        }
+        .to_isolate_arg();
+        assert_eq!(arg.as_deref(), Some("--dir=/work=/tmp/work:rw"));
+    }
+
+    #[test]
+    fn mount_to_isolate_arg_optional_present() {
+        let source = env!("CARGO_MANIFEST_DIR");
+        let arg = MountConfig {
+            optional: true,
+            ..mount(source, "/data")
+        }
+        .to_isolate_arg();
+        assert_eq!(arg, Some(format!("--dir=/data={source}:maybe")));
+    }
+
+    #[test]
+    fn mount_to_isolate_arg_optional_missing() {
+        let arg = MountConfig {
+            optional: true,
+            ..mount("/nonexistent/silicube/mount", "/data")
+        }
+        .to_isolate_arg();
+        assert!(arg.is_none());
+    }
+
+    #[test]
+    fn mount_to_isolate_arg_tmp() {
+        let arg = MountConfig {
+            tmp: true,
+            dev: false,
+            ..mount("", "/scratch")
+        }
+        .to_isolate_arg();
+        assert_eq!(arg.as_deref(), Some("--dir=/scratch:tmp"));
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,3 @@
+#pragma once
+
+#define GREETING "Hello from a mounted header!"
@@ -0,0 +1,12 @@
+import sys
+
+with open(sys.argv[2]) as f:
+    output = f.read().split()
+with open(sys.argv[3]) as f:
+    expected = f.read().split()
+
+if output == expected:
+    sys.exit(0)
+
+print("tokens differ", file=sys.stderr)
+sys.exit(1)
@@ -0,0 +1,7 @@
+#include <filesystem>
+#include <iostream>
+
+int main() {
+    std::cout << (std::filesystem::is_directory("/interp") ? "present" : "absent") << std::endl;
+    return 0;
+}
@@ -0,0 +1,3 @@
+import os
+
+print("present" if os.path.isdir("/interp") else "absent")
@@ -0,0 +1,4 @@
+import sys
+
+sys.stdout.buffer.write(sys.stdin.buffer.read())
+sys.stdout.buffer.flush()
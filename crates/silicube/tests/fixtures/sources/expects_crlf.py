@@ -0,0 +1,4 @@
+import sys
+
+line = sys.stdin.buffer.readline()
+print("crlf" if line.endswith(b"\r\n") else "lf", flush=True)
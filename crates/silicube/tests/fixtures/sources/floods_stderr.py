@@ -0,0 +1,5 @@
+import sys
+
+sys.stderr.write("x" * 262144)
+sys.stderr.flush()
+print("done")
@@ -0,0 +1,9 @@
+#if 0
+"""
+#endif
+#include <cstdio>
+int main() { std::puts("polyglot"); }
+#if 0
+"""
+print("polyglot")
+#endif
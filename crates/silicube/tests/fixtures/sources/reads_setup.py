@@ -0,0 +1,2 @@
+with open("setup.txt") as f:
+    print(f.read().strip())
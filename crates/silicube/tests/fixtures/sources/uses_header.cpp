@@ -0,0 +1,8 @@
+#include <iostream>
+
+#include "/headers/greeting.h"
+
+int main() {
+    std::cout << GREETING << std::endl;
+    return 0;
+}
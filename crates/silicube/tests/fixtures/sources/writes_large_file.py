@@ -0,0 +1,2 @@
+with open("big.bin", "wb") as f:
+    f.write(b"\0" * 262144)
@@ -0,0 +1,8 @@
+import os
+
+with open("/output/result.txt", "w") as f:
+    f.write("42\n")
+
+os.makedirs("/output/nested", exist_ok=True)
+with open("/output/nested/log.txt", "w") as f:
+    f.write("done\n")
@@ -0,0 +1,48 @@
+use silicube::isolate::IsolateBox;
+use silicube::runner::{CheckerArgs, Runner, Verdict};
+
+use super::{fixture_source, test_config};
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_checker_verdicts() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(80, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let checker = fixture_source("checker_diff.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    sandbox
+        .write_file(&language.source_name(), &checker)
+        .await
+        .expect("Failed to write checker");
+
+    // Matching output is accepted
+    let args = CheckerArgs {
+        input: b"1 2\n",
+        expected: b"3\n",
+        output: b"3\n",
+    };
+    let result = runner
+        .run_checker(&sandbox, language, args, None)
+        .await
+        .expect("Checker failed to run");
+    assert_eq!(result.verdict, Verdict::Accepted);
+
+    // Differing output is a wrong answer
+    let args = CheckerArgs {
+        input: b"1 2\n",
+        expected: b"3\n",
+        output: b"4\n",
+    };
+    let result = runner
+        .run_checker(&sandbox, language, args, None)
+        .await
+        .expect("Checker failed to run");
+    assert_eq!(result.verdict, Verdict::WrongAnswer);
+    assert!(result.message.contains("tokens differ"));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
@@ -1,7 +1,8 @@
 use silicube::isolate::IsolateBox;
 use silicube::runner::{CompileError, Runner};
+use silicube::types::MountConfig;
 
-use super::{fixture_source, test_config};
+use super::{FIXTURES_PATH, fixture_source, test_config};
 
 #[tokio::test]
 #[ignore = "requires root"]
@@ -67,3 +68,143 @@ async fn test_compile_interpreted_language_fails() {
 
     sandbox.cleanup().await.expect("Failed to cleanup");
 }
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_list_files_after_compile() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(23, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("hello.cpp");
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+
+    sandbox
+        .write_file("extra/notes.txt", b"notes")
+        .await
+        .expect("Failed to write file");
+    runner
+        .compile(&sandbox, &source, language, None)
+        .await
+        .expect("Compilation failed");
+
+    let files = sandbox.list_files().await.expect("Failed to list files");
+    assert!(files.contains(&"main.cpp".to_string()));
+    assert!(files.contains(&"main".to_string()));
+    assert!(files.contains(&"extra/notes.txt".to_string()));
+    assert!(!files.contains(&"extra".to_string()));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_compile_with_mounted_headers() {
+    let mut config = test_config();
+    let language = config.languages.get_mut("cpp17").expect("cpp17 not found");
+    language
+        .compile
+        .as_mut()
+        .expect("cpp17 should be compiled")
+        .mounts
+        .push(MountConfig {
+            source: format!("{FIXTURES_PATH}/include"),
+            target: "/headers".to_string(),
+            writable: false,
+            optional: false,
+            tmp: false,
+            dev: false,
+        });
+
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(24, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("uses_header.cpp");
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+
+    let compile_result = runner
+        .compile(&sandbox, &source, language, None)
+        .await
+        .expect("Compilation call failed");
+    assert!(compile_result.is_success(), "{}", compile_result.output);
+
+    let result = runner
+        .run(&sandbox, None, language, None)
+        .await
+        .expect("Execution failed");
+    let stdout = String::from_utf8_lossy(result.stdout.as_deref().unwrap_or_default());
+    assert!(stdout.contains("Hello from a mounted header!"));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_compile_streaming_forwards_output() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(25, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("compile_error.cpp");
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+
+    let mut streamed = Vec::new();
+    let result = runner
+        .compile_streaming(&sandbox, &source, language, None, &mut streamed)
+        .await
+        .expect("Compilation should complete");
+
+    assert!(!result.is_success());
+    // g++ reports only on stderr, so the stream is exactly that output
+    assert!(result.stdout.is_empty());
+    assert_eq!(String::from_utf8_lossy(&streamed), result.stderr);
+    assert_eq!(result.output, result.stderr);
+    assert!(result.stderr.contains("undefined_function"));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_compile_with_source_name_java_class() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(26, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("greeter.java");
+    let mut language = config.get_language("java").expect("java not found").clone();
+
+    // javac rejects a public class Greeter in Main.java
+    let result = runner
+        .compile(&sandbox, &source, &language, None)
+        .await
+        .expect("Compilation call failed");
+    assert!(!result.is_success());
+
+    let result = runner
+        .compile_with_source_name(&sandbox, &source, "Greeter.java", &language, None)
+        .await
+        .expect("Compilation call failed");
+    assert!(result.is_success(), "{}", result.output);
+    assert!(sandbox.file_exists("Greeter.class").await.unwrap());
+
+    // The class name also decides the compiled file and the entry point
+    language.compile.as_mut().unwrap().output_name = "Greeter.class".to_owned();
+    language.run.entry = Some("Greeter".to_owned());
+    let run = runner
+        .run(&sandbox, None, &language, None)
+        .await
+        .expect("Execution failed");
+    run.assert_success().unwrap();
+    assert_eq!(run.stdout.as_deref(), Some(&b"Hello from Greeter\n"[..]));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
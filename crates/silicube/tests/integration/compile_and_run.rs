@@ -1,5 +1,7 @@
-use silicube::isolate::IsolateBox;
-use silicube::runner::{CompileAndRunRequest, Runner};
+use std::sync::Arc;
+
+use silicube::isolate::{BoxPool, IsolateBox};
+use silicube::runner::{CompileAndRunError, CompileAndRunRequest, Runner};
 
 use super::{fixture_source, test_config};
 
@@ -69,3 +71,76 @@ async fn test_compile_and_run_compile_failure() {
 
     sandbox.cleanup().await.expect("Failed to cleanup");
 }
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_compile_in_then_run_in_separate_boxes() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut compile_box = IsolateBox::init(42, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create compile sandbox");
+    let mut run_box = IsolateBox::init(43, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create run sandbox");
+
+    let source = fixture_source("hello.cpp");
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+
+    let (compile_result, run_result) = runner
+        .compile_in_then_run_in(&compile_box, &run_box, &source, None, language, None)
+        .await
+        .expect("Compile and run failed");
+
+    assert!(compile_result.is_success());
+    let run_result = run_result.expect("Expected a run result");
+    assert!(run_result.is_success());
+
+    // The source never reaches the run box, only the binary
+    assert!(!run_box.file_exists("main.cpp").await.unwrap());
+    assert!(run_box.file_exists("main").await.unwrap());
+
+    compile_box.cleanup().await.expect("Failed to cleanup");
+    run_box.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_source_cpp17() {
+    let runner = Runner::new(test_config());
+    let source = fixture_source("hello.cpp");
+
+    let result = runner
+        .run_source(&source, "cpp17", None, None)
+        .await
+        .expect("run_source failed");
+
+    assert!(result.is_success());
+    let stdout = String::from_utf8_lossy(result.stdout.as_deref().unwrap_or_default());
+    assert!(stdout.contains("Hello, World!"));
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_across_languages() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let pool = Arc::new(BoxPool::new(93, 3, config.isolate_binary(), config.cgroup));
+
+    let source = fixture_source("polyglot.cpp.py");
+    let results = runner
+        .run_across(&pool, &source, &["cpp17", "python3", "missing"], None, None)
+        .await;
+
+    let ids: Vec<_> = results.iter().map(|(id, _)| id.as_str()).collect();
+    assert_eq!(ids, ["cpp17", "python3", "missing"]);
+    for (id, result) in &results[..2] {
+        let result = result
+            .as_ref()
+            .unwrap_or_else(|e| panic!("{id} failed: {e}"));
+        assert!(result.is_success(), "{id}: {result:?}");
+        assert_eq!(result.stdout.as_deref(), Some(b"polyglot\n".as_slice()));
+    }
+    assert!(matches!(results[2].1, Err(CompileAndRunError::Config(_))));
+    assert_eq!(pool.available(), 3);
+}
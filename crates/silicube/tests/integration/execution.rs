@@ -1,6 +1,7 @@
-use silicube::isolate::IsolateBox;
-use silicube::runner::Runner;
-use silicube::types::{ExecutionStatus, LimitExceeded, ResourceLimits};
+use silicube::Language;
+use silicube::isolate::{IsolateBox, IsolateError, MetaFile};
+use silicube::runner::{ComparePolicy, CompileError, ExecuteError, Runner, Verdict};
+use silicube::types::{ExecutionStatus, LimitExceeded, MountConfig, ResourceLimits};
 
 use super::{fixture_source, test_config};
 
@@ -107,10 +108,7 @@ async fn test_run_time_limit_exceeded() {
         .expect("Execution call failed");
 
     assert_eq!(result.status, ExecutionStatus::TimeLimitExceeded);
-    assert!(
-        result.limit_exceeded == LimitExceeded::Time
-            || result.limit_exceeded == LimitExceeded::WallTime
-    );
+    assert!(result.is_time_limit());
 
     sandbox.cleanup().await.expect("Failed to cleanup");
 }
@@ -204,3 +202,602 @@ async fn test_run_interpreted_python() {
 
     sandbox.cleanup().await.expect("Failed to cleanup");
 }
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_prepare_interpreted_then_run() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(36, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("hello.py");
+    let language = config.get_language("python3").expect("python3 not found");
+
+    // Interpreted languages have no compile step
+    let compile_result = runner
+        .prepare(&sandbox, &source, language)
+        .await
+        .expect("Prepare failed");
+    assert!(compile_result.is_none());
+    assert!(sandbox.file_exists(&language.source_name()).await.unwrap());
+
+    let result = runner
+        .run(&sandbox, None, language, None)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_with_output_mount() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(37, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("writes_output.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &source, language)
+        .await
+        .expect("Prepare failed");
+
+    let host_dir = std::env::temp_dir().join("silicube-output-mount-37");
+    let _ = std::fs::remove_dir_all(&host_dir);
+    let output_mount = MountConfig {
+        source: host_dir.to_string_lossy().into_owned(),
+        target: "/output".to_string(),
+        writable: true,
+        optional: false,
+        tmp: false,
+        dev: false,
+    };
+
+    let (result, files) = runner
+        .run_with_output_mount(&sandbox, None, language, None, &output_mount)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+
+    assert_eq!(files.len(), 2);
+    assert_eq!(files["result.txt"], b"42\n");
+    assert_eq!(files["nested/log.txt"], b"done\n");
+
+    // A directory left with files in it is refused rather than emptied
+    let err = runner
+        .run_with_output_mount(&sandbox, None, language, None, &output_mount)
+        .await
+        .expect_err("non-empty output dir should be refused");
+    assert!(matches!(err, ExecuteError::NotStarted(_)));
+    assert!(host_dir.join("result.txt").exists());
+
+    std::fs::remove_dir_all(&host_dir).unwrap();
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_deterministic_fixes_hash_seed() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(38, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("hash_seed.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &source, language)
+        .await
+        .expect("Prepare failed");
+
+    let mut outputs = Vec::new();
+    for _ in 0..2 {
+        let result = runner
+            .run_deterministic(&sandbox, None, language, None)
+            .await
+            .expect("Execution failed");
+        assert!(result.is_success());
+        outputs.push(result.stdout.expect("missing stdout"));
+    }
+    assert_eq!(outputs[0], outputs[1]);
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_with_meta_path_archives_meta() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(39, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("hello.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &source, language)
+        .await
+        .expect("Prepare failed");
+
+    let meta_out = std::env::temp_dir().join(format!("silicube-meta-{}.txt", std::process::id()));
+    let result = runner
+        .run_with_meta_path(&sandbox, None, language, None, &meta_out)
+        .await
+        .expect("Execution failed");
+
+    let archived = MetaFile::load(&meta_out).await.expect("Meta not archived");
+    std::fs::remove_file(&meta_out).unwrap();
+    assert_eq!(result.status, ExecutionStatus::Ok);
+    assert_eq!(archived.to_execution_result().exit_code, result.exit_code);
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_archiving_stderr() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(53, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(
+            &sandbox,
+            b"import sys\nprint('out')\nsys.stderr.write('audit trail\\n')\n",
+            language,
+        )
+        .await
+        .expect("Prepare failed");
+
+    let stderr_out =
+        std::env::temp_dir().join(format!("silicube-stderr-{}.txt", std::process::id()));
+    let result = runner
+        .run_archiving_stderr(&sandbox, None, language, None, &stderr_out)
+        .await
+        .expect("Execution failed");
+
+    let archived = std::fs::read(&stderr_out).expect("Stderr not archived");
+    std::fs::remove_file(&stderr_out).unwrap();
+    assert_eq!(result.status, ExecutionStatus::Ok);
+    assert_eq!(archived, b"audit trail\n");
+    assert_eq!(result.stderr.as_deref(), Some(&archived[..]));
+    assert_eq!(result.stdout.as_deref(), Some(&b"out\n"[..]));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_without_compile_reports_missing_binary() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(44, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+    let err = runner
+        .run(&sandbox, None, language, None)
+        .await
+        .expect_err("Run should fail without a binary");
+
+    assert!(
+        matches!(err, ExecuteError::BinaryMissing { ref name } if name == "main"),
+        "unexpected error: {err:?}"
+    );
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_java_entry() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(45, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("hello.java");
+    let language = config.get_language("java").expect("java not found");
+    assert_eq!(language.expand_run_command().last().unwrap(), "Main");
+
+    let compile_result = runner
+        .compile(&sandbox, &source, language, None)
+        .await
+        .expect("Compilation failed");
+    assert!(compile_result.is_success(), "{}", compile_result.output);
+    assert!(sandbox.file_exists("Main.class").await.unwrap());
+
+    let result = runner
+        .run(&sandbox, None, language, None)
+        .await
+        .expect("Execution failed");
+    result.assert_success().unwrap();
+    assert_eq!(result.stdout.as_deref(), Some(&b"Hello, World!\n"[..]));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_and_judge_exact() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(46, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("echo.cpp");
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+
+    let compile_result = runner
+        .compile(&sandbox, &source, language, None)
+        .await
+        .expect("Compilation failed");
+    assert!(compile_result.is_success());
+
+    let input = b"hello judge\n";
+    let (result, verdict) = runner
+        .run_and_judge(
+            &sandbox,
+            Some(input),
+            input,
+            ComparePolicy::Exact,
+            language,
+            None,
+        )
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+    assert_eq!(verdict, Verdict::Accepted);
+
+    let (_, verdict) = runner
+        .run_and_judge(
+            &sandbox,
+            Some(input),
+            b"goodbye judge\n",
+            ComparePolicy::Exact,
+            language,
+            None,
+        )
+        .await
+        .expect("Execution failed");
+    assert_eq!(verdict, Verdict::WrongAnswer);
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_interpreted_mounts_only_for_interpreted_languages() {
+    let mut config = test_config();
+    config.interpreted_mounts.push(MountConfig {
+        source: std::env::temp_dir().to_string_lossy().into_owned(),
+        target: "/interp".to_string(),
+        writable: false,
+        optional: false,
+        tmp: false,
+        dev: false,
+    });
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(47, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let python = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &fixture_source("checks_mount.py"), python)
+        .await
+        .expect("Prepare failed");
+    let result = runner
+        .run(&sandbox, None, python, None)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+    assert_eq!(result.stdout.as_deref(), Some(&b"present\n"[..]));
+
+    let cpp = config.get_language("cpp17").expect("cpp17 not found");
+    let compile_result = runner
+        .compile(&sandbox, &fixture_source("checks_mount.cpp"), cpp, None)
+        .await
+        .expect("Compilation failed");
+    assert!(compile_result.is_success());
+    let result = runner
+        .run(&sandbox, None, cpp, None)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+    assert_eq!(result.stdout.as_deref(), Some(&b"absent\n"[..]));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_prepare_step() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(48, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let mut language = config
+        .get_language("python3")
+        .expect("python3 not found")
+        .clone();
+    language.run.prepare = Some(vec![
+        "/bin/sh".to_string(),
+        "-c".to_string(),
+        "echo prepared > setup.txt".to_string(),
+    ]);
+    runner
+        .prepare(&sandbox, &fixture_source("reads_setup.py"), &language)
+        .await
+        .expect("Prepare failed");
+
+    let result = runner
+        .run(&sandbox, None, &language, None)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+    assert_eq!(result.stdout.as_deref(), Some(&b"prepared\n"[..]));
+
+    // Runs reuse the prepared box without repeating the step
+    sandbox
+        .write_file("setup.txt", b"changed\n")
+        .await
+        .expect("Failed to write file");
+    let result = runner
+        .run(&sandbox, None, &language, None)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+    assert_eq!(result.stdout.as_deref(), Some(&b"changed\n"[..]));
+
+    language.run.prepare = Some(vec![
+        "/bin/sh".to_string(),
+        "-c".to_string(),
+        "echo broken >&2; exit 3".to_string(),
+    ]);
+    let err = runner
+        .prepare(&sandbox, &fixture_source("reads_setup.py"), &language)
+        .await
+        .expect_err("failing prepare step should fail prepare");
+    match err {
+        CompileError::PrepareFailed { output, .. } => assert!(output.contains("broken")),
+        other => panic!("expected PrepareFailed, got {other:?}"),
+    }
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_shell_pipeline() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(49, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let mut language = config
+        .get_language("python3")
+        .expect("python3 not found")
+        .clone();
+    language.run.command =
+        Language::shell_command("sh", "python3 {source} | tr a-z A-Z").expect("sh not found");
+    runner
+        .prepare(&sandbox, &fixture_source("hello.py"), &language)
+        .await
+        .expect("Prepare failed");
+
+    let result = runner
+        .run(&sandbox, None, &language, None)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+    assert_eq!(result.stdout.as_deref(), Some(&b"HELLO, WORLD!\n"[..]));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_streaming_large_input() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(56, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+    let compile_result = runner
+        .compile(&sandbox, &fixture_source("echo.cpp"), language, None)
+        .await
+        .expect("Compilation failed");
+    assert!(compile_result.is_success());
+
+    let input_path = std::env::temp_dir().join("silicube-streaming-input-50");
+    let input: String = (0..100_000).map(|i| format!("line {i}\n")).collect();
+    std::fs::write(&input_path, &input).unwrap();
+    let reader = tokio::fs::File::open(&input_path).await.unwrap();
+
+    let result = runner
+        .run_streaming(&sandbox, reader, language, None)
+        .await
+        .expect("Execution failed");
+    std::fs::remove_file(&input_path).unwrap();
+
+    assert!(result.is_success());
+    assert_eq!(result.stdout.as_deref(), Some(input.as_bytes()));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_reports_disk_usage() {
+    let mut config = test_config();
+    config.report_disk_usage = true;
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(51, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &fixture_source("writes_large_file.py"), language)
+        .await
+        .expect("Prepare failed");
+
+    let result = runner
+        .run(&sandbox, None, language, None)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+
+    let usage = result.disk_usage.expect("disk usage not reported");
+    assert!(usage >= 262144, "usage {usage} below the written file size");
+    assert_eq!(sandbox.disk_usage().await.unwrap(), usage);
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_wrapper_timeout_reaps_box() {
+    let mut config = test_config();
+    config.wrapper_timeout = Some(0.3);
+    config.wrapper_kill_grace = 0.2;
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(52, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &fixture_source("sleep_one_second.py"), language)
+        .await
+        .expect("Prepare failed");
+
+    let err = runner
+        .run(&sandbox, None, language, None)
+        .await
+        .expect_err("run should outlive the wrapper timeout");
+    assert!(
+        matches!(
+            err,
+            ExecuteError::Isolate(IsolateError::WrapperTimeout { id: 52, .. })
+        ),
+        "unexpected error: {err:?}"
+    );
+
+    // Best-effort orphan check: no isolate process for this box is left
+    let leftover = std::process::Command::new("pgrep")
+        .args(["-f", "--", "--box-id=52 "])
+        .status()
+        .expect("Failed to run pgrep");
+    assert!(!leftover.success(), "isolate for box 52 still running");
+
+    // The box was re-initialized and is usable again
+    let runner = Runner::new(test_config());
+    runner
+        .prepare(&sandbox, &fixture_source("hello.py"), language)
+        .await
+        .expect("Prepare failed");
+    let result = runner
+        .run(&sandbox, None, language, None)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_output_limit_keeps_partial_stdout() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(54, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = b"#include <cstdio>\nint main() {\n    for (int i = 0; i < 1000000; i++) std::printf(\"%07d\\n\", i);\n}\n";
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+    let compile_result = runner
+        .compile(&sandbox, source, language, None)
+        .await
+        .expect("Compilation failed");
+    assert!(compile_result.is_success());
+
+    let limits = ResourceLimits::new().with_max_output(1);
+    let result = runner
+        .run(&sandbox, None, language, Some(&limits))
+        .await
+        .expect("Execution failed");
+
+    assert!(result.output_truncated, "unexpected result: {result:?}");
+    assert_eq!(result.limit_exceeded, LimitExceeded::Output);
+    let stdout = result.stdout.expect("partial stdout missing");
+    assert!(stdout.starts_with(b"0000000\n0000001\n"));
+    assert!(stdout.len() <= 1024, "output exceeds the limit");
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_internal_file_prefix_used_by_compile_and_run() {
+    let config = silicube::Config {
+        internal_file_prefix: ".silicube_".to_string(),
+        ..test_config()
+    };
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(55, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("hello.cpp");
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+    let compile_result = runner
+        .compile(&sandbox, &source, language, None)
+        .await
+        .expect("Compilation failed");
+    assert!(compile_result.is_success());
+    let result = runner
+        .run(&sandbox, Some(b"input"), language, None)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+
+    let files = sandbox.list_files().await.expect("Failed to list files");
+    for name in [
+        "meta.txt",
+        "stdin.txt",
+        "stdout.txt",
+        "stderr.txt",
+        "compile_stdout.txt",
+        "compile_stderr.txt",
+    ] {
+        assert!(
+            files.contains(&format!(".silicube_{name}")),
+            "{name}: {files:?}"
+        );
+        assert!(!files.contains(&name.to_string()), "{name}: {files:?}");
+    }
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
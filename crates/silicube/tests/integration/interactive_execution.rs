@@ -1,7 +1,12 @@
+use std::pin::Pin;
 use std::time::Duration;
 
+use futures_core::Stream;
 use silicube::isolate::IsolateBox;
-use silicube::runner::{InteractiveEvent, InteractiveEventStream, Runner};
+use silicube::runner::{
+    InteractiveError, InteractiveEvent, InteractiveEventStream, LineEnding, OutputStream, Runner,
+    SessionRegistry,
+};
 use silicube::types::ResourceLimits;
 
 use super::{fixture_source, test_config};
@@ -896,3 +901,481 @@ async fn test_interactive_interpreted_python() {
 
     sandbox.cleanup().await.expect("Failed to cleanup");
 }
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_interactive_deadline_exceeded() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(73, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("echo.cpp");
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+
+    let compile_result = runner
+        .compile(&sandbox, &source, language, None)
+        .await
+        .expect("Compilation failed");
+    assert!(compile_result.is_success());
+
+    let mut session = runner
+        .run_interactive(&sandbox, language, None)
+        .await
+        .expect("Failed to start interactive session")
+        .with_deadline(Duration::from_millis(500));
+
+    // Within the deadline the session behaves normally
+    session.write_line("hello").await.expect("Write failed");
+    let line = session.read_line().await.expect("Read failed");
+    assert_eq!(line, Some("hello".to_string()));
+
+    // The program waits for more input, so this read runs past the deadline
+    let result = session.read_line().await;
+    assert!(matches!(result, Err(InteractiveError::Deadline)));
+    assert!(session.is_terminated());
+
+    // Every later operation also reports the deadline
+    let result = session.write_line("again").await;
+    assert!(matches!(result, Err(InteractiveError::Deadline)));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_interactive_event_stream_acks() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(74, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("echo.cpp");
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+
+    let compile_result = runner
+        .compile(&sandbox, &source, language, None)
+        .await
+        .expect("Compilation failed");
+    assert!(compile_result.is_success());
+
+    // Short wall time so isolate kills the process for cleanup
+    let limits = ResourceLimits::new()
+        .with_time_limit(2.0)
+        .with_wall_time_limit(2.0);
+
+    let session = runner
+        .run_interactive(&sandbox, language, Some(&limits))
+        .await
+        .expect("Failed to start interactive session");
+
+    let (mut stream, handle) = InteractiveEventStream::new_with_acks(session);
+
+    for line in ["first", "second"] {
+        handle.write_line(line).await.expect("Failed to write");
+
+        // The ack for the write arrives before the echoed output
+        let event = tokio::time::timeout(Duration::from_secs(5), stream.recv())
+            .await
+            .expect("Timeout waiting for ack")
+            .expect("Stream closed unexpectedly");
+        match event {
+            InteractiveEvent::StdinWritten(n) => assert_eq!(n, line.len() + 1),
+            other => panic!("Expected StdinWritten event, got: {:?}", other),
+        }
+
+        let event = tokio::time::timeout(Duration::from_secs(5), stream.recv())
+            .await
+            .expect("Timeout waiting for output")
+            .expect("Stream closed unexpectedly");
+        match event {
+            InteractiveEvent::Stdout(data) => {
+                assert!(String::from_utf8_lossy(&data).contains(line));
+            }
+            other => panic!("Expected Stdout event, got: {:?}", other),
+        }
+    }
+
+    // Drop resources; wait for isolate wall time limit to kill process
+    drop(handle);
+    drop(stream);
+    tokio::time::sleep(Duration::from_secs(4)).await;
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_interactive_wait_with_progress_ticks() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(75, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("sleep_one_second.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &source, language)
+        .await
+        .expect("Prepare failed");
+
+    let session = runner
+        .run_interactive(&sandbox, language, None)
+        .await
+        .expect("Failed to start interactive session");
+
+    let mut ticks = Vec::new();
+    let result = session
+        .wait_with_progress(Duration::from_millis(200), |usage| ticks.push(usage))
+        .await
+        .expect("Failed to wait");
+
+    assert!(result.is_success());
+    assert!(!ticks.is_empty());
+    assert!(ticks.windows(2).all(|w| w[0].wall_time <= w[1].wall_time));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_interactive_write_line_crlf() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(76, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("expects_crlf.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &source, language)
+        .await
+        .expect("Prepare failed");
+
+    let mut session = runner
+        .run_interactive(&sandbox, language, None)
+        .await
+        .expect("Failed to start interactive session");
+    session.set_line_ending(LineEnding::CrLf);
+
+    session.write_line("hello").await.expect("Failed to write");
+    let line = session
+        .read_line()
+        .await
+        .expect("Failed to read line")
+        .expect("Expected a line");
+    assert_eq!(line, "crlf");
+
+    let result = session.wait().await.expect("Failed to wait");
+    assert!(result.is_success());
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_interactive_force_result_after_kill() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(77, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("interactive_echo.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &source, language)
+        .await
+        .expect("Prepare failed");
+
+    let mut session = runner
+        .run_interactive(&sandbox, language, None)
+        .await
+        .expect("Failed to start interactive session");
+    assert!(session.force_result().await.is_none());
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    session.kill().await.expect("Failed to kill");
+
+    let result = session
+        .force_result()
+        .await
+        .expect("Expected a partial result after kill");
+    assert!(result.wall_time > 0.0);
+    assert!(!result.is_success());
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_session_registry_terminate_all() {
+    let config = test_config();
+    let registry = SessionRegistry::new();
+    let runner = Runner::new(config.clone()).with_session_registry(registry.clone());
+    let mut echo_box = IsolateBox::init(78, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+    let mut sleep_box = IsolateBox::init(79, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&echo_box, &fixture_source("interactive_echo.py"), language)
+        .await
+        .expect("Prepare failed");
+    runner
+        .prepare(&sleep_box, &fixture_source("sleep_one_second.py"), language)
+        .await
+        .expect("Prepare failed");
+
+    let mut sessions = Vec::new();
+    for sandbox in [&echo_box, &sleep_box] {
+        let mut session = runner
+            .run_interactive(sandbox, language, None)
+            .await
+            .expect("Failed to start interactive session");
+        sessions.push(tokio::spawn(async move {
+            while session.read_line().await.expect("Failed to read").is_some() {}
+            session
+        }));
+    }
+    assert_eq!(registry.active_sessions(), 2);
+
+    // The echo program exits once stdin closes; the sleeper outlasts the grace period
+    let signalled = registry.terminate_all(Duration::from_millis(200)).await;
+    assert_eq!(signalled, 2);
+
+    let mut sleep_session =
+        tokio::time::timeout(Duration::from_millis(800), sessions.pop().unwrap())
+            .await
+            .expect("Sleeping session was not killed")
+            .unwrap();
+    let echo_session = sessions.pop().unwrap().await.unwrap();
+
+    let result = echo_session.wait().await.expect("Failed to wait");
+    assert!(result.is_success());
+    assert!(sleep_session.is_terminated());
+    let result = sleep_session
+        .force_result()
+        .await
+        .expect("Expected a result after kill");
+    assert!(!result.is_success());
+
+    drop(sleep_session);
+    assert_eq!(registry.active_sessions(), 0);
+
+    echo_box.cleanup().await.expect("Failed to cleanup");
+    sleep_box.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_interactive_write_raw_binary() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(81, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("echo_bytes.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &source, language)
+        .await
+        .expect("Prepare failed");
+
+    let mut session = runner
+        .run_interactive(&sandbox, language, None)
+        .await
+        .expect("Failed to start interactive session");
+
+    let data = b"a\0b\r\n\xff\0";
+    session.write_raw(data).await.expect("Failed to write");
+    session.close_stdin();
+
+    let mut echoed = Vec::new();
+    let mut buf = [0u8; 64];
+    loop {
+        let n = session.read_stdout(&mut buf).await.expect("Failed to read");
+        if n == 0 {
+            break;
+        }
+        echoed.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(echoed, data);
+
+    let result = session.wait().await.expect("Failed to wait");
+    assert!(result.is_success());
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_interactive_exchange() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(82, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("interactive_adder.cpp");
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+    let compile_result = runner
+        .compile(&sandbox, &source, language, None)
+        .await
+        .expect("Compilation failed");
+    assert!(compile_result.is_success());
+
+    let mut session = runner
+        .run_interactive(&sandbox, language, None)
+        .await
+        .expect("Failed to start interactive session");
+    session.set_exchange_timeout(Some(Duration::from_secs(5)));
+
+    for (a, b) in [(1, 2), (10, 20), (0, 0), (-5, 15)] {
+        let response = session
+            .exchange(&format!("{a} {b}"))
+            .await
+            .expect("Exchange failed");
+        assert_eq!(response, (a + b).to_string());
+    }
+
+    // A lone number is not a full request, so no response arrives
+    session.set_exchange_timeout(Some(Duration::from_millis(200)));
+    let err = session.exchange("7").await.unwrap_err();
+    assert!(matches!(err, InteractiveError::Timeout));
+
+    session.close_stdin();
+    let result = session
+        .wait_timeout(Duration::from_secs(5))
+        .await
+        .expect("Wait failed");
+    assert!(result.is_success());
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_interactive_write_after_exit_is_terminated() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(83, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("hello.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &source, language)
+        .await
+        .expect("Prepare failed");
+
+    let mut session = runner
+        .run_interactive(&sandbox, language, None)
+        .await
+        .expect("Failed to start interactive session");
+    // Let the program exit without the session noticing, so the write
+    // itself hits the closed pipe
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let err = session
+        .write(&[b'x'; 1 << 16])
+        .await
+        .expect_err("write to an exited program should fail");
+    assert!(
+        matches!(err, InteractiveError::Terminated),
+        "unexpected error: {err:?}"
+    );
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_stream_collects_output_and_result() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(84, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let source = fixture_source("echo.cpp");
+    let language = config.get_language("cpp17").expect("cpp17 not found");
+    let compile_result = runner
+        .compile(&sandbox, &source, language, None)
+        .await
+        .expect("Compilation failed");
+    assert!(compile_result.is_success());
+
+    // More than one pipe buffer, so input and output have to interleave
+    let input: String = (0..20_000).map(|i| format!("line {i}\n")).collect();
+    let mut stream = runner
+        .run_stream(&sandbox, language, input.as_bytes(), None)
+        .await
+        .expect("Failed to start stream");
+
+    let mut collected = Vec::new();
+    while let Some(chunk) = tokio::time::timeout(Duration::from_secs(10), next_chunk(&mut stream))
+        .await
+        .expect("Timeout waiting for output")
+    {
+        collected.extend_from_slice(&chunk.expect("stream error"));
+    }
+
+    assert_eq!(collected, input.as_bytes());
+    let result = stream.into_result().expect("stream ended without a result");
+    assert!(result.is_success(), "unexpected result: {result:?}");
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_run_stream_drains_stderr() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(85, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    // Writes more stderr than a pipe holds before any stdout
+    let source = fixture_source("floods_stderr.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    runner
+        .prepare(&sandbox, &source, language)
+        .await
+        .expect("Prepare failed");
+
+    let mut stream = runner
+        .run_stream(&sandbox, language, b"", None)
+        .await
+        .expect("Failed to start stream");
+
+    let mut collected = Vec::new();
+    while let Some(chunk) = tokio::time::timeout(Duration::from_secs(10), next_chunk(&mut stream))
+        .await
+        .expect("Timeout waiting for output")
+    {
+        collected.extend_from_slice(&chunk.expect("stream error"));
+    }
+
+    assert_eq!(collected, b"done\n");
+    let result = stream.into_result().expect("stream ended without a result");
+    assert!(result.is_success(), "unexpected result: {result:?}");
+    assert_eq!(result.stderr.as_deref().map(<[u8]>::len), Some(262_144));
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+/// Await the next item of an output stream
+async fn next_chunk(stream: &mut OutputStream) -> Option<<OutputStream as Stream>::Item> {
+    std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
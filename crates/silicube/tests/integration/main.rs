@@ -12,6 +12,7 @@ use std::fs;
 
 use silicube::config::Config;
 
+mod checker;
 mod compilation;
 mod compile_and_run;
 mod config_loading;
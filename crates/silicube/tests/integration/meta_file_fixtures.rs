@@ -1,5 +1,5 @@
 use silicube::isolate::MetaFile;
-use silicube::types::{ExecutionStatus, LimitExceeded};
+use silicube::types::{ExecutionStatus, LimitExceeded, MemoryReportMode};
 
 use super::FIXTURES_PATH;
 
@@ -61,6 +61,39 @@ fn test_meta_runtime_error() {
     assert_eq!(meta.exit_code(), Some(1));
 }
 
+#[test]
+fn test_limit_predicates() {
+    let tle = load_meta_fixture("tle.meta").to_execution_result();
+    assert!(tle.is_time_limit());
+    assert!(!tle.is_wall_time_limit());
+    assert!(!tle.is_memory_limit());
+
+    let wall_tle = load_meta_fixture("wall_tle.meta").to_execution_result();
+    assert!(wall_tle.is_time_limit());
+    assert!(wall_tle.is_wall_time_limit());
+    assert!(wall_tle.likely_idle_wall_timeout());
+
+    let busy_wall_tle = load_meta_fixture("wall_tle_busy.meta").to_execution_result();
+    assert!(busy_wall_tle.is_wall_time_limit());
+    assert!(!busy_wall_tle.likely_idle_wall_timeout());
+    assert!(!tle.likely_idle_wall_timeout());
+
+    let mle = load_meta_fixture("mle.meta").to_execution_result();
+    assert!(mle.is_memory_limit());
+    assert!(!mle.is_time_limit());
+
+    let ole = load_meta_fixture("ole.meta").to_execution_result();
+    assert!(ole.is_output_limit());
+    assert!(!ole.is_memory_limit());
+
+    for name in ["success.meta", "runtime_error.meta", "signal.meta"] {
+        let result = load_meta_fixture(name).to_execution_result();
+        assert!(!result.is_time_limit(), "{name}");
+        assert!(!result.is_memory_limit(), "{name}");
+        assert!(!result.is_output_limit(), "{name}");
+    }
+}
+
 #[test]
 fn test_meta_cgroup_mem_priority() {
     let meta = load_meta_fixture("cgroup_mem.meta");
@@ -78,3 +111,57 @@ fn test_meta_mle_no_message_without_limits() {
     // Parser returns NotExceeded because message is "Caught fatal signal 9"
     assert_eq!(meta.limit_exceeded(), LimitExceeded::NotExceeded);
 }
+
+#[test]
+fn test_memory_discrepancy_from_fixture() {
+    let result = load_meta_fixture("cgroup_mem.meta").to_execution_result();
+    let ratio = result
+        .memory_discrepancy()
+        .expect("both measurements present");
+    assert!((ratio - 524288.0 / 512000.0).abs() < 1e-9);
+
+    // Without cg-mem there is nothing to compare
+    let result = load_meta_fixture("success.meta").to_execution_result();
+    assert_eq!(result.memory_discrepancy(), None);
+}
+
+#[test]
+fn test_meta_cgroup_mem_without_max_rss() {
+    let meta = load_meta_fixture("cgroup_mem_no_rss.meta");
+    assert_eq!(meta.max_rss(), None);
+    assert_eq!(meta.memory(), 40960);
+
+    let mut result = meta.to_execution_result();
+    assert!(result.is_success());
+    assert_eq!(result.memory, 40960);
+    assert_eq!(result.cg_memory, Some(40960));
+    assert_eq!(result.max_rss, None);
+    assert_eq!(result.memory_discrepancy(), None);
+
+    // Preferred falls back to cg-mem; MaxRss stays strict and reports 0
+    result.apply_memory_report(MemoryReportMode::Preferred);
+    assert_eq!(result.memory, 40960);
+    result.apply_memory_report(MemoryReportMode::CgMem);
+    assert_eq!(result.memory, 40960);
+    result.apply_memory_report(MemoryReportMode::MaxRss);
+    assert_eq!(result.memory, 0);
+}
+
+#[test]
+fn test_memory_report_modes_cgroup_mem() {
+    let meta = load_meta_fixture("cgroup_mem.meta");
+    let mut result = meta.to_execution_result();
+
+    result.apply_memory_report(MemoryReportMode::Preferred);
+    assert_eq!(result.memory, 524288);
+
+    result.apply_memory_report(MemoryReportMode::CgMem);
+    assert_eq!(result.memory, 524288);
+
+    result.apply_memory_report(MemoryReportMode::MaxRss);
+    assert_eq!(result.memory, 512000);
+
+    // Detailed fields are preserved regardless of mode
+    assert_eq!(result.cg_memory, Some(524288));
+    assert_eq!(result.max_rss, Some(512000));
+}
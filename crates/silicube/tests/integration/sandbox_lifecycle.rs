@@ -1,6 +1,9 @@
 use silicube::isolate::{BoxPool, IsolateBox};
 
-use super::test_config;
+use silicube::runner::Runner;
+use silicube::types::HashAlgo;
+
+use super::{fixture_source, test_config};
 
 #[tokio::test]
 #[ignore = "requires root"]
@@ -17,6 +20,22 @@ async fn test_box_init_and_cleanup() {
     sandbox.cleanup().await.expect("Failed to cleanup sandbox");
 }
 
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_box_init_info() {
+    let config = test_config();
+    let mut sandbox = IsolateBox::init(5, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    let info = sandbox.init_info();
+    assert_eq!(info.cgroup_enabled, config.cgroup);
+    assert_eq!(info.box_path, sandbox.path());
+    assert!(info.box_path.exists());
+
+    sandbox.cleanup().await.expect("Failed to cleanup sandbox");
+}
+
 #[tokio::test]
 #[ignore = "requires root"]
 async fn test_box_write_and_read_file() {
@@ -93,3 +112,96 @@ async fn test_box_pool() {
         .await
         .expect("Failed to cleanup sandbox2");
 }
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_cgroup_root_alignment() {
+    let config = test_config();
+    if !config.cgroup {
+        return;
+    }
+
+    config
+        .verify_cgroup_alignment()
+        .await
+        .expect("cg_root does not match isolate's configuration");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_box_reset() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let mut sandbox = IsolateBox::init(3, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    sandbox
+        .write_file("stale.txt", b"stale")
+        .await
+        .expect("Failed to write file");
+    sandbox
+        .write_file("nested/dir/stale.txt", b"stale")
+        .await
+        .expect("Failed to write file");
+
+    sandbox.reset().await.expect("Failed to reset box");
+
+    let files = sandbox.list_files().await.expect("Failed to list files");
+    assert!(files.is_empty(), "unexpected files after reset: {files:?}");
+    assert!(!sandbox.file_exists("nested").await.unwrap());
+
+    // The box is still initialized and can run programs
+    let source = fixture_source("hello.py");
+    let language = config.get_language("python3").expect("python3 not found");
+    let result = runner
+        .run_interpreted(&sandbox, &source, None, language, None)
+        .await
+        .expect("Execution failed");
+    assert!(result.is_success());
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_box_file_hash() {
+    let config = test_config();
+    let mut sandbox = IsolateBox::init(4, config.isolate_binary(), config.cgroup)
+        .await
+        .expect("Failed to create sandbox");
+
+    sandbox
+        .write_file("hello.txt", b"hello")
+        .await
+        .expect("Failed to write file");
+
+    let hash = sandbox
+        .file_hash("hello.txt", HashAlgo::Sha256)
+        .await
+        .expect("Failed to hash file");
+    assert_eq!(
+        hash,
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+
+    sandbox.cleanup().await.expect("Failed to cleanup");
+}
+
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_toolchain_report() {
+    let config = test_config();
+    let runner = Runner::new(config.clone());
+    let pool = BoxPool::new(90, 2, config.isolate_binary(), config.cgroup);
+
+    let report = runner.toolchain_report(&pool).await;
+
+    let cpp17 = report.iter().find(|(id, _)| id == "cpp17");
+    let python3 = report.iter().find(|(id, _)| id == "python3");
+    let (_, cpp17) = cpp17.expect("report is missing cpp17");
+    let (_, python3) = python3.expect("report is missing python3");
+    assert!(cpp17.is_ok(), "cpp17 probe failed: {cpp17:?}");
+    assert!(python3.as_ref().is_ok_and(|v| v.contains("Python")));
+    assert_eq!(pool.available(), 2);
+}